@@ -0,0 +1,123 @@
+use clap::Subcommand;
+use solana_escrow::client::{ClientError, Connection, EscrowClient};
+use solana_program::pubkey::Pubkey;
+use solana_sdk::signature::Keypair;
+
+#[derive(Subcommand)]
+pub enum Command {
+    /// Create the escrow registry account
+    Init,
+    /// Put a token account into escrow
+    Deposit {
+        /// Token account to be deposited
+        token_account: Pubkey,
+        /// Mint expected from the counterparty
+        #[arg(long)]
+        token_expected: Pubkey,
+        /// Amount of tokens expected from the counterparty
+        #[arg(long)]
+        amount_expected: u32,
+    },
+    /// Swap own token account with a deposited one
+    Execute {
+        /// Own token account given to the depositor
+        token_account: Pubkey,
+        /// Deposited token account to receive
+        #[arg(long)]
+        deposit_account: Pubkey,
+        /// Mint expected from the deposit
+        #[arg(long)]
+        token_expected: Pubkey,
+        /// Amount of tokens expected from the deposit
+        #[arg(long)]
+        amount_expected: u32,
+        /// Sequence of the escrow to fill, as shown by `list`
+        #[arg(long)]
+        sequence: u64,
+    },
+    /// Take a deposited token account back
+    Reclaim {
+        /// Deposited token account
+        token_account: Pubkey,
+    },
+    /// List active escrows
+    List,
+    /// Show the escrow holding the given token account
+    Show {
+        /// Deposited token account
+        holding_account: Pubkey,
+    },
+}
+
+/// Runs the command on behalf of `keypair` and returns the text to be printed
+pub async fn run<C: Connection>(
+    client: &mut EscrowClient<C>,
+    keypair: &Keypair,
+    command: Command,
+) -> Result<String, ClientError> {
+    let output = match command {
+        Command::Init => {
+            client.init(keypair).await?;
+            format!("Escrow registry initialized: {}", client.registry_address())
+        }
+        Command::Deposit {
+            token_account,
+            token_expected,
+            amount_expected,
+        } => client
+            .deposit(keypair, &token_account, &token_expected, amount_expected)
+            .await?
+            .to_string(),
+        Command::Execute {
+            token_account,
+            deposit_account,
+            token_expected,
+            amount_expected,
+            sequence,
+        } => {
+            let signature = client
+                .execute(
+                    keypair,
+                    &token_account,
+                    &token_expected,
+                    &deposit_account,
+                    amount_expected,
+                    sequence,
+                )
+                .await?;
+            format!(
+                "Swap executed: {}\nAccount {} is now owned by {}",
+                signature,
+                deposit_account,
+                client.token_account_owner(&deposit_account).await?
+            )
+        }
+        Command::Reclaim { token_account } => {
+            let signature = client.reclaim(keypair, &token_account).await?;
+            format!(
+                "Account reclaimed: {}\nAccount {} is now owned by {}",
+                signature,
+                token_account,
+                client.token_account_owner(&token_account).await?
+            )
+        }
+        Command::List => {
+            let entries = client.list_open_escrows().await?;
+            if entries.is_empty() {
+                "No active escrows".to_string()
+            } else {
+                entries
+                    .iter()
+                    .map(ToString::to_string)
+                    .collect::<Vec<_>>()
+                    .join("\n")
+            }
+        }
+        Command::Show { holding_account } => client
+            .find_escrow_by_holding_account(&holding_account)
+            .await?
+            .ok_or(ClientError::EscrowNotFound(holding_account))?
+            .to_string(),
+    };
+    Ok(output)
+}
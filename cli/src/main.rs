@@ -0,0 +1,53 @@
+use clap::Parser;
+use escrow_cli::{run, Command};
+use solana_client::nonblocking::rpc_client::RpcClient;
+use solana_escrow::client::EscrowClient;
+use solana_program::pubkey::Pubkey;
+use solana_sdk::commitment_config::CommitmentConfig;
+use solana_sdk::signature::read_keypair_file;
+
+/// Command-line tool for operating the Solana escrow program
+#[derive(Parser)]
+#[command(name = "escrow-cli", version)]
+struct Cli {
+    /// JSON RPC URL of the cluster
+    #[arg(
+        long,
+        short = 'u',
+        global = true,
+        default_value = "http://127.0.0.1:8899"
+    )]
+    url: String,
+
+    /// Keypair file paying for transactions and acting as escrow participant
+    #[arg(long, short = 'k', global = true)]
+    keypair: Option<String>,
+
+    /// Address of the deployed escrow program
+    #[arg(long, global = true, env = "ESCROW_PROGRAM_ID")]
+    program_id: Pubkey,
+
+    #[command(subcommand)]
+    command: Command,
+}
+
+fn default_keypair_path() -> String {
+    let home = std::env::var("HOME").unwrap_or_else(|_| ".".to_string());
+    format!("{}/.config/solana/id.json", home)
+}
+
+#[tokio::main]
+async fn main() -> Result<(), Box<dyn std::error::Error>> {
+    let cli = Cli::parse();
+    let keypair_path = cli.keypair.unwrap_or_else(default_keypair_path);
+    let keypair = read_keypair_file(&keypair_path)
+        .map_err(|e| format!("Unable to read keypair {}: {}", keypair_path, e))?;
+
+    let mut client = EscrowClient::new(
+        RpcClient::new_with_commitment(cli.url, CommitmentConfig::confirmed()),
+        cli.program_id,
+    );
+    println!("{}", run(&mut client, &keypair, cli.command).await?);
+
+    Ok(())
+}
@@ -0,0 +1,126 @@
+use escrow_cli::{run, Command};
+use solana_escrow::client::EscrowClient;
+use solana_escrow::process_instruction;
+use solana_escrow::test_fixtures::*;
+use solana_program::pubkey::Pubkey;
+use solana_program_test::{processor, tokio, ProgramTest};
+use solana_sdk::signature::{Keypair, Signer};
+use solana_sdk::transaction::Transaction;
+
+#[tokio::test]
+async fn cli_commands_test() {
+    let mint_authority_kp = Keypair::new();
+    let program_id = Pubkey::new_unique();
+
+    let test_program =
+        ProgramTest::new("solana_escrow", program_id, processor!(process_instruction));
+    let (mut banks_client, payer, recent_blockhash) = test_program.start().await;
+
+    let alice = UserAccounts::prepare(
+        &mut banks_client,
+        &recent_blockhash,
+        &payer,
+        &mint_authority_kp,
+        10,
+    )
+    .await;
+    let bob = UserAccounts::prepare(
+        &mut banks_client,
+        &recent_blockhash,
+        &payer,
+        &mint_authority_kp,
+        5,
+    )
+    .await;
+
+    // Wallets pay for their own transactions
+    for wallet in [&alice.wallet_account, &bob.wallet_account] {
+        let fund_tx = Transaction::new_signed_with_payer(
+            &[solana_sdk::system_instruction::transfer(
+                &payer.pubkey(),
+                &wallet.pubkey(),
+                1_000_000_000,
+            )],
+            Some(&payer.pubkey()),
+            &[&payer],
+            recent_blockhash,
+        );
+        banks_client.process_transaction(fund_tx).await.unwrap();
+    }
+
+    let mut client = EscrowClient::new(banks_client.clone(), program_id);
+
+    let output = run(&mut client, &payer, Command::Init).await.unwrap();
+    assert!(output.contains(&client.registry_address().to_string()));
+    let output = run(&mut client, &payer, Command::List).await.unwrap();
+    assert_eq!(output, "No active escrows");
+
+    let output = run(
+        &mut client,
+        &alice.wallet_account,
+        Command::Deposit {
+            token_account: alice.token_account.pubkey(),
+            token_expected: bob.mint_account.pubkey(),
+            amount_expected: 5,
+        },
+    )
+    .await
+    .expect("Unable to make a deposit");
+    assert!(output.contains(&alice.wallet_account.pubkey().to_string()));
+    assert!(output.contains("Amount expected: 5"));
+
+    let output = run(
+        &mut client,
+        &payer,
+        Command::Show {
+            holding_account: alice.token_account.pubkey(),
+        },
+    )
+    .await
+    .unwrap();
+    assert!(output.contains(&bob.mint_account.pubkey().to_string()));
+
+    let output = run(
+        &mut client,
+        &bob.wallet_account,
+        Command::Execute {
+            token_account: bob.token_account.pubkey(),
+            deposit_account: alice.token_account.pubkey(),
+            token_expected: alice.mint_account.pubkey(),
+            amount_expected: 10,
+            sequence: 1,
+        },
+    )
+    .await
+    .expect("Unable to make an escrow execution");
+    assert!(output.ends_with(&format!("owned by {}", bob.wallet_account.pubkey())));
+
+    let output = run(&mut client, &payer, Command::List).await.unwrap();
+    assert_eq!(output, "No active escrows");
+
+    // Alice offers the account she got and takes it back
+    run(
+        &mut client,
+        &alice.wallet_account,
+        Command::Deposit {
+            token_account: bob.token_account.pubkey(),
+            token_expected: alice.mint_account.pubkey(),
+            amount_expected: 10,
+        },
+    )
+    .await
+    .expect("Unable to make a deposit");
+    let output = run(
+        &mut client,
+        &alice.wallet_account,
+        Command::Reclaim {
+            token_account: bob.token_account.pubkey(),
+        },
+    )
+    .await
+    .expect("Unable to make reclaim");
+    assert!(output.ends_with(&format!("owned by {}", alice.wallet_account.pubkey())));
+
+    let output = run(&mut client, &payer, Command::List).await.unwrap();
+    assert_eq!(output, "No active escrows");
+}
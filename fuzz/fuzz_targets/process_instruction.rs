@@ -0,0 +1,98 @@
+#![no_main]
+
+use arbitrary::Arbitrary;
+use libfuzzer_sys::fuzz_target;
+use solana_escrow::{find_registry_address, process_instruction};
+use solana_program::account_info::AccountInfo;
+use solana_program::program_option::COption;
+use solana_program::program_pack::Pack;
+use solana_program::pubkey::Pubkey;
+use spl_token::state::{Account, AccountState};
+
+const ACCOUNTS: usize = 6;
+
+#[derive(Arbitrary, Debug)]
+struct Input {
+    instruction_data: Vec<u8>,
+    registry_data: Vec<u8>,
+    signers: [bool; ACCOUNTS],
+    writable: [bool; ACCOUNTS],
+    lamports: [u64; ACCOUNTS],
+    token_amount: u64,
+    deposit_in_escrow: bool,
+    account_count: u8,
+}
+
+fuzz_target!(|input: Input| {
+    let program_id = Pubkey::new_from_array([7; 32]);
+    let (registry, _) = find_registry_address(&program_id);
+    let owner = Pubkey::new_from_array([1; 32]);
+    let mint = Pubkey::new_from_array([2; 32]);
+    let keys = [
+        registry,
+        owner,
+        Pubkey::new_from_array([3; 32]),
+        spl_token::id(),
+        mint,
+        Pubkey::new_from_array([4; 32]),
+    ];
+    let owners = [
+        program_id,
+        Pubkey::default(),
+        spl_token::id(),
+        Pubkey::default(),
+        spl_token::id(),
+        spl_token::id(),
+    ];
+
+    let token_account = Account {
+        mint,
+        owner: if input.deposit_in_escrow {
+            registry
+        } else {
+            owner
+        },
+        amount: input.token_amount,
+        state: AccountState::Initialized,
+        delegate: COption::None,
+        is_native: COption::None,
+        delegated_amount: 0,
+        close_authority: COption::None,
+    };
+    let mut token_data = vec![0u8; Account::LEN];
+    token_account.pack_into_slice(&mut token_data);
+
+    let mut datas = [
+        input.registry_data,
+        vec![],
+        token_data.clone(),
+        vec![],
+        vec![],
+        token_data,
+    ];
+    let mut lamports = input.lamports;
+    let accounts: Vec<AccountInfo> = datas
+        .iter_mut()
+        .zip(lamports.iter_mut())
+        .enumerate()
+        .map(|(i, (data, lamports))| {
+            AccountInfo::new(
+                &keys[i],
+                input.signers[i],
+                input.writable[i],
+                lamports,
+                data,
+                &owners[i],
+                false,
+                0,
+            )
+        })
+        .collect();
+
+    let account_count = input.account_count as usize % (ACCOUNTS + 1);
+    let _ = process_instruction(
+        &program_id,
+        &accounts[..account_count],
+        &input.instruction_data,
+    );
+});
@@ -0,0 +1,1034 @@
+//! Async client for the escrow program working over `RpcClient` or `BanksClient`
+
+use crate::error::EscrowError;
+use crate::instruction;
+use crate::state::{
+    find_bid_vault_address, find_registry_address, Alternative, AuctionTerms, BidRecord,
+    BundleAsset, BundleTerms, DelegatedTerms, Escrow, EscrowCollection, EscrowKind, Milestone,
+    MilestoneTerms, ReclaimDelay, SignedOffer, SwapTerms,
+};
+use async_trait::async_trait;
+use solana_banks_client::{BanksClient, BanksClientError};
+use solana_client::nonblocking::rpc_client::RpcClient;
+use solana_program::hash::Hash;
+use solana_program::instruction::Instruction;
+use solana_program::program_error::ProgramError;
+use solana_program::program_pack::Pack;
+use solana_program::pubkey::Pubkey;
+use solana_sdk::signature::{Keypair, Signature, Signer};
+use solana_sdk::transaction::{Transaction, TransactionError};
+use std::fmt;
+use thiserror::Error;
+
+#[derive(Error, Debug)]
+pub enum ClientError {
+    #[error("Escrow program error: {0}")]
+    Escrow(EscrowError),
+
+    #[error("Escrow registry {0} is not initialized")]
+    RegistryNotInitialized(Pubkey),
+
+    #[error("Account {0} does not exist")]
+    AccountNotFound(Pubkey),
+
+    #[error("No active escrow holds account {0}")]
+    EscrowNotFound(Pubkey),
+
+    #[error("Transaction failed: {0}")]
+    Transaction(TransactionError),
+
+    #[error(transparent)]
+    Program(#[from] ProgramError),
+
+    #[error(transparent)]
+    Rpc(Box<solana_client::client_error::ClientError>),
+
+    #[error(transparent)]
+    Banks(Box<BanksClientError>),
+}
+
+impl From<TransactionError> for ClientError {
+    fn from(error: TransactionError) -> Self {
+        match decode_transaction_error(&error) {
+            Some(escrow_error) => ClientError::Escrow(escrow_error),
+            None => ClientError::Transaction(error),
+        }
+    }
+}
+
+impl From<solana_client::client_error::ClientError> for ClientError {
+    fn from(error: solana_client::client_error::ClientError) -> Self {
+        match error.get_transaction_error() {
+            Some(transaction_error) => transaction_error.into(),
+            None => ClientError::Rpc(Box::new(error)),
+        }
+    }
+}
+
+impl From<BanksClientError> for ClientError {
+    fn from(error: BanksClientError) -> Self {
+        match error {
+            BanksClientError::TransactionError(transaction_error)
+            | BanksClientError::SimulationError {
+                err: transaction_error,
+                ..
+            } => transaction_error.into(),
+            error => ClientError::Banks(Box::new(error)),
+        }
+    }
+}
+
+/// Recovers the escrow error of a failed transaction, its `Display` is the user-facing message
+pub fn decode_transaction_error(error: &TransactionError) -> Option<EscrowError> {
+    match error {
+        TransactionError::InstructionError(_, instruction_error) => {
+            EscrowError::from_instruction_error(instruction_error)
+        }
+        _ => None,
+    }
+}
+
+/// Cluster access needed by the escrow client
+#[async_trait]
+pub trait Connection: Send {
+    async fn get_account_data(&mut self, address: &Pubkey) -> Result<Option<Vec<u8>>, ClientError>;
+
+    async fn get_latest_blockhash(&mut self) -> Result<Hash, ClientError>;
+
+    async fn send_and_confirm_transaction(
+        &mut self,
+        transaction: &Transaction,
+    ) -> Result<Signature, ClientError>;
+}
+
+#[async_trait]
+impl Connection for RpcClient {
+    async fn get_account_data(&mut self, address: &Pubkey) -> Result<Option<Vec<u8>>, ClientError> {
+        let account = self
+            .get_account_with_commitment(address, self.commitment())
+            .await?
+            .value;
+        Ok(account.map(|account| account.data))
+    }
+
+    async fn get_latest_blockhash(&mut self) -> Result<Hash, ClientError> {
+        Ok(RpcClient::get_latest_blockhash(self).await?)
+    }
+
+    async fn send_and_confirm_transaction(
+        &mut self,
+        transaction: &Transaction,
+    ) -> Result<Signature, ClientError> {
+        Ok(RpcClient::send_and_confirm_transaction(self, transaction).await?)
+    }
+}
+
+#[async_trait]
+impl Connection for BanksClient {
+    async fn get_account_data(&mut self, address: &Pubkey) -> Result<Option<Vec<u8>>, ClientError> {
+        Ok(self
+            .get_account(*address)
+            .await?
+            .map(|account| account.data))
+    }
+
+    async fn get_latest_blockhash(&mut self) -> Result<Hash, ClientError> {
+        Ok(BanksClient::get_latest_blockhash(self).await?)
+    }
+
+    async fn send_and_confirm_transaction(
+        &mut self,
+        transaction: &Transaction,
+    ) -> Result<Signature, ClientError> {
+        self.process_transaction(transaction.clone()).await?;
+        Ok(transaction.signatures[0])
+    }
+}
+
+/// Escrow record along with its slot index in the registry
+#[derive(Clone, Copy)]
+pub struct EscrowEntry {
+    pub slot: usize,
+    pub escrow: Escrow,
+}
+
+impl fmt::Display for EscrowEntry {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        writeln!(f, "Escrow slot #{}", self.slot)?;
+        writeln!(f, "  Sequence:        {}", self.escrow.sequence)?;
+        writeln!(f, "  Holding account: {}", self.escrow.holding_account)?;
+        writeln!(f, "  Owner:           {}", self.escrow.owner_account)?;
+        writeln!(f, "  Mint expected:   {}", self.escrow.token_expected)?;
+        write!(f, "  Amount expected: {}", self.escrow.amount_expected)?;
+        if self.escrow.kind == EscrowKind::Swap {
+            let terms: SwapTerms = self.escrow.terms().map_err(|_| fmt::Error)?;
+            for alternative in terms.alternatives() {
+                write!(
+                    f,
+                    "\n  Or:              {} of {}",
+                    alternative.amount, alternative.mint
+                )?;
+            }
+        }
+        Ok(())
+    }
+}
+
+/// Typed access to the escrow program.
+/// Every transaction is paid by the keypair performing the operation.
+pub struct EscrowClient<C: Connection> {
+    pub connection: C,
+    pub program_id: Pubkey,
+}
+
+impl<C: Connection> EscrowClient<C> {
+    pub fn new(connection: C, program_id: Pubkey) -> Self {
+        Self {
+            connection,
+            program_id,
+        }
+    }
+
+    pub fn registry_address(&self) -> Pubkey {
+        find_registry_address(&self.program_id).0
+    }
+
+    async fn send(
+        &mut self,
+        instruction: Instruction,
+        signer: &Keypair,
+    ) -> Result<Signature, ClientError> {
+        self.send_all(&[instruction], signer).await
+    }
+
+    async fn send_all(
+        &mut self,
+        instructions: &[Instruction],
+        signer: &Keypair,
+    ) -> Result<Signature, ClientError> {
+        let blockhash = self.connection.get_latest_blockhash().await?;
+        let transaction = Transaction::new_signed_with_payer(
+            instructions,
+            Some(&signer.pubkey()),
+            &[signer],
+            blockhash,
+        );
+        self.connection
+            .send_and_confirm_transaction(&transaction)
+            .await
+    }
+
+    /// Fetches and decodes the whole escrow registry
+    pub async fn registry(&mut self) -> Result<EscrowCollection, ClientError> {
+        let registry = self.registry_address();
+        let data = self
+            .connection
+            .get_account_data(&registry)
+            .await?
+            .ok_or(ClientError::RegistryNotInitialized(registry))?;
+        Ok(EscrowCollection::unpack_from_slice(&data)?)
+    }
+
+    /// Returns all active escrows of the registry
+    pub async fn list_open_escrows(&mut self) -> Result<Vec<EscrowEntry>, ClientError> {
+        let registry = self.registry().await?;
+        Ok(registry
+            .items
+            .iter()
+            .enumerate()
+            .filter(|(_, escrow)| escrow.active)
+            .map(|(slot, escrow)| EscrowEntry {
+                slot,
+                escrow: *escrow,
+            })
+            .collect())
+    }
+
+    /// Looks up an active escrow by its holding account
+    pub async fn find_escrow_by_holding_account(
+        &mut self,
+        holding_account: &Pubkey,
+    ) -> Result<Option<EscrowEntry>, ClientError> {
+        Ok(self
+            .list_open_escrows()
+            .await?
+            .into_iter()
+            .find(|entry| entry.escrow.holding_account == *holding_account))
+    }
+
+    /// Returns the current owner of a token account
+    pub async fn token_account_owner(
+        &mut self,
+        token_account: &Pubkey,
+    ) -> Result<Pubkey, ClientError> {
+        let data = self
+            .connection
+            .get_account_data(token_account)
+            .await?
+            .ok_or(ClientError::AccountNotFound(*token_account))?;
+        Ok(spl_token::state::Account::unpack(&data)?.owner)
+    }
+
+    /// Creates the escrow registry paid by `payer`
+    pub async fn init(&mut self, payer: &Keypair) -> Result<Signature, ClientError> {
+        let ix = instruction::init(&self.program_id, &payer.pubkey());
+        self.send(ix, payer).await
+    }
+
+    /// Deposits `token_account` of `owner` and returns the escrow record created for it
+    pub async fn deposit(
+        &mut self,
+        owner: &Keypair,
+        token_account: &Pubkey,
+        token_expected: &Pubkey,
+        amount_expected: u32,
+    ) -> Result<EscrowEntry, ClientError> {
+        let ix = instruction::deposit(
+            &self.program_id,
+            &owner.pubkey(),
+            token_account,
+            token_expected,
+            amount_expected,
+        );
+        self.send(ix, owner).await?;
+        self.find_escrow_by_holding_account(token_account)
+            .await?
+            .ok_or(ClientError::EscrowNotFound(*token_account))
+    }
+
+    /// Deposits `token_account` accepting `amount_expected` of `token_expected` or any of
+    /// `alternatives` in exchange
+    pub async fn deposit_alternatives(
+        &mut self,
+        owner: &Keypair,
+        token_account: &Pubkey,
+        token_expected: &Pubkey,
+        amount_expected: u32,
+        alternatives: &[Alternative],
+    ) -> Result<EscrowEntry, ClientError> {
+        let ix = instruction::deposit_alternatives(
+            &self.program_id,
+            &owner.pubkey(),
+            token_account,
+            token_expected,
+            amount_expected,
+            alternatives,
+        );
+        self.send(ix, owner).await?;
+        self.find_escrow_by_holding_account(token_account)
+            .await?
+            .ok_or(ClientError::EscrowNotFound(*token_account))
+    }
+
+    /// Delegates `amount` tokens of `token_account` to the registry and offers them for
+    /// `amount_expected` tokens of `token_expected` paid into `proceeds_account`
+    #[allow(clippy::too_many_arguments)]
+    pub async fn deposit_delegated(
+        &mut self,
+        owner: &Keypair,
+        token_account: &Pubkey,
+        amount: u64,
+        token_expected: &Pubkey,
+        proceeds_account: &Pubkey,
+        amount_expected: u32,
+    ) -> Result<EscrowEntry, ClientError> {
+        let instructions = [
+            instruction::delegate(&self.program_id, &owner.pubkey(), token_account, amount)?,
+            instruction::deposit_delegated(
+                &self.program_id,
+                &owner.pubkey(),
+                token_account,
+                token_expected,
+                proceeds_account,
+                amount_expected,
+            ),
+        ];
+        self.send_all(&instructions, owner).await?;
+        self.find_escrow_by_holding_account(token_account)
+            .await?
+            .ok_or(ClientError::EscrowNotFound(*token_account))
+    }
+
+    /// Pays for the delegated deposit of `holding_account` from `taker_source`, receiving the
+    /// offered tokens into `taker_destination`
+    pub async fn execute_delegated(
+        &mut self,
+        taker: &Keypair,
+        taker_source: &Pubkey,
+        taker_destination: &Pubkey,
+        holding_account: &Pubkey,
+        amount_expected: u64,
+        sequence: u64,
+    ) -> Result<Signature, ClientError> {
+        let entry = self
+            .find_escrow_by_holding_account(holding_account)
+            .await?
+            .ok_or(ClientError::EscrowNotFound(*holding_account))?;
+        let terms: DelegatedTerms = entry.escrow.terms()?;
+        let ix = instruction::execute_delegated(
+            &self.program_id,
+            &taker.pubkey(),
+            taker_source,
+            taker_destination,
+            holding_account,
+            &terms.proceeds_account,
+            amount_expected,
+            sequence,
+        );
+        self.send(ix, taker).await
+    }
+
+    /// Swaps `token_account` of `owner` with the deposited one
+    pub async fn execute(
+        &mut self,
+        owner: &Keypair,
+        token_account: &Pubkey,
+        token_expected: &Pubkey,
+        deposit_account: &Pubkey,
+        amount_expected: u32,
+        sequence: u64,
+    ) -> Result<Signature, ClientError> {
+        let ix = instruction::execute(
+            &self.program_id,
+            &owner.pubkey(),
+            token_account,
+            token_expected,
+            deposit_account,
+            amount_expected,
+            sequence,
+        );
+        self.send(ix, owner).await
+    }
+
+    /// Fills several deposits at once, failing as a whole if any leg fails
+    pub async fn execute_batch(
+        &mut self,
+        owner: &Keypair,
+        legs: &[instruction::ExecuteLeg],
+    ) -> Result<Signature, ClientError> {
+        let ix = instruction::execute_batch(&self.program_id, &owner.pubkey(), legs);
+        self.send(ix, owner).await
+    }
+
+    /// Sets the match reward and the reclaim delay, signed by the registry admin
+    pub async fn configure(
+        &mut self,
+        admin: &Keypair,
+        match_reward_lamports: u64,
+        reclaim_delay: ReclaimDelay,
+    ) -> Result<Signature, ClientError> {
+        let ix = instruction::configure(
+            &self.program_id,
+            &admin.pubkey(),
+            match_reward_lamports,
+            reclaim_delay,
+        );
+        self.send(ix, admin).await
+    }
+
+    /// Swaps two deposits whose terms satisfy each other, `matcher` gets the match reward
+    pub async fn match_escrows(
+        &mut self,
+        matcher: &Keypair,
+        first_deposit: &Pubkey,
+        second_deposit: &Pubkey,
+    ) -> Result<Signature, ClientError> {
+        let ix = instruction::match_escrows(
+            &self.program_id,
+            &matcher.pubkey(),
+            first_deposit,
+            second_deposit,
+        );
+        self.send(ix, matcher).await
+    }
+
+    /// Settles a ring of three or more `deposits`, `executor` gets the match reward
+    pub async fn execute_ring(
+        &mut self,
+        executor: &Keypair,
+        deposits: &[Pubkey],
+    ) -> Result<Signature, ClientError> {
+        let ix = instruction::execute_ring(&self.program_id, &executor.pubkey(), deposits);
+        self.send(ix, executor).await
+    }
+
+    /// Changes the terms of the escrow holding `token_account`
+    pub async fn amend(
+        &mut self,
+        owner: &Keypair,
+        token_account: &Pubkey,
+        token_expected: &Pubkey,
+        amount_expected: u32,
+    ) -> Result<Signature, ClientError> {
+        let ix = instruction::amend(
+            &self.program_id,
+            &owner.pubkey(),
+            token_account,
+            token_expected,
+            amount_expected,
+        );
+        self.send(ix, owner).await
+    }
+
+    /// Adds `amount` tokens from `source` to the escrowed `token_account`
+    pub async fn top_up(
+        &mut self,
+        owner: &Keypair,
+        token_account: &Pubkey,
+        source: &Pubkey,
+        amount: u64,
+        amount_expected: u32,
+    ) -> Result<Signature, ClientError> {
+        let ix = instruction::top_up(
+            &self.program_id,
+            &owner.pubkey(),
+            token_account,
+            source,
+            amount,
+            amount_expected,
+        );
+        self.send(ix, owner).await
+    }
+
+    /// Takes `amount` tokens of the escrowed `token_account` back to `destination`
+    pub async fn withdraw(
+        &mut self,
+        owner: &Keypair,
+        token_account: &Pubkey,
+        destination: &Pubkey,
+        amount: u64,
+        amount_expected: u32,
+    ) -> Result<Signature, ClientError> {
+        let ix = instruction::withdraw(
+            &self.program_id,
+            &owner.pubkey(),
+            token_account,
+            destination,
+            amount,
+            amount_expected,
+        );
+        self.send(ix, owner).await
+    }
+
+    /// Puts `token_account` of `owner` up for auction with bids paid in `bid_mint`
+    #[allow(clippy::too_many_arguments)]
+    pub async fn create_auction(
+        &mut self,
+        owner: &Keypair,
+        token_account: &Pubkey,
+        bid_mint: &Pubkey,
+        kind: EscrowKind,
+        reserve_price: u64,
+        start_price: u64,
+        end_timestamp: i64,
+    ) -> Result<EscrowEntry, ClientError> {
+        let ix = instruction::create_auction(
+            &self.program_id,
+            &owner.pubkey(),
+            token_account,
+            bid_mint,
+            kind,
+            reserve_price,
+            start_price,
+            end_timestamp,
+        );
+        self.send(ix, owner).await?;
+        self.find_escrow_by_holding_account(token_account)
+            .await?
+            .ok_or(ClientError::EscrowNotFound(*token_account))
+    }
+
+    /// Bids `bid_account` of `bidder` on the auctioned `token_account`,
+    /// the outbid account of an English auction is looked up in the registry
+    pub async fn bid(
+        &mut self,
+        bidder: &Keypair,
+        bid_account: &Pubkey,
+        token_account: &Pubkey,
+    ) -> Result<Signature, ClientError> {
+        let previous_bid_account = self.highest_bid_account(token_account).await?;
+        let ix = instruction::bid(
+            &self.program_id,
+            &bidder.pubkey(),
+            bid_account,
+            token_account,
+            previous_bid_account.as_ref(),
+        );
+        self.send(ix, bidder).await
+    }
+
+    /// Closes the ended English auction of `token_account`, `payer` only pays the fee
+    pub async fn settle_auction(
+        &mut self,
+        payer: &Keypair,
+        token_account: &Pubkey,
+    ) -> Result<Signature, ClientError> {
+        let bid_account = self.highest_bid_account(token_account).await?;
+        let ix = instruction::settle_auction(&self.program_id, token_account, bid_account.as_ref());
+        self.send(ix, payer).await
+    }
+
+    async fn highest_bid_account(
+        &mut self,
+        token_account: &Pubkey,
+    ) -> Result<Option<Pubkey>, ClientError> {
+        let escrow = self
+            .find_escrow_by_holding_account(token_account)
+            .await?
+            .ok_or(ClientError::EscrowNotFound(*token_account))?
+            .escrow;
+        if escrow.kind != EscrowKind::EnglishAuction {
+            return Ok(None);
+        }
+        let terms: AuctionTerms = escrow.terms()?;
+        Ok((terms.highest_bid > 0).then_some(terms.bid_account))
+    }
+
+    /// Puts `token_account` of `owner` up for quotes in `bid_mint`
+    pub async fn create_rfq(
+        &mut self,
+        owner: &Keypair,
+        token_account: &Pubkey,
+        bid_mint: &Pubkey,
+    ) -> Result<EscrowEntry, ClientError> {
+        let ix =
+            instruction::create_rfq(&self.program_id, &owner.pubkey(), token_account, bid_mint);
+        self.send(ix, owner).await?;
+        self.find_escrow_by_holding_account(token_account)
+            .await?
+            .ok_or(ClientError::EscrowNotFound(*token_account))
+    }
+
+    /// Locks `bid_account` of `bidder` as a quote for the RFQ of `token_account`
+    pub async fn submit_bid(
+        &mut self,
+        bidder: &Keypair,
+        bid_account: &Pubkey,
+        token_account: &Pubkey,
+    ) -> Result<Signature, ClientError> {
+        let ix = instruction::submit_bid(
+            &self.program_id,
+            &bidder.pubkey(),
+            bid_account,
+            token_account,
+        );
+        self.send(ix, bidder).await
+    }
+
+    /// Reads the bid of `bidder` on the RFQ of `token_account`
+    pub async fn bid_record(
+        &mut self,
+        token_account: &Pubkey,
+        bidder: &Pubkey,
+    ) -> Result<BidRecord, ClientError> {
+        let (vault, _) = find_bid_vault_address(&self.program_id, token_account, bidder);
+        let data = self
+            .connection
+            .get_account_data(&vault)
+            .await?
+            .ok_or(ClientError::AccountNotFound(vault))?;
+        Ok(BidRecord::unpack_from_slice(&data)?)
+    }
+
+    /// Settles the RFQ of `token_account` with the bid of `bidder`
+    pub async fn accept_bid(
+        &mut self,
+        owner: &Keypair,
+        token_account: &Pubkey,
+        bidder: &Pubkey,
+    ) -> Result<Signature, ClientError> {
+        let record = self.bid_record(token_account, bidder).await?;
+        let ix = instruction::accept_bid(
+            &self.program_id,
+            &owner.pubkey(),
+            token_account,
+            bidder,
+            &record.bid_account,
+        );
+        self.send(ix, owner).await
+    }
+
+    /// Takes the bid of `bidder` back once the RFQ of `token_account` is closed
+    pub async fn withdraw_bid(
+        &mut self,
+        bidder: &Keypair,
+        token_account: &Pubkey,
+    ) -> Result<Signature, ClientError> {
+        let record = self.bid_record(token_account, &bidder.pubkey()).await?;
+        let ix = instruction::withdraw_bid(
+            &self.program_id,
+            &bidder.pubkey(),
+            token_account,
+            &record.bid_account,
+        );
+        self.send(ix, bidder).await
+    }
+
+    /// Deposits `token_account` of `buyer` as a payment to `seller` mediated by `arbiter`
+    pub async fn create_arbitrated(
+        &mut self,
+        buyer: &Keypair,
+        token_account: &Pubkey,
+        seller: &Pubkey,
+        arbiter: &Pubkey,
+    ) -> Result<EscrowEntry, ClientError> {
+        let data = self
+            .connection
+            .get_account_data(token_account)
+            .await?
+            .ok_or(ClientError::AccountNotFound(*token_account))?;
+        let mint = spl_token::state::Account::unpack(&data)?.mint;
+        let ix = instruction::create_arbitrated(
+            &self.program_id,
+            &buyer.pubkey(),
+            token_account,
+            &mint,
+            seller,
+            arbiter,
+        );
+        self.send(ix, buyer).await?;
+        self.find_escrow_by_holding_account(token_account)
+            .await?
+            .ok_or(ClientError::EscrowNotFound(*token_account))
+    }
+
+    /// Passes the arbitrated payment to the seller
+    pub async fn release(
+        &mut self,
+        buyer: &Keypair,
+        token_account: &Pubkey,
+    ) -> Result<Signature, ClientError> {
+        let ix = instruction::release(&self.program_id, &buyer.pubkey(), token_account);
+        self.send(ix, buyer).await
+    }
+
+    /// Returns the arbitrated payment to the buyer
+    pub async fn refund(
+        &mut self,
+        seller: &Keypair,
+        token_account: &Pubkey,
+    ) -> Result<Signature, ClientError> {
+        let ix = instruction::refund(&self.program_id, &seller.pubkey(), token_account);
+        self.send(ix, seller).await
+    }
+
+    /// Opens a dispute as the buyer or the seller
+    pub async fn dispute(
+        &mut self,
+        party: &Keypair,
+        token_account: &Pubkey,
+    ) -> Result<Signature, ClientError> {
+        let ix = instruction::dispute(&self.program_id, &party.pubkey(), token_account);
+        self.send(ix, party).await
+    }
+
+    /// Splits a disputed payment, `seller_bps` of it go to `seller_token_account`
+    pub async fn resolve(
+        &mut self,
+        arbiter: &Keypair,
+        token_account: &Pubkey,
+        seller_token_account: &Pubkey,
+        seller_bps: u16,
+    ) -> Result<Signature, ClientError> {
+        let ix = instruction::resolve(
+            &self.program_id,
+            &arbiter.pubkey(),
+            token_account,
+            seller_token_account,
+            seller_bps,
+        );
+        self.send(ix, arbiter).await
+    }
+
+    /// Deposits `token_account` of `owner` for `recipient`, released once `threshold` of
+    /// `approvers` approve
+    pub async fn create_multisig(
+        &mut self,
+        owner: &Keypair,
+        token_account: &Pubkey,
+        recipient: &Pubkey,
+        threshold: u8,
+        approvers: &[Pubkey],
+    ) -> Result<EscrowEntry, ClientError> {
+        let data = self
+            .connection
+            .get_account_data(token_account)
+            .await?
+            .ok_or(ClientError::AccountNotFound(*token_account))?;
+        let mint = spl_token::state::Account::unpack(&data)?.mint;
+        let ix = instruction::create_multisig(
+            &self.program_id,
+            &owner.pubkey(),
+            token_account,
+            &mint,
+            recipient,
+            threshold,
+            approvers,
+        );
+        self.send(ix, owner).await?;
+        self.find_escrow_by_holding_account(token_account)
+            .await?
+            .ok_or(ClientError::EscrowNotFound(*token_account))
+    }
+
+    /// Approves the release of a multisig escrow, or its refund if `refund`
+    pub async fn approve(
+        &mut self,
+        approver: &Keypair,
+        token_account: &Pubkey,
+        refund: bool,
+    ) -> Result<Signature, ClientError> {
+        let ix = instruction::approve(&self.program_id, &approver.pubkey(), token_account, refund);
+        self.send(ix, approver).await
+    }
+
+    /// Withdraws the approval of `approver`
+    pub async fn revoke(
+        &mut self,
+        approver: &Keypair,
+        token_account: &Pubkey,
+    ) -> Result<Signature, ClientError> {
+        let ix = instruction::revoke(&self.program_id, &approver.pubkey(), token_account);
+        self.send(ix, approver).await
+    }
+
+    /// Deposits `token_account` of `owner` to be paid to `payee` in `milestones`
+    pub async fn create_milestones(
+        &mut self,
+        owner: &Keypair,
+        token_account: &Pubkey,
+        payee: &Pubkey,
+        arbiter: Option<&Pubkey>,
+        deadline: Option<i64>,
+        milestones: &[Milestone],
+    ) -> Result<EscrowEntry, ClientError> {
+        let data = self
+            .connection
+            .get_account_data(token_account)
+            .await?
+            .ok_or(ClientError::AccountNotFound(*token_account))?;
+        let mint = spl_token::state::Account::unpack(&data)?.mint;
+        let ix = instruction::create_milestones(
+            &self.program_id,
+            &owner.pubkey(),
+            token_account,
+            &mint,
+            payee,
+            arbiter,
+            deadline,
+            milestones,
+        );
+        self.send(ix, owner).await?;
+        self.find_escrow_by_holding_account(token_account)
+            .await?
+            .ok_or(ClientError::EscrowNotFound(*token_account))
+    }
+
+    /// Pays the next milestone of the escrow of `token_account` to `payee_token_account`
+    pub async fn release_milestone(
+        &mut self,
+        releaser: &Keypair,
+        token_account: &Pubkey,
+        payee_token_account: &Pubkey,
+    ) -> Result<Signature, ClientError> {
+        let escrow = self
+            .find_escrow_by_holding_account(token_account)
+            .await?
+            .ok_or(ClientError::EscrowNotFound(*token_account))?
+            .escrow;
+        let terms: MilestoneTerms = escrow.terms()?;
+        let ix = instruction::release_milestone(
+            &self.program_id,
+            &releaser.pubkey(),
+            token_account,
+            payee_token_account,
+            terms.released,
+        );
+        self.send(ix, releaser).await
+    }
+
+    /// Takes the unreleased milestones back after the deadline
+    pub async fn refund_milestones(
+        &mut self,
+        owner: &Keypair,
+        token_account: &Pubkey,
+    ) -> Result<Signature, ClientError> {
+        let ix = instruction::refund_milestones(&self.program_id, &owner.pubkey(), token_account);
+        self.send(ix, owner).await
+    }
+
+    /// Deposits `token_account` of `owner` for `recipient` under a SHA-256 `hashlock`
+    pub async fn create_htlc(
+        &mut self,
+        owner: &Keypair,
+        token_account: &Pubkey,
+        recipient: &Pubkey,
+        hashlock: [u8; 32],
+        timeout: i64,
+    ) -> Result<EscrowEntry, ClientError> {
+        let data = self
+            .connection
+            .get_account_data(token_account)
+            .await?
+            .ok_or(ClientError::AccountNotFound(*token_account))?;
+        let mint = spl_token::state::Account::unpack(&data)?.mint;
+        let ix = instruction::create_htlc(
+            &self.program_id,
+            &owner.pubkey(),
+            token_account,
+            &mint,
+            recipient,
+            hashlock,
+            timeout,
+        );
+        self.send(ix, owner).await?;
+        self.find_escrow_by_holding_account(token_account)
+            .await?
+            .ok_or(ClientError::EscrowNotFound(*token_account))
+    }
+
+    /// Claims the HTLC of `token_account` for its recipient, `payer` only pays the fee
+    pub async fn claim(
+        &mut self,
+        payer: &Keypair,
+        token_account: &Pubkey,
+        preimage: &[u8],
+    ) -> Result<Signature, ClientError> {
+        let ix = instruction::claim(&self.program_id, token_account, preimage);
+        self.send(ix, payer).await
+    }
+
+    /// Takes the unclaimed HTLC back after the timeout
+    pub async fn refund_htlc(
+        &mut self,
+        owner: &Keypair,
+        token_account: &Pubkey,
+    ) -> Result<Signature, ClientError> {
+        let ix = instruction::refund_htlc(&self.program_id, &owner.pubkey(), token_account);
+        self.send(ix, owner).await
+    }
+
+    /// Lets the registry transfer up to `amount` tokens of `token_account` for signed offers
+    pub async fn delegate(
+        &mut self,
+        owner: &Keypair,
+        token_account: &Pubkey,
+        amount: u64,
+    ) -> Result<Signature, ClientError> {
+        let ix = instruction::delegate(&self.program_id, &owner.pubkey(), token_account, amount)?;
+        self.send(ix, owner).await
+    }
+
+    /// Signs `offer` off-chain as its maker, the signature is what takers fill the offer with
+    pub fn sign_offer(&self, maker: &Keypair, offer: &SignedOffer) -> [u8; 64] {
+        maker.sign_message(&offer.message(&self.program_id)).into()
+    }
+
+    /// Fills `offer` of another maker given its `signature`
+    #[allow(clippy::too_many_arguments)]
+    pub async fn fill_offer(
+        &mut self,
+        taker: &Keypair,
+        taker_source: &Pubkey,
+        taker_destination: &Pubkey,
+        maker_source: &Pubkey,
+        maker_destination: &Pubkey,
+        offer: &SignedOffer,
+        signature: &[u8; 64],
+    ) -> Result<Signature, ClientError> {
+        let instructions = [
+            instruction::verify_offer(&self.program_id, offer, signature),
+            instruction::fill_offer(
+                &self.program_id,
+                &taker.pubkey(),
+                taker_source,
+                taker_destination,
+                maker_source,
+                maker_destination,
+                offer,
+            ),
+        ];
+        self.send_all(&instructions, taker).await
+    }
+
+    /// Invalidates the offers of `maker` signed with `nonce`
+    pub async fn cancel_offer(
+        &mut self,
+        maker: &Keypair,
+        nonce: u64,
+    ) -> Result<Signature, ClientError> {
+        let ix = instruction::cancel_offer(&self.program_id, &maker.pubkey(), nonce);
+        self.send(ix, maker).await
+    }
+
+    /// Deposits all of `holdings` of `owner` as one bundle asking for `wanted` in return
+    pub async fn create_bundle(
+        &mut self,
+        owner: &Keypair,
+        holdings: &[Pubkey],
+        wanted: &[BundleAsset],
+    ) -> Result<EscrowEntry, ClientError> {
+        let ix = instruction::create_bundle(&self.program_id, &owner.pubkey(), holdings, wanted);
+        self.send(ix, owner).await?;
+        self.find_escrow_by_holding_account(&holdings[0])
+            .await?
+            .ok_or(ClientError::EscrowNotFound(holdings[0]))
+    }
+
+    /// Lists all holding accounts of the bundle recorded by `holding_account`
+    async fn bundle_holdings(
+        &mut self,
+        holding_account: &Pubkey,
+    ) -> Result<Vec<Pubkey>, ClientError> {
+        let entry = self
+            .find_escrow_by_holding_account(holding_account)
+            .await?
+            .ok_or(ClientError::EscrowNotFound(*holding_account))?;
+        let terms: BundleTerms = entry.escrow.terms()?;
+        Ok([*holding_account]
+            .into_iter()
+            .chain(terms.extra_holdings().iter().copied())
+            .collect())
+    }
+
+    /// Swaps the bundle recorded by `holding_account` for `taker_accounts`, one per wanted asset.
+    /// `sequence` is the one of the bundle the taker saw.
+    pub async fn execute_bundle(
+        &mut self,
+        taker: &Keypair,
+        holding_account: &Pubkey,
+        taker_accounts: &[Pubkey],
+        sequence: u64,
+    ) -> Result<Signature, ClientError> {
+        let holdings = self.bundle_holdings(holding_account).await?;
+        let ix = instruction::execute_bundle(
+            &self.program_id,
+            &taker.pubkey(),
+            &holdings,
+            taker_accounts,
+            sequence,
+        );
+        self.send(ix, taker).await
+    }
+
+    /// Takes all accounts of the bundle recorded by `holding_account` back to `owner`
+    pub async fn reclaim_bundle(
+        &mut self,
+        owner: &Keypair,
+        holding_account: &Pubkey,
+    ) -> Result<Signature, ClientError> {
+        let holdings = self.bundle_holdings(holding_account).await?;
+        let ix = instruction::reclaim_bundle(&self.program_id, &owner.pubkey(), &holdings);
+        self.send(ix, owner).await
+    }
+
+    /// Takes a deposited `token_account` back to `owner`
+    pub async fn reclaim(
+        &mut self,
+        owner: &Keypair,
+        token_account: &Pubkey,
+    ) -> Result<Signature, ClientError> {
+        let ix = instruction::reclaim(&self.program_id, &owner.pubkey(), token_account);
+        self.send(ix, owner).await
+    }
+}
@@ -0,0 +1,131 @@
+//! Helpers for other programs invoking the escrow program.
+//! Owners may be program derived addresses, their seeds are passed as `signer_seeds`.
+
+use crate::instruction;
+use crate::instruction_parser::EscrowQuery;
+use crate::state::Escrow;
+use solana_program::account_info::AccountInfo;
+use solana_program::entrypoint::ProgramResult;
+use solana_program::program::{get_return_data, invoke, invoke_signed};
+use solana_program::program_error::ProgramError;
+
+/// Accounts of the `Deposit` instruction
+pub struct Deposit<'a, 'info> {
+    pub escrow_program: &'a AccountInfo<'info>,
+    pub registry: &'a AccountInfo<'info>,
+    pub owner: &'a AccountInfo<'info>,
+    pub token_account: &'a AccountInfo<'info>,
+    pub token_program: &'a AccountInfo<'info>,
+    pub token_expected: &'a AccountInfo<'info>,
+}
+
+/// Accounts of the `Execute` instruction
+pub struct Execute<'a, 'info> {
+    pub escrow_program: &'a AccountInfo<'info>,
+    pub registry: &'a AccountInfo<'info>,
+    pub owner: &'a AccountInfo<'info>,
+    pub token_account: &'a AccountInfo<'info>,
+    pub token_program: &'a AccountInfo<'info>,
+    pub token_expected: &'a AccountInfo<'info>,
+    pub deposit_account: &'a AccountInfo<'info>,
+}
+
+/// Accounts of the `Reclaim` instruction
+pub struct Reclaim<'a, 'info> {
+    pub escrow_program: &'a AccountInfo<'info>,
+    pub registry: &'a AccountInfo<'info>,
+    pub owner: &'a AccountInfo<'info>,
+    pub token_account: &'a AccountInfo<'info>,
+    pub token_program: &'a AccountInfo<'info>,
+}
+
+pub fn deposit(
+    accounts: Deposit,
+    amount_expected: u32,
+    signer_seeds: &[&[&[u8]]],
+) -> ProgramResult {
+    let ix = instruction::deposit(
+        accounts.escrow_program.key,
+        accounts.owner.key,
+        accounts.token_account.key,
+        accounts.token_expected.key,
+        amount_expected,
+    );
+    invoke_signed(
+        &ix,
+        &[
+            accounts.registry.clone(),
+            accounts.owner.clone(),
+            accounts.token_account.clone(),
+            accounts.token_program.clone(),
+            accounts.token_expected.clone(),
+            accounts.escrow_program.clone(),
+        ],
+        signer_seeds,
+    )
+}
+
+pub fn execute(
+    accounts: Execute,
+    amount_expected: u32,
+    sequence: u64,
+    signer_seeds: &[&[&[u8]]],
+) -> ProgramResult {
+    let ix = instruction::execute(
+        accounts.escrow_program.key,
+        accounts.owner.key,
+        accounts.token_account.key,
+        accounts.token_expected.key,
+        accounts.deposit_account.key,
+        amount_expected,
+        sequence,
+    );
+    invoke_signed(
+        &ix,
+        &[
+            accounts.registry.clone(),
+            accounts.owner.clone(),
+            accounts.token_account.clone(),
+            accounts.token_program.clone(),
+            accounts.token_expected.clone(),
+            accounts.deposit_account.clone(),
+            accounts.escrow_program.clone(),
+        ],
+        signer_seeds,
+    )
+}
+
+pub fn reclaim(accounts: Reclaim, signer_seeds: &[&[&[u8]]]) -> ProgramResult {
+    let ix = instruction::reclaim(
+        accounts.escrow_program.key,
+        accounts.owner.key,
+        accounts.token_account.key,
+    );
+    invoke_signed(
+        &ix,
+        &[
+            accounts.registry.clone(),
+            accounts.owner.clone(),
+            accounts.token_account.clone(),
+            accounts.token_program.clone(),
+            accounts.escrow_program.clone(),
+        ],
+        signer_seeds,
+    )
+}
+
+/// Looks active escrows up without reading the registry layout, an empty result means no escrow matches
+pub fn query<'info>(
+    escrow_program: &AccountInfo<'info>,
+    registry: &AccountInfo<'info>,
+    query: EscrowQuery,
+) -> Result<Vec<Escrow>, ProgramError> {
+    let ix = instruction::query(escrow_program.key, query);
+    invoke(&ix, &[registry.clone(), escrow_program.clone()])?;
+    match get_return_data() {
+        Some((program_id, data)) if program_id == *escrow_program.key => {
+            instruction::unpack_query_result(&data)
+        }
+        _ => Ok(vec![]),
+    }
+}
@@ -1,33 +1,102 @@
+use num_derive::FromPrimitive;
+use num_traits::FromPrimitive;
+use solana_program::decode_error::DecodeError;
+use solana_program::instruction::InstructionError;
 use solana_program::msg;
-use solana_program::program_error::ProgramError;
+use solana_program::program_error::{PrintProgramError, ProgramError};
 use thiserror::Error;
 
-#[derive(Error, Debug, Copy, Clone)]
+/// Discriminants are the custom program error codes seen by clients and must stay stable
+#[derive(Error, Debug, Copy, Clone, PartialEq, Eq, FromPrimitive)]
 pub enum EscrowError {
     /// Invalid instruction
     #[error("Invalid Instruction")]
-    InvalidInstruction,
+    InvalidInstruction = 0,
 
     #[error("PDA already exists")]
-    PdaExists,
+    PdaExists = 1,
 
     #[error("Escrow program is not initialized")]
-    NotInitialized,
+    NotInitialized = 2,
 
     #[error("Executor expects another amount of token deposited")]
-    DepositTokenAmtMismatch,
+    DepositTokenAmtMismatch = 3,
 
     #[error("Executor expects another token mint from deposit")]
-    DepositTokenMintMismatch,
+    DepositTokenMintMismatch = 4,
 
     #[error("Depositor expected another amount of token passed by the executor")]
-    ExecutorTokenAmtMismatch,
+    ExecutorTokenAmtMismatch = 5,
 
     #[error("Depositor expected another token mint passed by the executor")]
-    ExecutorTokenMintMismatch,
+    ExecutorTokenMintMismatch = 6,
 
     #[error("No available escrow accounts")]
-    NoAvailableEscrowAccounts,
+    NoAvailableEscrowAccounts = 7,
+
+    #[error("Escrow was replaced, its sequence differs from the expected one")]
+    SequenceMismatch = 8,
+
+    #[error("Escrow can't be reclaimed before the reclaim delay passes")]
+    ReclaimTooEarly = 9,
+
+    #[error("Instruction doesn't apply to this kind of escrow")]
+    WrongEscrowKind = 10,
+
+    #[error("Auction has already ended")]
+    AuctionEnded = 11,
+
+    #[error("Auction hasn't ended yet")]
+    AuctionNotEnded = 12,
+
+    #[error("Bid is below the current price")]
+    BidTooLow = 13,
+
+    #[error("Auction with bids can't be reclaimed")]
+    AuctionHasBids = 14,
+
+    #[error("Bid can't be withdrawn while its request for quote is open")]
+    BidLocked = 15,
+
+    #[error("Only a disputed escrow can be resolved")]
+    NotDisputed = 16,
+
+    #[error("Escrow can't be refunded before its deadline")]
+    DeadlineNotReached = 17,
+
+    #[error("Preimage doesn't match the hashlock")]
+    HashlockMismatch = 18,
+
+    #[error("Hash time-locked escrow has timed out")]
+    HtlcExpired = 19,
+
+    #[error("Signed offer has expired")]
+    OfferExpired = 20,
+
+    #[error("Offer nonce has already been used")]
+    NonceAlreadyUsed = 21,
+
+    #[error("Offer isn't signed by its maker")]
+    InvalidOfferSignature = 22,
+
+    #[error("Registry isn't delegated enough tokens of the account")]
+    InsufficientDelegation = 23,
+
+    #[error("Holding account doesn't have the offered tokens anymore")]
+    DelegatedTokensSpent = 24,
+
+    #[error("Escrow registry has an old layout, it has to be migrated with MigrateRegistry")]
+    OutdatedRegistryLayout = 25,
+}
+
+impl EscrowError {
+    /// Recovers the escrow error from a failed instruction, e.g. `Custom(3)` of a transaction error
+    pub fn from_instruction_error(error: &InstructionError) -> Option<Self> {
+        match error {
+            InstructionError::Custom(code) => Self::try_from(*code).ok(),
+            _ => None,
+        }
+    }
 }
 
 pub fn throw_and_log(error: EscrowError) -> ProgramError {
@@ -40,3 +109,26 @@ impl From<EscrowError> for ProgramError {
         ProgramError::Custom(value as u32)
     }
 }
+
+impl TryFrom<u32> for EscrowError {
+    type Error = ProgramError;
+
+    fn try_from(code: u32) -> Result<Self, Self::Error> {
+        Self::from_u32(code).ok_or(ProgramError::Custom(code))
+    }
+}
+
+impl<T> DecodeError<T> for EscrowError {
+    fn type_of() -> &'static str {
+        "EscrowError"
+    }
+}
+
+impl PrintProgramError for EscrowError {
+    fn print<E>(&self)
+    where
+        E: 'static + std::error::Error + DecodeError<E> + PrintProgramError + FromPrimitive,
+    {
+        msg!("{}", self);
+    }
+}
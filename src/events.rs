@@ -0,0 +1,16 @@
+//! Events logged with `sol_log_data`, shown as `Program data:` lines of the transaction logs.
+//! The first field is the event name, the second one is the packed `Escrow` record.
+
+use crate::state::Escrow;
+use solana_program::log::sol_log_data;
+use solana_program::program_pack::Pack;
+
+pub const ESCROW_AMENDED: &[u8] = b"escrow_amended";
+pub const ESCROW_TOPPED_UP: &[u8] = b"escrow_topped_up";
+pub const ESCROW_WITHDRAWN: &[u8] = b"escrow_withdrawn";
+
+pub fn emit(name: &[u8], escrow: &Escrow) {
+    let mut data = [0u8; Escrow::LEN];
+    escrow.pack_into_slice(&mut data);
+    sol_log_data(&[name, &data]);
+}
@@ -0,0 +1,1006 @@
+use crate::instruction_parser::{EscrowInstruction, EscrowQuery};
+use crate::state::{
+    find_bid_vault_address, find_nonce_address, find_registry_address, Alternative, BundleAsset,
+    Escrow, EscrowKind, Milestone, ReclaimDelay, SignedOffer, SEED,
+};
+use solana_program::ed25519_program;
+use solana_program::instruction::{AccountMeta, Instruction};
+use solana_program::program_error::ProgramError;
+use solana_program::program_pack::Pack;
+use solana_program::pubkey::Pubkey;
+use solana_program::system_program;
+use solana_program::sysvar;
+
+/// Creates the escrow registry PDA, the rent is paid by `payer`
+pub fn init(program_id: &Pubkey, payer: &Pubkey) -> Instruction {
+    let (registry, bump_seed) = find_registry_address(program_id);
+    Instruction {
+        program_id: *program_id,
+        accounts: vec![
+            AccountMeta::new(*payer, true),
+            AccountMeta::new(registry, false),
+            AccountMeta::new_readonly(system_program::id(), false),
+        ],
+        data: EscrowInstruction::Init {
+            seed: SEED,
+            bump_seed,
+        }
+        .pack(),
+    }
+}
+
+/// Puts `token_account` into escrow asking for `amount_expected` tokens of `token_expected` mint
+pub fn deposit(
+    program_id: &Pubkey,
+    owner: &Pubkey,
+    token_account: &Pubkey,
+    token_expected: &Pubkey,
+    amount_expected: u32,
+) -> Instruction {
+    let (registry, _) = find_registry_address(program_id);
+    Instruction {
+        program_id: *program_id,
+        accounts: vec![
+            AccountMeta::new(registry, false),
+            AccountMeta::new_readonly(*owner, true),
+            AccountMeta::new(*token_account, false),
+            AccountMeta::new_readonly(spl_token::id(), false),
+            AccountMeta::new_readonly(*token_expected, false),
+        ],
+        data: EscrowInstruction::Deposit { amount_expected }.pack(),
+    }
+}
+
+/// Same as `deposit`, also accepting any of `alternatives` instead of `amount_expected` tokens
+/// of `token_expected` mint
+pub fn deposit_alternatives(
+    program_id: &Pubkey,
+    owner: &Pubkey,
+    token_account: &Pubkey,
+    token_expected: &Pubkey,
+    amount_expected: u32,
+    alternatives: &[Alternative],
+) -> Instruction {
+    let (registry, _) = find_registry_address(program_id);
+    Instruction {
+        program_id: *program_id,
+        accounts: vec![
+            AccountMeta::new(registry, false),
+            AccountMeta::new_readonly(*owner, true),
+            AccountMeta::new(*token_account, false),
+            AccountMeta::new_readonly(spl_token::id(), false),
+            AccountMeta::new_readonly(*token_expected, false),
+        ],
+        data: EscrowInstruction::DepositAlternatives {
+            amount_expected,
+            alternatives: alternatives.to_vec(),
+        }
+        .pack(),
+    }
+}
+
+/// Offers the tokens of `token_account` delegated to the registry, asking for `amount_expected`
+/// tokens of `token_expected` mint paid into `proceeds_account`. `owner` keeps the account.
+pub fn deposit_delegated(
+    program_id: &Pubkey,
+    owner: &Pubkey,
+    token_account: &Pubkey,
+    token_expected: &Pubkey,
+    proceeds_account: &Pubkey,
+    amount_expected: u32,
+) -> Instruction {
+    let (registry, _) = find_registry_address(program_id);
+    Instruction {
+        program_id: *program_id,
+        accounts: vec![
+            AccountMeta::new(registry, false),
+            AccountMeta::new_readonly(*owner, true),
+            AccountMeta::new_readonly(*token_account, false),
+            AccountMeta::new_readonly(*token_expected, false),
+            AccountMeta::new_readonly(*proceeds_account, false),
+        ],
+        data: EscrowInstruction::DepositDelegated { amount_expected }.pack(),
+    }
+}
+
+/// Pays for the delegated deposit of `holding_account` from `taker_source` into
+/// `proceeds_account`, the delegated tokens go to `taker_destination`
+#[allow(clippy::too_many_arguments)]
+pub fn execute_delegated(
+    program_id: &Pubkey,
+    taker: &Pubkey,
+    taker_source: &Pubkey,
+    taker_destination: &Pubkey,
+    holding_account: &Pubkey,
+    proceeds_account: &Pubkey,
+    amount_expected: u64,
+    sequence: u64,
+) -> Instruction {
+    let (registry, _) = find_registry_address(program_id);
+    Instruction {
+        program_id: *program_id,
+        accounts: vec![
+            AccountMeta::new(registry, false),
+            AccountMeta::new_readonly(*taker, true),
+            AccountMeta::new(*taker_source, false),
+            AccountMeta::new(*taker_destination, false),
+            AccountMeta::new(*holding_account, false),
+            AccountMeta::new(*proceeds_account, false),
+            AccountMeta::new_readonly(spl_token::id(), false),
+        ],
+        data: EscrowInstruction::ExecuteDelegated {
+            amount_expected,
+            sequence,
+        }
+        .pack(),
+    }
+}
+
+/// Swaps `token_account` with the escrowed `deposit_account`.
+/// `token_expected` and `amount_expected` are the executor requirements for the deposit,
+/// `sequence` is the one of the escrow seen by the executor.
+pub fn execute(
+    program_id: &Pubkey,
+    owner: &Pubkey,
+    token_account: &Pubkey,
+    token_expected: &Pubkey,
+    deposit_account: &Pubkey,
+    amount_expected: u32,
+    sequence: u64,
+) -> Instruction {
+    let (registry, _) = find_registry_address(program_id);
+    Instruction {
+        program_id: *program_id,
+        accounts: vec![
+            AccountMeta::new(registry, false),
+            AccountMeta::new_readonly(*owner, true),
+            AccountMeta::new(*token_account, false),
+            AccountMeta::new_readonly(spl_token::id(), false),
+            AccountMeta::new_readonly(*token_expected, false),
+            AccountMeta::new(*deposit_account, false),
+        ],
+        data: EscrowInstruction::Execute {
+            amount_expected,
+            sequence,
+        }
+        .pack(),
+    }
+}
+
+/// Taker side of a single swap within `execute_batch`
+pub struct ExecuteLeg {
+    pub token_account: Pubkey,
+    pub token_expected: Pubkey,
+    pub deposit_account: Pubkey,
+    pub amount_expected: u32,
+    pub sequence: u64,
+}
+
+/// Swaps several taker token accounts with escrowed deposits in one instruction.
+/// Either all the legs are executed or none of them.
+pub fn execute_batch(program_id: &Pubkey, owner: &Pubkey, legs: &[ExecuteLeg]) -> Instruction {
+    let (registry, _) = find_registry_address(program_id);
+    let mut accounts = vec![
+        AccountMeta::new(registry, false),
+        AccountMeta::new_readonly(*owner, true),
+        AccountMeta::new_readonly(spl_token::id(), false),
+    ];
+    for leg in legs {
+        accounts.push(AccountMeta::new(leg.token_account, false));
+        accounts.push(AccountMeta::new_readonly(leg.token_expected, false));
+        accounts.push(AccountMeta::new(leg.deposit_account, false));
+    }
+    Instruction {
+        program_id: *program_id,
+        accounts,
+        data: EscrowInstruction::ExecuteBatch {
+            amounts_expected: legs.iter().map(|leg| leg.amount_expected).collect(),
+            sequences: legs.iter().map(|leg| leg.sequence).collect(),
+        }
+        .pack(),
+    }
+}
+
+/// Updates the registry settings, signed by the registry `admin`
+pub fn configure(
+    program_id: &Pubkey,
+    admin: &Pubkey,
+    match_reward_lamports: u64,
+    reclaim_delay: ReclaimDelay,
+) -> Instruction {
+    let (registry, _) = find_registry_address(program_id);
+    Instruction {
+        program_id: *program_id,
+        accounts: vec![
+            AccountMeta::new(registry, false),
+            AccountMeta::new_readonly(*admin, true),
+        ],
+        data: EscrowInstruction::Configure {
+            match_reward_lamports,
+            reclaim_delay,
+        }
+        .pack(),
+    }
+}
+
+/// Swaps two escrowed accounts whose terms satisfy each other, `matcher` receives the match reward
+pub fn match_escrows(
+    program_id: &Pubkey,
+    matcher: &Pubkey,
+    first_deposit: &Pubkey,
+    second_deposit: &Pubkey,
+) -> Instruction {
+    let (registry, _) = find_registry_address(program_id);
+    Instruction {
+        program_id: *program_id,
+        accounts: vec![
+            AccountMeta::new(registry, false),
+            AccountMeta::new(*matcher, true),
+            AccountMeta::new_readonly(spl_token::id(), false),
+            AccountMeta::new(*first_deposit, false),
+            AccountMeta::new(*second_deposit, false),
+        ],
+        data: EscrowInstruction::Match.pack(),
+    }
+}
+
+/// Settles the ring of `deposits`, each going to the owner of the next one and the last one to
+/// the owner of the first; `executor` gets the match reward
+pub fn execute_ring(program_id: &Pubkey, executor: &Pubkey, deposits: &[Pubkey]) -> Instruction {
+    let (registry, _) = find_registry_address(program_id);
+    let mut accounts = vec![
+        AccountMeta::new(registry, false),
+        AccountMeta::new(*executor, true),
+        AccountMeta::new_readonly(spl_token::id(), false),
+    ];
+    accounts.extend(
+        deposits
+            .iter()
+            .map(|deposit| AccountMeta::new(*deposit, false)),
+    );
+    Instruction {
+        program_id: *program_id,
+        accounts,
+        data: EscrowInstruction::ExecuteRing.pack(),
+    }
+}
+
+/// Converts the registry of the first layout to the current one, `payer` funds the extra rent
+pub fn migrate_registry(program_id: &Pubkey, payer: &Pubkey) -> Instruction {
+    let (registry, _) = find_registry_address(program_id);
+    Instruction {
+        program_id: *program_id,
+        accounts: vec![
+            AccountMeta::new(*payer, true),
+            AccountMeta::new(registry, false),
+            AccountMeta::new_readonly(system_program::id(), false),
+        ],
+        data: EscrowInstruction::MigrateRegistry.pack(),
+    }
+}
+
+pub fn query(program_id: &Pubkey, query: EscrowQuery) -> Instruction {
+    let (registry, _) = find_registry_address(program_id);
+    Instruction {
+        program_id: *program_id,
+        accounts: vec![AccountMeta::new_readonly(registry, false)],
+        data: EscrowInstruction::Query { query }.pack(),
+    }
+}
+
+/// Decodes the return data of the `Query` instruction
+pub fn unpack_query_result(data: &[u8]) -> Result<Vec<Escrow>, ProgramError> {
+    if data.len() % Escrow::LEN != 0 {
+        return Err(ProgramError::InvalidAccountData);
+    }
+    data.chunks_exact(Escrow::LEN)
+        .map(Escrow::unpack_from_slice)
+        .collect()
+}
+
+/// Changes the terms of the escrow holding `token_account`, signed by its depositor
+pub fn amend(
+    program_id: &Pubkey,
+    owner: &Pubkey,
+    token_account: &Pubkey,
+    token_expected: &Pubkey,
+    amount_expected: u32,
+) -> Instruction {
+    let (registry, _) = find_registry_address(program_id);
+    Instruction {
+        program_id: *program_id,
+        accounts: vec![
+            AccountMeta::new(registry, false),
+            AccountMeta::new_readonly(*owner, true),
+            AccountMeta::new_readonly(*token_account, false),
+            AccountMeta::new_readonly(*token_expected, false),
+        ],
+        data: EscrowInstruction::Amend { amount_expected }.pack(),
+    }
+}
+
+/// Moves `amount` tokens from `source` into the escrowed `token_account`,
+/// the escrow then asks for `amount_expected` tokens
+pub fn top_up(
+    program_id: &Pubkey,
+    owner: &Pubkey,
+    token_account: &Pubkey,
+    source: &Pubkey,
+    amount: u64,
+    amount_expected: u32,
+) -> Instruction {
+    let (registry, _) = find_registry_address(program_id);
+    Instruction {
+        program_id: *program_id,
+        accounts: vec![
+            AccountMeta::new(registry, false),
+            AccountMeta::new_readonly(*owner, true),
+            AccountMeta::new(*token_account, false),
+            AccountMeta::new(*source, false),
+            AccountMeta::new_readonly(spl_token::id(), false),
+        ],
+        data: EscrowInstruction::TopUp {
+            amount,
+            amount_expected,
+        }
+        .pack(),
+    }
+}
+
+/// Moves `amount` tokens out of the escrowed `token_account` to `destination`,
+/// the escrow then asks for `amount_expected` tokens
+pub fn withdraw(
+    program_id: &Pubkey,
+    owner: &Pubkey,
+    token_account: &Pubkey,
+    destination: &Pubkey,
+    amount: u64,
+    amount_expected: u32,
+) -> Instruction {
+    let (registry, _) = find_registry_address(program_id);
+    Instruction {
+        program_id: *program_id,
+        accounts: vec![
+            AccountMeta::new(registry, false),
+            AccountMeta::new_readonly(*owner, true),
+            AccountMeta::new(*token_account, false),
+            AccountMeta::new(*destination, false),
+            AccountMeta::new_readonly(spl_token::id(), false),
+        ],
+        data: EscrowInstruction::Withdraw {
+            amount,
+            amount_expected,
+        }
+        .pack(),
+    }
+}
+
+/// Puts `token_account` up for auction, bids are paid in `bid_mint`.
+/// A Dutch auction price decays from `start_price` to `reserve_price` at `end_timestamp`.
+#[allow(clippy::too_many_arguments)]
+pub fn create_auction(
+    program_id: &Pubkey,
+    owner: &Pubkey,
+    token_account: &Pubkey,
+    bid_mint: &Pubkey,
+    kind: EscrowKind,
+    reserve_price: u64,
+    start_price: u64,
+    end_timestamp: i64,
+) -> Instruction {
+    let (registry, _) = find_registry_address(program_id);
+    Instruction {
+        program_id: *program_id,
+        accounts: vec![
+            AccountMeta::new(registry, false),
+            AccountMeta::new_readonly(*owner, true),
+            AccountMeta::new(*token_account, false),
+            AccountMeta::new_readonly(spl_token::id(), false),
+            AccountMeta::new_readonly(*bid_mint, false),
+        ],
+        data: EscrowInstruction::CreateAuction {
+            kind,
+            reserve_price,
+            start_price,
+            end_timestamp,
+        }
+        .pack(),
+    }
+}
+
+/// Bids the whole `bid_account` on the auctioned `token_account`.
+/// `previous_bid_account` is the outbid account of an English auction, refunded to its bidder.
+pub fn bid(
+    program_id: &Pubkey,
+    bidder: &Pubkey,
+    bid_account: &Pubkey,
+    token_account: &Pubkey,
+    previous_bid_account: Option<&Pubkey>,
+) -> Instruction {
+    let (registry, _) = find_registry_address(program_id);
+    let mut accounts = vec![
+        AccountMeta::new(registry, false),
+        AccountMeta::new_readonly(*bidder, true),
+        AccountMeta::new(*bid_account, false),
+        AccountMeta::new_readonly(spl_token::id(), false),
+        AccountMeta::new(*token_account, false),
+    ];
+    if let Some(previous_bid_account) = previous_bid_account {
+        accounts.push(AccountMeta::new(*previous_bid_account, false));
+    }
+    Instruction {
+        program_id: *program_id,
+        accounts,
+        data: EscrowInstruction::Bid.pack(),
+    }
+}
+
+/// Closes an ended English auction, `bid_account` is the winning bid if there is any
+pub fn settle_auction(
+    program_id: &Pubkey,
+    token_account: &Pubkey,
+    bid_account: Option<&Pubkey>,
+) -> Instruction {
+    let (registry, _) = find_registry_address(program_id);
+    let mut accounts = vec![
+        AccountMeta::new(registry, false),
+        AccountMeta::new_readonly(spl_token::id(), false),
+        AccountMeta::new(*token_account, false),
+    ];
+    if let Some(bid_account) = bid_account {
+        accounts.push(AccountMeta::new(*bid_account, false));
+    }
+    Instruction {
+        program_id: *program_id,
+        accounts,
+        data: EscrowInstruction::SettleAuction.pack(),
+    }
+}
+
+/// Puts `token_account` up for quotes in `bid_mint`, the maker picks the bid to accept
+pub fn create_rfq(
+    program_id: &Pubkey,
+    owner: &Pubkey,
+    token_account: &Pubkey,
+    bid_mint: &Pubkey,
+) -> Instruction {
+    let (registry, _) = find_registry_address(program_id);
+    Instruction {
+        program_id: *program_id,
+        accounts: vec![
+            AccountMeta::new(registry, false),
+            AccountMeta::new_readonly(*owner, true),
+            AccountMeta::new(*token_account, false),
+            AccountMeta::new_readonly(spl_token::id(), false),
+            AccountMeta::new_readonly(*bid_mint, false),
+        ],
+        data: EscrowInstruction::CreateRfq.pack(),
+    }
+}
+
+/// Locks the whole `bid_account` in the bid vault of `bidder` as a quote for the RFQ of `token_account`
+pub fn submit_bid(
+    program_id: &Pubkey,
+    bidder: &Pubkey,
+    bid_account: &Pubkey,
+    token_account: &Pubkey,
+) -> Instruction {
+    let (registry, _) = find_registry_address(program_id);
+    let (vault, _) = find_bid_vault_address(program_id, token_account, bidder);
+    Instruction {
+        program_id: *program_id,
+        accounts: vec![
+            AccountMeta::new_readonly(registry, false),
+            AccountMeta::new(*bidder, true),
+            AccountMeta::new(*bid_account, false),
+            AccountMeta::new(vault, false),
+            AccountMeta::new_readonly(*token_account, false),
+            AccountMeta::new_readonly(spl_token::id(), false),
+            AccountMeta::new_readonly(system_program::id(), false),
+        ],
+        data: EscrowInstruction::SubmitBid.pack(),
+    }
+}
+
+/// Settles the RFQ of `token_account` with the bid of `bidder` locked in `bid_account`
+pub fn accept_bid(
+    program_id: &Pubkey,
+    owner: &Pubkey,
+    token_account: &Pubkey,
+    bidder: &Pubkey,
+    bid_account: &Pubkey,
+) -> Instruction {
+    let (registry, _) = find_registry_address(program_id);
+    let (vault, _) = find_bid_vault_address(program_id, token_account, bidder);
+    Instruction {
+        program_id: *program_id,
+        accounts: vec![
+            AccountMeta::new(registry, false),
+            AccountMeta::new_readonly(*owner, true),
+            AccountMeta::new(*token_account, false),
+            AccountMeta::new(vault, false),
+            AccountMeta::new(*bid_account, false),
+            AccountMeta::new(*bidder, false),
+            AccountMeta::new_readonly(spl_token::id(), false),
+        ],
+        data: EscrowInstruction::AcceptBid.pack(),
+    }
+}
+
+/// Takes a bid on the closed RFQ of `token_account` back to `bidder`
+pub fn withdraw_bid(
+    program_id: &Pubkey,
+    bidder: &Pubkey,
+    token_account: &Pubkey,
+    bid_account: &Pubkey,
+) -> Instruction {
+    let (registry, _) = find_registry_address(program_id);
+    let (vault, _) = find_bid_vault_address(program_id, token_account, bidder);
+    Instruction {
+        program_id: *program_id,
+        accounts: vec![
+            AccountMeta::new_readonly(registry, false),
+            AccountMeta::new(*bidder, true),
+            AccountMeta::new(vault, false),
+            AccountMeta::new(*bid_account, false),
+            AccountMeta::new_readonly(spl_token::id(), false),
+        ],
+        data: EscrowInstruction::WithdrawBid.pack(),
+    }
+}
+
+/// Deposits `token_account` of `buyer` as a payment to `seller`, disputes are resolved by `arbiter`
+pub fn create_arbitrated(
+    program_id: &Pubkey,
+    buyer: &Pubkey,
+    token_account: &Pubkey,
+    mint: &Pubkey,
+    seller: &Pubkey,
+    arbiter: &Pubkey,
+) -> Instruction {
+    let (registry, _) = find_registry_address(program_id);
+    Instruction {
+        program_id: *program_id,
+        accounts: vec![
+            AccountMeta::new(registry, false),
+            AccountMeta::new_readonly(*buyer, true),
+            AccountMeta::new(*token_account, false),
+            AccountMeta::new_readonly(spl_token::id(), false),
+            AccountMeta::new_readonly(*mint, false),
+        ],
+        data: EscrowInstruction::CreateArbitrated {
+            seller: *seller,
+            arbiter: *arbiter,
+        }
+        .pack(),
+    }
+}
+
+/// Instruction on the escrow of `token_account` signed by one of its parties
+fn party_instruction(
+    program_id: &Pubkey,
+    party: &Pubkey,
+    token_account: &Pubkey,
+    instruction: EscrowInstruction,
+) -> Instruction {
+    let (registry, _) = find_registry_address(program_id);
+    Instruction {
+        program_id: *program_id,
+        accounts: vec![
+            AccountMeta::new(registry, false),
+            AccountMeta::new_readonly(*party, true),
+            AccountMeta::new(*token_account, false),
+            AccountMeta::new_readonly(spl_token::id(), false),
+        ],
+        data: instruction.pack(),
+    }
+}
+
+/// Passes the arbitrated payment in `token_account` to the seller, signed by the buyer
+pub fn release(program_id: &Pubkey, buyer: &Pubkey, token_account: &Pubkey) -> Instruction {
+    party_instruction(program_id, buyer, token_account, EscrowInstruction::Release)
+}
+
+/// Returns the arbitrated payment in `token_account` to the buyer, signed by the seller
+pub fn refund(program_id: &Pubkey, seller: &Pubkey, token_account: &Pubkey) -> Instruction {
+    party_instruction(program_id, seller, token_account, EscrowInstruction::Refund)
+}
+
+/// Opens a dispute on the arbitrated payment in `token_account`, signed by the buyer or the seller
+pub fn dispute(program_id: &Pubkey, party: &Pubkey, token_account: &Pubkey) -> Instruction {
+    let (registry, _) = find_registry_address(program_id);
+    Instruction {
+        program_id: *program_id,
+        accounts: vec![
+            AccountMeta::new(registry, false),
+            AccountMeta::new_readonly(*party, true),
+            AccountMeta::new_readonly(*token_account, false),
+        ],
+        data: EscrowInstruction::Dispute.pack(),
+    }
+}
+
+/// Splits the disputed payment in `token_account`: `seller_bps` of it go to `seller_token_account`
+pub fn resolve(
+    program_id: &Pubkey,
+    arbiter: &Pubkey,
+    token_account: &Pubkey,
+    seller_token_account: &Pubkey,
+    seller_bps: u16,
+) -> Instruction {
+    let (registry, _) = find_registry_address(program_id);
+    Instruction {
+        program_id: *program_id,
+        accounts: vec![
+            AccountMeta::new(registry, false),
+            AccountMeta::new_readonly(*arbiter, true),
+            AccountMeta::new(*token_account, false),
+            AccountMeta::new(*seller_token_account, false),
+            AccountMeta::new_readonly(spl_token::id(), false),
+        ],
+        data: EscrowInstruction::Resolve { seller_bps }.pack(),
+    }
+}
+
+/// Deposits `token_account` to be released to `recipient` or refunded once `threshold`
+/// of `approvers` agree
+pub fn create_multisig(
+    program_id: &Pubkey,
+    owner: &Pubkey,
+    token_account: &Pubkey,
+    mint: &Pubkey,
+    recipient: &Pubkey,
+    threshold: u8,
+    approvers: &[Pubkey],
+) -> Instruction {
+    let (registry, _) = find_registry_address(program_id);
+    Instruction {
+        program_id: *program_id,
+        accounts: vec![
+            AccountMeta::new(registry, false),
+            AccountMeta::new_readonly(*owner, true),
+            AccountMeta::new(*token_account, false),
+            AccountMeta::new_readonly(spl_token::id(), false),
+            AccountMeta::new_readonly(*mint, false),
+        ],
+        data: EscrowInstruction::CreateMultisig {
+            recipient: *recipient,
+            threshold,
+            approvers: approvers.to_vec(),
+        }
+        .pack(),
+    }
+}
+
+/// Approves the release of the multisig escrow of `token_account`, or its refund if `refund`
+pub fn approve(
+    program_id: &Pubkey,
+    approver: &Pubkey,
+    token_account: &Pubkey,
+    refund: bool,
+) -> Instruction {
+    let (registry, _) = find_registry_address(program_id);
+    Instruction {
+        program_id: *program_id,
+        accounts: vec![
+            AccountMeta::new(registry, false),
+            AccountMeta::new_readonly(*approver, true),
+            AccountMeta::new(*token_account, false),
+            AccountMeta::new_readonly(spl_token::id(), false),
+        ],
+        data: EscrowInstruction::Approve { refund }.pack(),
+    }
+}
+
+/// Withdraws the approval of `approver` on the multisig escrow of `token_account`
+pub fn revoke(program_id: &Pubkey, approver: &Pubkey, token_account: &Pubkey) -> Instruction {
+    let (registry, _) = find_registry_address(program_id);
+    Instruction {
+        program_id: *program_id,
+        accounts: vec![
+            AccountMeta::new(registry, false),
+            AccountMeta::new_readonly(*approver, true),
+            AccountMeta::new_readonly(*token_account, false),
+        ],
+        data: EscrowInstruction::Revoke.pack(),
+    }
+}
+
+/// Deposits `token_account` to be paid to `payee` in `milestones` splitting all of its tokens.
+/// Milestones are released by `owner` or `arbiter`, the rest is refundable after `deadline`.
+#[allow(clippy::too_many_arguments)]
+pub fn create_milestones(
+    program_id: &Pubkey,
+    owner: &Pubkey,
+    token_account: &Pubkey,
+    mint: &Pubkey,
+    payee: &Pubkey,
+    arbiter: Option<&Pubkey>,
+    deadline: Option<i64>,
+    milestones: &[Milestone],
+) -> Instruction {
+    let (registry, _) = find_registry_address(program_id);
+    Instruction {
+        program_id: *program_id,
+        accounts: vec![
+            AccountMeta::new(registry, false),
+            AccountMeta::new_readonly(*owner, true),
+            AccountMeta::new(*token_account, false),
+            AccountMeta::new_readonly(spl_token::id(), false),
+            AccountMeta::new_readonly(*mint, false),
+        ],
+        data: EscrowInstruction::CreateMilestones {
+            payee: *payee,
+            arbiter: arbiter.copied(),
+            deadline,
+            milestones: milestones.to_vec(),
+        }
+        .pack(),
+    }
+}
+
+/// Pays milestone `index` of the escrow of `token_account` to `payee_token_account`
+pub fn release_milestone(
+    program_id: &Pubkey,
+    releaser: &Pubkey,
+    token_account: &Pubkey,
+    payee_token_account: &Pubkey,
+    index: u8,
+) -> Instruction {
+    let (registry, _) = find_registry_address(program_id);
+    Instruction {
+        program_id: *program_id,
+        accounts: vec![
+            AccountMeta::new(registry, false),
+            AccountMeta::new_readonly(*releaser, true),
+            AccountMeta::new(*token_account, false),
+            AccountMeta::new(*payee_token_account, false),
+            AccountMeta::new_readonly(spl_token::id(), false),
+        ],
+        data: EscrowInstruction::ReleaseMilestone { index }.pack(),
+    }
+}
+
+/// Returns `token_account` with the unreleased milestones to `owner` after the deadline
+pub fn refund_milestones(
+    program_id: &Pubkey,
+    owner: &Pubkey,
+    token_account: &Pubkey,
+) -> Instruction {
+    party_instruction(
+        program_id,
+        owner,
+        token_account,
+        EscrowInstruction::RefundMilestones,
+    )
+}
+
+/// Deposits `token_account` for `recipient`, claimable with the SHA-256 preimage of `hashlock`
+/// until `timeout`
+pub fn create_htlc(
+    program_id: &Pubkey,
+    owner: &Pubkey,
+    token_account: &Pubkey,
+    mint: &Pubkey,
+    recipient: &Pubkey,
+    hashlock: [u8; 32],
+    timeout: i64,
+) -> Instruction {
+    let (registry, _) = find_registry_address(program_id);
+    Instruction {
+        program_id: *program_id,
+        accounts: vec![
+            AccountMeta::new(registry, false),
+            AccountMeta::new_readonly(*owner, true),
+            AccountMeta::new(*token_account, false),
+            AccountMeta::new_readonly(spl_token::id(), false),
+            AccountMeta::new_readonly(*mint, false),
+        ],
+        data: EscrowInstruction::CreateHtlc {
+            recipient: *recipient,
+            hashlock,
+            timeout,
+        }
+        .pack(),
+    }
+}
+
+/// Passes the HTLC `token_account` to its recipient, revealing `preimage`
+pub fn claim(program_id: &Pubkey, token_account: &Pubkey, preimage: &[u8]) -> Instruction {
+    let (registry, _) = find_registry_address(program_id);
+    Instruction {
+        program_id: *program_id,
+        accounts: vec![
+            AccountMeta::new(registry, false),
+            AccountMeta::new(*token_account, false),
+            AccountMeta::new_readonly(spl_token::id(), false),
+        ],
+        data: EscrowInstruction::Claim { preimage }.pack(),
+    }
+}
+
+/// Returns the unclaimed HTLC `token_account` to `owner` after the timeout
+pub fn refund_htlc(program_id: &Pubkey, owner: &Pubkey, token_account: &Pubkey) -> Instruction {
+    party_instruction(
+        program_id,
+        owner,
+        token_account,
+        EscrowInstruction::RefundHtlc,
+    )
+}
+
+/// Lets the registry transfer up to `amount` tokens of `token_account` on behalf of `owner`,
+/// which signed offers are filled with
+pub fn delegate(
+    program_id: &Pubkey,
+    owner: &Pubkey,
+    token_account: &Pubkey,
+    amount: u64,
+) -> Result<Instruction, ProgramError> {
+    let (registry, _) = find_registry_address(program_id);
+    spl_token::instruction::approve(
+        &spl_token::id(),
+        token_account,
+        &registry,
+        owner,
+        &[],
+        amount,
+    )
+}
+
+/// Has the ed25519 program verify `signature` of the maker over `offer`, to be placed right
+/// before the `fill_offer` instruction
+pub fn verify_offer(program_id: &Pubkey, offer: &SignedOffer, signature: &[u8; 64]) -> Instruction {
+    const DATA_START: u16 = 16;
+    let message = offer.message(program_id);
+    let public_key_offset = DATA_START;
+    let signature_offset = public_key_offset + 32;
+    let message_offset = signature_offset + 64;
+    let mut data = vec![1, 0];
+    for field in [
+        signature_offset,
+        u16::MAX,
+        public_key_offset,
+        u16::MAX,
+        message_offset,
+        message.len() as u16,
+        u16::MAX,
+    ] {
+        data.extend_from_slice(&field.to_le_bytes());
+    }
+    data.extend_from_slice(offer.maker.as_ref());
+    data.extend_from_slice(signature);
+    data.extend_from_slice(&message);
+    Instruction {
+        program_id: ed25519_program::id(),
+        accounts: vec![],
+        data,
+    }
+}
+
+/// Fills `offer` of its maker for `taker`, who pays from `taker_source` and receives into
+/// `taker_destination`; `verify_offer` is to precede it in the transaction
+pub fn fill_offer(
+    program_id: &Pubkey,
+    taker: &Pubkey,
+    taker_source: &Pubkey,
+    taker_destination: &Pubkey,
+    maker_source: &Pubkey,
+    maker_destination: &Pubkey,
+    offer: &SignedOffer,
+) -> Instruction {
+    let (registry, _) = find_registry_address(program_id);
+    let (nonce, _) = find_nonce_address(program_id, &offer.maker, offer.nonce);
+    Instruction {
+        program_id: *program_id,
+        accounts: vec![
+            AccountMeta::new_readonly(registry, false),
+            AccountMeta::new(*taker, true),
+            AccountMeta::new(*taker_source, false),
+            AccountMeta::new(*taker_destination, false),
+            AccountMeta::new(*maker_source, false),
+            AccountMeta::new(*maker_destination, false),
+            AccountMeta::new(nonce, false),
+            AccountMeta::new_readonly(sysvar::instructions::id(), false),
+            AccountMeta::new_readonly(spl_token::id(), false),
+            AccountMeta::new_readonly(system_program::id(), false),
+        ],
+        data: EscrowInstruction::FillOffer { offer: *offer }.pack(),
+    }
+}
+
+/// Burns `nonce` of `maker`, invalidating the offers signed with it
+pub fn cancel_offer(program_id: &Pubkey, maker: &Pubkey, nonce: u64) -> Instruction {
+    let (nonce_account, _) = find_nonce_address(program_id, maker, nonce);
+    Instruction {
+        program_id: *program_id,
+        accounts: vec![
+            AccountMeta::new(*maker, true),
+            AccountMeta::new(nonce_account, false),
+            AccountMeta::new_readonly(system_program::id(), false),
+        ],
+        data: EscrowInstruction::CancelOffer { nonce }.pack(),
+    }
+}
+
+/// Deposits all of `holdings` as one bundle, asking for a token account of each of `wanted`
+pub fn create_bundle(
+    program_id: &Pubkey,
+    owner: &Pubkey,
+    holdings: &[Pubkey],
+    wanted: &[BundleAsset],
+) -> Instruction {
+    let (registry, _) = find_registry_address(program_id);
+    let mut accounts = vec![
+        AccountMeta::new(registry, false),
+        AccountMeta::new_readonly(*owner, true),
+        AccountMeta::new_readonly(spl_token::id(), false),
+    ];
+    accounts.extend(
+        holdings
+            .iter()
+            .map(|holding| AccountMeta::new(*holding, false)),
+    );
+    Instruction {
+        program_id: *program_id,
+        accounts,
+        data: EscrowInstruction::CreateBundle {
+            wanted: wanted.to_vec(),
+        }
+        .pack(),
+    }
+}
+
+/// Swaps the bundle of `holdings`, listed as deposited, for `taker_accounts`, one per wanted
+/// asset in order
+pub fn execute_bundle(
+    program_id: &Pubkey,
+    taker: &Pubkey,
+    holdings: &[Pubkey],
+    taker_accounts: &[Pubkey],
+    sequence: u64,
+) -> Instruction {
+    let (registry, _) = find_registry_address(program_id);
+    let mut accounts = vec![
+        AccountMeta::new(registry, false),
+        AccountMeta::new_readonly(*taker, true),
+        AccountMeta::new_readonly(spl_token::id(), false),
+    ];
+    accounts.extend(
+        holdings
+            .iter()
+            .chain(taker_accounts)
+            .map(|account| AccountMeta::new(*account, false)),
+    );
+    Instruction {
+        program_id: *program_id,
+        accounts,
+        data: EscrowInstruction::ExecuteBundle { sequence }.pack(),
+    }
+}
+
+/// Returns all `holdings` of a bundle, listed as deposited, back to its depositor
+pub fn reclaim_bundle(program_id: &Pubkey, owner: &Pubkey, holdings: &[Pubkey]) -> Instruction {
+    let mut ix = reclaim(program_id, owner, &holdings[0]);
+    ix.accounts.extend(
+        holdings[1..]
+            .iter()
+            .map(|holding| AccountMeta::new(*holding, false)),
+    );
+    ix
+}
+
+/// Returns the escrowed `token_account` back to its depositor
+pub fn reclaim(program_id: &Pubkey, owner: &Pubkey, token_account: &Pubkey) -> Instruction {
+    let (registry, _) = find_registry_address(program_id);
+    Instruction {
+        program_id: *program_id,
+        accounts: vec![
+            AccountMeta::new(registry, false),
+            AccountMeta::new_readonly(*owner, true),
+            AccountMeta::new(*token_account, false),
+            AccountMeta::new_readonly(spl_token::id(), false),
+        ],
+        data: EscrowInstruction::Reclaim.pack(),
+    }
+}
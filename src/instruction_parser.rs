@@ -1,39 +1,596 @@
+use crate::state::{
+    Alternative, BundleAsset, Escrow, EscrowKind, Milestone, ReclaimDelay, SignedOffer,
+    MAX_ALTERNATIVES, MAX_APPROVERS, MAX_BUNDLE_LEGS, MAX_MILESTONES,
+};
+use solana_program::program::MAX_RETURN_DATA;
 use solana_program::program_error::ProgramError;
+use solana_program::program_pack::Pack;
+use solana_program::pubkey::Pubkey;
 
+/// Key to look escrows up by in the `Query` instruction
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum EscrowQuery {
+    HoldingAccount(Pubkey),
+    Owner(Pubkey),
+}
+
+/// Most escrow records fitting into the return data of a `Query`, further matches are left out
+pub const MAX_QUERY_RESULTS: usize = MAX_RETURN_DATA / Escrow::LEN;
+
+#[derive(Debug, PartialEq, Eq)]
 pub enum EscrowInstruction<'a> {
-    Init { seed: &'a [u8], bump_seed: u8 },
-    Deposit { amount_expected: u32 },
-    Execute { amount_expected: u32 },
+    Init {
+        seed: &'a [u8],
+        bump_seed: u8,
+    },
+    Deposit {
+        amount_expected: u32,
+    },
+    /// `sequence` is the one of the escrow the taker saw
+    Execute {
+        amount_expected: u32,
+        sequence: u64,
+    },
     Reclaim,
+    ExecuteBatch {
+        amounts_expected: Vec<u32>,
+        sequences: Vec<u64>,
+    },
+    Configure {
+        match_reward_lamports: u64,
+        reclaim_delay: ReclaimDelay,
+    },
+    Match,
+    Query {
+        query: EscrowQuery,
+    },
+    Amend {
+        amount_expected: u32,
+    },
+    TopUp {
+        amount: u64,
+        amount_expected: u32,
+    },
+    Withdraw {
+        amount: u64,
+        amount_expected: u32,
+    },
+    /// `kind` is either of the auction kinds
+    CreateAuction {
+        kind: EscrowKind,
+        reserve_price: u64,
+        start_price: u64,
+        end_timestamp: i64,
+    },
+    Bid,
+    SettleAuction,
+    CreateRfq,
+    SubmitBid,
+    AcceptBid,
+    WithdrawBid,
+    CreateArbitrated {
+        seller: Pubkey,
+        arbiter: Pubkey,
+    },
+    /// Buyer pays the seller
+    Release,
+    /// Seller returns the payment to the buyer
+    Refund,
+    Dispute,
+    /// Arbiter pays `seller_bps` of the payment to the seller, the rest returns to the buyer
+    Resolve {
+        seller_bps: u16,
+    },
+    /// `approvers` are at most `MAX_APPROVERS`
+    CreateMultisig {
+        recipient: Pubkey,
+        threshold: u8,
+        approvers: Vec<Pubkey>,
+    },
+    /// Approves either the release to the recipient or the refund to the depositor
+    Approve {
+        refund: bool,
+    },
+    Revoke,
+    /// `milestones` are at most `MAX_MILESTONES`, paid in order
+    CreateMilestones {
+        payee: Pubkey,
+        arbiter: Option<Pubkey>,
+        deadline: Option<i64>,
+        milestones: Vec<Milestone>,
+    },
+    /// `index` is the milestone the signer expects to release
+    ReleaseMilestone {
+        index: u8,
+    },
+    RefundMilestones,
+    CreateHtlc {
+        recipient: Pubkey,
+        hashlock: [u8; 32],
+        timeout: i64,
+    },
+    /// Anyone may claim with the preimage, the escrow goes to the recipient
+    Claim {
+        preimage: &'a [u8],
+    },
+    RefundHtlc,
+    /// Taker fills `offer`, whose maker signature is verified by the preceding ed25519 instruction
+    FillOffer {
+        offer: SignedOffer,
+    },
+    /// Maker burns `nonce` so that offers signed with it can't be filled
+    CancelOffer {
+        nonce: u64,
+    },
+    /// Deposits the tokens delegated to the registry, the account stays with its owner
+    DepositDelegated {
+        amount_expected: u32,
+    },
+    /// `amount_expected` is the taker requirement for the delegated amount
+    ExecuteDelegated {
+        amount_expected: u64,
+        sequence: u64,
+    },
+    /// Same as `Deposit`, also accepting any of `alternatives` instead of the expected token
+    DepositAlternatives {
+        amount_expected: u32,
+        alternatives: Vec<Alternative>,
+    },
+    /// Deposits the holding accounts passed after the fixed accounts, asking for `wanted`
+    CreateBundle {
+        wanted: Vec<BundleAsset>,
+    },
+    /// Swaps all legs of the bundle at once, `sequence` is the one the taker saw
+    ExecuteBundle {
+        sequence: u64,
+    },
+    /// Anyone settles a ring of three or more swap deposits passed in ring order
+    ExecuteRing,
+    /// Resizes a registry of the first layout to the current one, the payer funds the extra rent
+    MigrateRegistry,
+}
+
+impl<'a> EscrowInstruction<'a> {
+    /// Deserializes instruction data as the program does, the inverse of `pack`
+    pub fn unpack(instruction_data: &'a [u8]) -> Result<Self, ProgramError> {
+        parse_data(instruction_data)
+    }
+
+    /// Serializes the instruction into the layout expected by `parse_data`
+    pub fn pack(&self) -> Vec<u8> {
+        match self {
+            EscrowInstruction::Init { seed, bump_seed } => [&[0], *seed, &[*bump_seed]].concat(),
+            EscrowInstruction::Deposit { amount_expected } => {
+                [[1].as_slice(), amount_expected.to_le_bytes().as_slice()].concat()
+            }
+            EscrowInstruction::Execute {
+                amount_expected,
+                sequence,
+            } => [
+                [2].as_slice(),
+                amount_expected.to_le_bytes().as_slice(),
+                sequence.to_le_bytes().as_slice(),
+            ]
+            .concat(),
+            EscrowInstruction::Reclaim => vec![3],
+            EscrowInstruction::ExecuteBatch {
+                amounts_expected,
+                sequences,
+            } => {
+                let mut data = vec![4];
+                for (amount_expected, sequence) in amounts_expected.iter().zip(sequences) {
+                    data.extend_from_slice(&amount_expected.to_le_bytes());
+                    data.extend_from_slice(&sequence.to_le_bytes());
+                }
+                data
+            }
+            EscrowInstruction::Configure {
+                match_reward_lamports,
+                reclaim_delay,
+            } => [
+                [5].as_slice(),
+                match_reward_lamports.to_le_bytes().as_slice(),
+                reclaim_delay.pack().as_slice(),
+            ]
+            .concat(),
+            EscrowInstruction::Match => vec![6],
+            EscrowInstruction::Query { query } => {
+                let (kind, key) = match query {
+                    EscrowQuery::HoldingAccount(key) => (0, key),
+                    EscrowQuery::Owner(key) => (1, key),
+                };
+                [[7, kind].as_slice(), key.as_ref()].concat()
+            }
+            EscrowInstruction::Amend { amount_expected } => {
+                [[8].as_slice(), amount_expected.to_le_bytes().as_slice()].concat()
+            }
+            EscrowInstruction::TopUp {
+                amount,
+                amount_expected,
+            } => [
+                [9].as_slice(),
+                amount.to_le_bytes().as_slice(),
+                amount_expected.to_le_bytes().as_slice(),
+            ]
+            .concat(),
+            EscrowInstruction::Withdraw {
+                amount,
+                amount_expected,
+            } => [
+                [10].as_slice(),
+                amount.to_le_bytes().as_slice(),
+                amount_expected.to_le_bytes().as_slice(),
+            ]
+            .concat(),
+            EscrowInstruction::CreateAuction {
+                kind,
+                reserve_price,
+                start_price,
+                end_timestamp,
+            } => [
+                [11, *kind as u8].as_slice(),
+                reserve_price.to_le_bytes().as_slice(),
+                start_price.to_le_bytes().as_slice(),
+                end_timestamp.to_le_bytes().as_slice(),
+            ]
+            .concat(),
+            EscrowInstruction::Bid => vec![12],
+            EscrowInstruction::SettleAuction => vec![13],
+            EscrowInstruction::CreateRfq => vec![14],
+            EscrowInstruction::SubmitBid => vec![15],
+            EscrowInstruction::AcceptBid => vec![16],
+            EscrowInstruction::WithdrawBid => vec![17],
+            EscrowInstruction::CreateArbitrated { seller, arbiter } => {
+                [[18].as_slice(), seller.as_ref(), arbiter.as_ref()].concat()
+            }
+            EscrowInstruction::Release => vec![19],
+            EscrowInstruction::Refund => vec![20],
+            EscrowInstruction::Dispute => vec![21],
+            EscrowInstruction::Resolve { seller_bps } => {
+                [[22].as_slice(), seller_bps.to_le_bytes().as_slice()].concat()
+            }
+            EscrowInstruction::CreateMultisig {
+                recipient,
+                threshold,
+                approvers,
+            } => {
+                let mut data = [[23].as_slice(), recipient.as_ref(), &[*threshold]].concat();
+                for approver in approvers {
+                    data.extend_from_slice(approver.as_ref());
+                }
+                data
+            }
+            EscrowInstruction::Approve { refund } => vec![24, *refund as u8],
+            EscrowInstruction::Revoke => vec![25],
+            EscrowInstruction::CreateMilestones {
+                payee,
+                arbiter,
+                deadline,
+                milestones,
+            } => {
+                let mut data = [
+                    [26].as_slice(),
+                    payee.as_ref(),
+                    arbiter.unwrap_or_default().as_ref(),
+                    deadline.unwrap_or_default().to_le_bytes().as_slice(),
+                ]
+                .concat();
+                for milestone in milestones {
+                    data.extend_from_slice(&milestone.amount.to_le_bytes());
+                    data.extend_from_slice(&milestone.description_hash);
+                }
+                data
+            }
+            EscrowInstruction::ReleaseMilestone { index } => vec![27, *index],
+            EscrowInstruction::RefundMilestones => vec![28],
+            EscrowInstruction::CreateHtlc {
+                recipient,
+                hashlock,
+                timeout,
+            } => [
+                [29].as_slice(),
+                recipient.as_ref(),
+                hashlock.as_slice(),
+                timeout.to_le_bytes().as_slice(),
+            ]
+            .concat(),
+            EscrowInstruction::Claim { preimage } => [&[30], *preimage].concat(),
+            EscrowInstruction::RefundHtlc => vec![31],
+            EscrowInstruction::FillOffer { offer } => {
+                let mut data = vec![32; 1 + SignedOffer::LEN];
+                offer.pack_into_slice(&mut data[1..]);
+                data
+            }
+            EscrowInstruction::CancelOffer { nonce } => {
+                [[33].as_slice(), &nonce.to_le_bytes()].concat()
+            }
+            EscrowInstruction::DepositDelegated { amount_expected } => {
+                [[34].as_slice(), &amount_expected.to_le_bytes()].concat()
+            }
+            EscrowInstruction::ExecuteDelegated {
+                amount_expected,
+                sequence,
+            } => [
+                [35].as_slice(),
+                &amount_expected.to_le_bytes(),
+                &sequence.to_le_bytes(),
+            ]
+            .concat(),
+            EscrowInstruction::DepositAlternatives {
+                amount_expected,
+                alternatives,
+            } => {
+                let mut data = [[36].as_slice(), &amount_expected.to_le_bytes()].concat();
+                for alternative in alternatives {
+                    data.extend_from_slice(alternative.mint.as_ref());
+                    data.extend_from_slice(&alternative.amount.to_le_bytes());
+                }
+                data
+            }
+            EscrowInstruction::CreateBundle { wanted } => {
+                let mut data = vec![37];
+                for asset in wanted {
+                    data.extend_from_slice(asset.mint.as_ref());
+                    data.extend_from_slice(&asset.amount.to_le_bytes());
+                }
+                data
+            }
+            EscrowInstruction::ExecuteBundle { sequence } => {
+                [[38].as_slice(), &sequence.to_le_bytes()].concat()
+            }
+            EscrowInstruction::ExecuteRing => vec![39],
+            EscrowInstruction::MigrateRegistry => vec![40],
+        }
+    }
+}
+
+fn unpack_amount(input: &[u8]) -> Result<u32, ProgramError> {
+    let arr: [u8; 4] = input
+        .get(..4)
+        .and_then(|slice| slice.try_into().ok())
+        .ok_or(ProgramError::InvalidInstructionData)?;
+    Ok(u32::from_le_bytes(arr))
+}
+
+fn unpack_u64(input: &[u8]) -> Result<u64, ProgramError> {
+    let arr: [u8; 8] = input
+        .get(..8)
+        .and_then(|slice| slice.try_into().ok())
+        .ok_or(ProgramError::InvalidInstructionData)?;
+    Ok(u64::from_le_bytes(arr))
+}
+
+fn unpack_bytes32(input: &[u8]) -> Result<[u8; 32], ProgramError> {
+    input
+        .get(..32)
+        .and_then(|slice| slice.try_into().ok())
+        .ok_or(ProgramError::InvalidInstructionData)
 }
 
-pub fn parse_data(instruction_data: &[u8]) -> Result<EscrowInstruction, ProgramError> {
-    let data_len = instruction_data.len();
-    assert!(data_len > 0);
-    let instruction = instruction_data[0];
+fn unpack_pubkey(input: &[u8]) -> Result<Pubkey, ProgramError> {
+    Ok(Pubkey::new_from_array(unpack_bytes32(input)?))
+}
+
+pub fn parse_data(instruction_data: &[u8]) -> Result<EscrowInstruction<'_>, ProgramError> {
+    let (&instruction, rest) = instruction_data
+        .split_first()
+        .ok_or(ProgramError::InvalidInstructionData)?;
     match instruction {
-        0 => Ok(EscrowInstruction::Init {
-            seed: &instruction_data[1..data_len - 1],
-            bump_seed: instruction_data[data_len - 1],
-        }),
-        1 => {
-            let arr: [u8; 4] = instruction_data[1..5]
-                .try_into()
-                .map_err(|_| ProgramError::InvalidInstructionData)?;
-            // Todo: make transmute
-            Ok(EscrowInstruction::Deposit {
-                amount_expected: unsafe { std::mem::transmute::<[u8; 4], u32>(arr) },
-            })
+        0 => {
+            let (&bump_seed, seed) = rest
+                .split_last()
+                .ok_or(ProgramError::InvalidInstructionData)?;
+            Ok(EscrowInstruction::Init { seed, bump_seed })
         }
+        1 => Ok(EscrowInstruction::Deposit {
+            amount_expected: unpack_amount(rest)?,
+        }),
         2 => {
-            let arr: [u8; 4] = instruction_data[1..5]
-                .try_into()
-                .map_err(|_| ProgramError::InvalidInstructionData)?;
+            if rest.len() != 12 {
+                return Err(ProgramError::InvalidInstructionData);
+            }
             Ok(EscrowInstruction::Execute {
-                amount_expected: unsafe { std::mem::transmute::<[u8; 4], u32>(arr) },
+                amount_expected: unpack_amount(rest)?,
+                sequence: unpack_u64(&rest[4..])?,
             })
         }
         3 => Ok(EscrowInstruction::Reclaim),
+        4 => {
+            if rest.is_empty() || rest.len() % 12 != 0 {
+                return Err(ProgramError::InvalidInstructionData);
+            }
+            let legs = rest.chunks_exact(12);
+            Ok(EscrowInstruction::ExecuteBatch {
+                amounts_expected: legs.clone().map(unpack_amount).collect::<Result<_, _>>()?,
+                sequences: legs
+                    .map(|leg| unpack_u64(&leg[4..]))
+                    .collect::<Result<_, _>>()?,
+            })
+        }
+        5 => {
+            let reclaim_delay: &[u8; ReclaimDelay::LEN] = rest
+                .get(8..8 + ReclaimDelay::LEN)
+                .and_then(|slice| slice.try_into().ok())
+                .ok_or(ProgramError::InvalidInstructionData)?;
+            Ok(EscrowInstruction::Configure {
+                match_reward_lamports: unpack_u64(rest)?,
+                reclaim_delay: ReclaimDelay::unpack(reclaim_delay)
+                    .map_err(|_| ProgramError::InvalidInstructionData)?,
+            })
+        }
+        6 => Ok(EscrowInstruction::Match),
+        7 => {
+            let (&kind, key) = rest
+                .split_first()
+                .ok_or(ProgramError::InvalidInstructionData)?;
+            let key = unpack_pubkey(key)?;
+            let query = match kind {
+                0 => EscrowQuery::HoldingAccount(key),
+                1 => EscrowQuery::Owner(key),
+                _ => return Err(ProgramError::InvalidInstructionData),
+            };
+            Ok(EscrowInstruction::Query { query })
+        }
+        8 => Ok(EscrowInstruction::Amend {
+            amount_expected: unpack_amount(rest)?,
+        }),
+        9 => Ok(EscrowInstruction::TopUp {
+            amount: unpack_u64(rest)?,
+            amount_expected: unpack_amount(rest.get(8..).unwrap_or_default())?,
+        }),
+        10 => Ok(EscrowInstruction::Withdraw {
+            amount: unpack_u64(rest)?,
+            amount_expected: unpack_amount(rest.get(8..).unwrap_or_default())?,
+        }),
+        11 => {
+            let (&kind, rest) = rest
+                .split_first()
+                .ok_or(ProgramError::InvalidInstructionData)?;
+            let kind = match kind {
+                1 => EscrowKind::EnglishAuction,
+                2 => EscrowKind::DutchAuction,
+                _ => return Err(ProgramError::InvalidInstructionData),
+            };
+            Ok(EscrowInstruction::CreateAuction {
+                kind,
+                reserve_price: unpack_u64(rest)?,
+                start_price: unpack_u64(rest.get(8..).unwrap_or_default())?,
+                end_timestamp: unpack_u64(rest.get(16..).unwrap_or_default())? as i64,
+            })
+        }
+        12 => Ok(EscrowInstruction::Bid),
+        13 => Ok(EscrowInstruction::SettleAuction),
+        14 => Ok(EscrowInstruction::CreateRfq),
+        15 => Ok(EscrowInstruction::SubmitBid),
+        16 => Ok(EscrowInstruction::AcceptBid),
+        17 => Ok(EscrowInstruction::WithdrawBid),
+        18 => Ok(EscrowInstruction::CreateArbitrated {
+            seller: unpack_pubkey(rest)?,
+            arbiter: unpack_pubkey(rest.get(32..).unwrap_or_default())?,
+        }),
+        19 => Ok(EscrowInstruction::Release),
+        20 => Ok(EscrowInstruction::Refund),
+        21 => Ok(EscrowInstruction::Dispute),
+        22 => {
+            let seller_bps: [u8; 2] = rest
+                .get(..2)
+                .and_then(|slice| slice.try_into().ok())
+                .ok_or(ProgramError::InvalidInstructionData)?;
+            Ok(EscrowInstruction::Resolve {
+                seller_bps: u16::from_le_bytes(seller_bps),
+            })
+        }
+        23 => {
+            let approvers = rest.get(33..).unwrap_or_default();
+            if approvers.len() % 32 != 0 || approvers.len() / 32 > MAX_APPROVERS {
+                return Err(ProgramError::InvalidInstructionData);
+            }
+            Ok(EscrowInstruction::CreateMultisig {
+                recipient: unpack_pubkey(rest)?,
+                threshold: *rest.get(32).ok_or(ProgramError::InvalidInstructionData)?,
+                approvers: approvers
+                    .chunks_exact(32)
+                    .map(unpack_pubkey)
+                    .collect::<Result<_, _>>()?,
+            })
+        }
+        24 => match rest.first() {
+            Some(0) => Ok(EscrowInstruction::Approve { refund: false }),
+            Some(1) => Ok(EscrowInstruction::Approve { refund: true }),
+            _ => Err(ProgramError::InvalidInstructionData),
+        },
+        25 => Ok(EscrowInstruction::Revoke),
+        26 => {
+            let milestones = rest.get(72..).unwrap_or_default();
+            if milestones.len() % Milestone::LEN != 0
+                || milestones.len() / Milestone::LEN > MAX_MILESTONES
+            {
+                return Err(ProgramError::InvalidInstructionData);
+            }
+            let arbiter = unpack_pubkey(rest.get(32..).unwrap_or_default())?;
+            let deadline = unpack_u64(rest.get(64..).unwrap_or_default())? as i64;
+            Ok(EscrowInstruction::CreateMilestones {
+                payee: unpack_pubkey(rest)?,
+                arbiter: (arbiter != Pubkey::default()).then_some(arbiter),
+                deadline: (deadline != 0).then_some(deadline),
+                milestones: milestones
+                    .chunks_exact(Milestone::LEN)
+                    .map(|milestone| {
+                        Ok(Milestone {
+                            amount: unpack_u64(milestone)?,
+                            description_hash: unpack_bytes32(&milestone[8..])?,
+                        })
+                    })
+                    .collect::<Result<_, ProgramError>>()?,
+            })
+        }
+        27 => Ok(EscrowInstruction::ReleaseMilestone {
+            index: *rest.first().ok_or(ProgramError::InvalidInstructionData)?,
+        }),
+        28 => Ok(EscrowInstruction::RefundMilestones),
+        29 => Ok(EscrowInstruction::CreateHtlc {
+            recipient: unpack_pubkey(rest)?,
+            hashlock: unpack_bytes32(rest.get(32..).unwrap_or_default())?,
+            timeout: unpack_u64(rest.get(64..).unwrap_or_default())? as i64,
+        }),
+        30 => Ok(EscrowInstruction::Claim { preimage: rest }),
+        31 => Ok(EscrowInstruction::RefundHtlc),
+        32 => Ok(EscrowInstruction::FillOffer {
+            offer: SignedOffer::unpack_from_slice(rest)
+                .map_err(|_| ProgramError::InvalidInstructionData)?,
+        }),
+        33 => Ok(EscrowInstruction::CancelOffer {
+            nonce: unpack_u64(rest)?,
+        }),
+        34 => Ok(EscrowInstruction::DepositDelegated {
+            amount_expected: unpack_amount(rest)?,
+        }),
+        35 => Ok(EscrowInstruction::ExecuteDelegated {
+            amount_expected: unpack_u64(rest)?,
+            sequence: unpack_u64(rest.get(8..).unwrap_or_default())?,
+        }),
+        36 => {
+            let alternatives = rest.get(4..).unwrap_or_default();
+            if alternatives.len() % Alternative::LEN != 0
+                || alternatives.len() / Alternative::LEN > MAX_ALTERNATIVES
+            {
+                return Err(ProgramError::InvalidInstructionData);
+            }
+            Ok(EscrowInstruction::DepositAlternatives {
+                amount_expected: unpack_amount(rest)?,
+                alternatives: alternatives
+                    .chunks_exact(Alternative::LEN)
+                    .map(|alternative| {
+                        Ok(Alternative {
+                            mint: unpack_pubkey(alternative)?,
+                            amount: unpack_amount(&alternative[32..])?,
+                        })
+                    })
+                    .collect::<Result<_, ProgramError>>()?,
+            })
+        }
+        37 => {
+            if rest.len() % BundleAsset::LEN != 0 || rest.len() / BundleAsset::LEN > MAX_BUNDLE_LEGS
+            {
+                return Err(ProgramError::InvalidInstructionData);
+            }
+            Ok(EscrowInstruction::CreateBundle {
+                wanted: rest
+                    .chunks_exact(BundleAsset::LEN)
+                    .map(|asset| {
+                        Ok(BundleAsset {
+                            mint: unpack_pubkey(asset)?,
+                            amount: unpack_amount(&asset[32..])?,
+                        })
+                    })
+                    .collect::<Result<_, ProgramError>>()?,
+            })
+        }
+        38 => Ok(EscrowInstruction::ExecuteBundle {
+            sequence: unpack_u64(rest)?,
+        }),
+        39 => Ok(EscrowInstruction::ExecuteRing),
+        40 => Ok(EscrowInstruction::MigrateRegistry),
         _ => Err(ProgramError::InvalidInstructionData),
     }
 }
@@ -1,6 +1,24 @@
+#[cfg(feature = "client")]
+pub mod client;
+pub mod cpi;
+#[cfg(not(feature = "no-entrypoint"))]
 mod entrypoint;
-mod error;
+pub mod error;
+pub mod events;
+pub mod instruction;
 mod instruction_parser;
 mod processor;
-mod state;
+pub mod state;
+#[cfg(feature = "test-fixtures")]
+pub mod test_fixtures;
 mod utils;
+
+pub use error::EscrowError;
+pub use instruction_parser::{EscrowInstruction, EscrowQuery, MAX_QUERY_RESULTS};
+pub use processor::parse_execute_instruction as process_instruction;
+pub use state::{
+    find_bid_vault_address, find_nonce_address, find_registry_address, Alternative, ArbiterTerms,
+    AuctionTerms, BidRecord, BundleAsset, BundleTerms, DelegatedTerms, Escrow, EscrowCollection,
+    EscrowKind, HtlcTerms, Milestone, MilestoneTerms, MultisigTerms, ReclaimDelay, RegistryConfig,
+    SignedOffer, SwapTerms, SEED,
+};
@@ -1,16 +1,28 @@
 use crate::error::{throw_and_log, EscrowError};
-use crate::instruction_parser::{parse_data, EscrowInstruction};
-use crate::state::{EscrowCollection, SEED};
-use crate::utils::{verify_pda, verify_rent_exemption};
+use crate::events;
+use crate::instruction_parser::{parse_data, EscrowInstruction, EscrowQuery, MAX_QUERY_RESULTS};
+use crate::state::{
+    find_bid_vault_address, find_nonce_address, ArbiterTerms, AuctionTerms, BidRecord, BundleAsset,
+    BundleTerms, DelegatedTerms, Escrow, EscrowCollection, EscrowKind, HtlcTerms, MilestoneTerms,
+    MultisigTerms, ReclaimDelay, SignedOffer, SwapTerms, BID_SEED, LEGACY_REGISTRY_LEN,
+    MAX_BUNDLE_LEGS, NONCE_SEED, SEED,
+};
+use crate::utils::{
+    to_hex, verify_delegation, verify_ed25519_signature, verify_kind, verify_pda,
+    verify_reclaim_delay, verify_rent_exemption, verify_signer, verify_writable,
+};
 use solana_program::account_info::{next_account_info, AccountInfo};
 use solana_program::entrypoint::ProgramResult;
-use solana_program::program::{invoke, invoke_signed};
+use solana_program::hash::hash;
+use solana_program::program::{invoke, invoke_signed, set_return_data};
 use solana_program::program_error::ProgramError;
 use solana_program::program_pack::Pack;
 use solana_program::pubkey::Pubkey;
+use solana_program::sysvar::clock::Clock;
 use solana_program::sysvar::rent::Rent;
+use solana_program::sysvar::Sysvar;
 use solana_program::{msg, system_instruction, system_program};
-use spl_token::instruction::set_authority;
+use spl_token::instruction::{set_authority, transfer};
 
 /// Escrow program is to be initialized by an admin user
 /// This user pays for PDA account creation
@@ -25,11 +37,12 @@ fn init_escrow(
     let pda_account_info = next_account_info(account_info_iter)?;
     let system_account = next_account_info(account_info_iter)?;
 
-    assert!(payer_account_info.is_signer);
-    assert!(payer_account_info.is_writable);
-    assert!(!pda_account_info.is_signer);
-    assert!(pda_account_info.is_writable);
-    assert!(system_program::check_id(system_account.key));
+    verify_signer(payer_account_info)?;
+    verify_writable(payer_account_info)?;
+    verify_writable(pda_account_info)?;
+    if !system_program::check_id(system_account.key) {
+        return Err(ProgramError::IncorrectProgramId);
+    }
 
     verify_pda(pda_account_info, program_id)?;
 
@@ -52,6 +65,13 @@ fn init_escrow(
                 ],
                 &[&[seed, &[bump_seed]]],
             )?;
+
+            let mut escrow_accounts = Box::new(EscrowCollection::unpack_from_slice(
+                &pda_account_info.try_borrow_data()?,
+            )?);
+            escrow_accounts.config.admin = *payer_account_info.key;
+            escrow_accounts.config.next_sequence = 1;
+            escrow_accounts.pack_into_slice(&mut pda_account_info.try_borrow_mut_data()?);
         }
         _ => {
             msg!("PDA account already exists");
@@ -62,10 +82,74 @@ fn init_escrow(
     Ok(())
 }
 
+/// Registries created before the registry config and the escrow terms were added are resized to
+/// the current layout keeping their escrows, the payer tops the rent up.
+/// Nobody initialized them as admin, so their match reward and reclaim delay can't be configured.
+fn migrate_registry(accounts: &[AccountInfo], program_id: &Pubkey) -> ProgramResult {
+    let account_info_iter = &mut accounts.iter();
+    let payer_account_info = next_account_info(account_info_iter)?;
+    let pda_account_info = next_account_info(account_info_iter)?;
+    let system_account = next_account_info(account_info_iter)?;
+
+    verify_signer(payer_account_info)?;
+    verify_writable(payer_account_info)?;
+    verify_writable(pda_account_info)?;
+    if !system_program::check_id(system_account.key) {
+        return Err(ProgramError::IncorrectProgramId);
+    }
+
+    verify_pda(pda_account_info, program_id)?;
+    if pda_account_info.owner != program_id {
+        return Err(throw_and_log(EscrowError::NotInitialized));
+    }
+    if pda_account_info.data_len() != LEGACY_REGISTRY_LEN {
+        msg!(
+            "Registry of {} bytes can't be migrated, expected {}",
+            pda_account_info.data_len(),
+            LEGACY_REGISTRY_LEN
+        );
+        return Err(ProgramError::InvalidAccountData);
+    }
+
+    let escrow_accounts = Box::new(EscrowCollection::unpack_legacy(
+        &pda_account_info.try_borrow_data()?,
+    )?);
+
+    let missing_lamports = Rent::default()
+        .minimum_balance(EscrowCollection::LEN)
+        .saturating_sub(pda_account_info.lamports());
+    if missing_lamports > 0 {
+        invoke(
+            &system_instruction::transfer(
+                payer_account_info.key,
+                pda_account_info.key,
+                missing_lamports,
+            ),
+            &[
+                payer_account_info.clone(),
+                pda_account_info.clone(),
+                system_account.clone(),
+            ],
+        )?;
+    }
+    pda_account_info.realloc(EscrowCollection::LEN, true)?;
+    escrow_accounts.pack_into_slice(&mut pda_account_info.try_borrow_mut_data()?);
+    msg!(
+        "Registry migrated, {} escrows kept",
+        escrow_accounts.config.next_sequence - 1
+    );
+    Ok(())
+}
+
 /// First participant of escrow swap prepares an account with tokens and passes it along with requirements for the other side.
 /// On the deposit transaction the token account ownership is passed to the PDA.
+/// The owner may be a wallet or a PDA of another program signing via `invoke_signed`.
 /// The user may revoke escrow and reclaim this account later on.
-fn deposit(accounts: &[AccountInfo], program_id: &Pubkey, amount_expected: u32) -> ProgramResult {
+/// `set_terms` fills in the kind specific part of the new escrow record given the deposited account.
+fn deposit<F>(accounts: &[AccountInfo], program_id: &Pubkey, set_terms: F) -> ProgramResult
+where
+    F: FnOnce(&mut Escrow, &Clock, &spl_token::state::Account) -> ProgramResult,
+{
     let account_info_iter = &mut accounts.iter();
     let pda_account_info = next_account_info(account_info_iter)?;
     let owner_account_info = next_account_info(account_info_iter)?;
@@ -73,9 +157,11 @@ fn deposit(accounts: &[AccountInfo], program_id: &Pubkey, amount_expected: u32)
     let token_program = next_account_info(account_info_iter)?;
     let token_expected = next_account_info(account_info_iter)?;
 
-    assert!(owner_account_info.is_signer);
-    assert_eq!(*token_account_info.owner, spl_token::id());
-    assert!(token_account_info.is_writable);
+    verify_signer(owner_account_info)?;
+    if *token_account_info.owner != spl_token::id() {
+        return Err(ProgramError::IncorrectProgramId);
+    }
+    verify_writable(token_account_info)?;
     verify_rent_exemption(token_account_info)?;
 
     if pda_account_info.owner != program_id {
@@ -93,21 +179,14 @@ fn deposit(accounts: &[AccountInfo], program_id: &Pubkey, amount_expected: u32)
     }
 
     verify_pda(pda_account_info, program_id)?;
-
-    let mut escrow_accounts = Box::new(EscrowCollection::unpack_from_slice(
-        &pda_account_info.try_borrow_mut_data()?,
-    )?);
-    match escrow_accounts.find_next_available() {
-        Some(account) => {
-            account.active = true;
-            account.token_expected = *token_expected.key;
-            account.amount_expected = amount_expected;
-            account.holding_account = *token_account_info.key;
-            account.owner_account = *owner_account_info.key;
-            escrow_accounts.pack_into_slice(&mut pda_account_info.try_borrow_mut_data()?);
-        }
-        _ => return Err(throw_and_log(EscrowError::NoAvailableEscrowAccounts)),
-    }
+    open_escrow(
+        pda_account_info,
+        owner_account_info.key,
+        token_account_info.key,
+        token_expected.key,
+        &token_account_data,
+        set_terms,
+    )?;
 
     let owner_change_ix = set_authority(
         token_program.key,
@@ -132,10 +211,441 @@ fn deposit(accounts: &[AccountInfo], program_id: &Pubkey, amount_expected: u32)
     Ok(())
 }
 
+/// Stores a new escrow record of `token_account` in the registry under the next sequence,
+/// an account backs one active escrow at a time
+fn open_escrow<F>(
+    pda_account_info: &AccountInfo,
+    owner: &Pubkey,
+    token_account: &Pubkey,
+    token_expected: &Pubkey,
+    token_account_data: &spl_token::state::Account,
+    set_terms: F,
+) -> ProgramResult
+where
+    F: FnOnce(&mut Escrow, &Clock, &spl_token::state::Account) -> ProgramResult,
+{
+    let mut escrow_accounts = Box::new(EscrowCollection::unpack_from_slice(
+        &pda_account_info.try_borrow_mut_data()?,
+    )?);
+    // A delegated account stays with its owner, who may offer it again in any way
+    if escrow_accounts
+        .find_by_token_account(token_account)
+        .is_some()
+    {
+        msg!("Account {} is already deposited", token_account);
+        return Err(ProgramError::AccountAlreadyInitialized);
+    }
+    let sequence = escrow_accounts.config.next_sequence;
+    let clock = Clock::get()?;
+    escrow_accounts.config.next_sequence = sequence + 1;
+    match escrow_accounts.find_next_available() {
+        Some(account) => {
+            msg!("Escrow sequence: {}", sequence);
+            account.sequence = sequence;
+            account.deposit_slot = clock.slot;
+            account.deposit_timestamp = clock.unix_timestamp;
+            account.active = true;
+            account.token_expected = *token_expected;
+            account.holding_account = *token_account;
+            account.owner_account = *owner;
+            set_terms(account, &clock, token_account_data)?;
+            escrow_accounts.pack_into_slice(&mut pda_account_info.try_borrow_mut_data()?);
+            Ok(())
+        }
+        _ => Err(throw_and_log(EscrowError::NoAvailableEscrowAccounts)),
+    }
+}
+
+/// Deposit without custody: the owner keeps the token account and has approved the registry
+/// as a delegate, the delegated amount is offered. The taker pays into `proceeds_account`.
+fn deposit_delegated(
+    accounts: &[AccountInfo],
+    program_id: &Pubkey,
+    amount_expected: u32,
+) -> ProgramResult {
+    let account_info_iter = &mut accounts.iter();
+    let pda_account_info = next_account_info(account_info_iter)?;
+    let owner_account_info = next_account_info(account_info_iter)?;
+    let token_account_info = next_account_info(account_info_iter)?;
+    let token_expected = next_account_info(account_info_iter)?;
+    let proceeds_account_info = next_account_info(account_info_iter)?;
+
+    verify_signer(owner_account_info)?;
+    verify_writable(pda_account_info)?;
+    if *token_account_info.owner != spl_token::id()
+        || *proceeds_account_info.owner != spl_token::id()
+    {
+        return Err(ProgramError::IncorrectProgramId);
+    }
+    if pda_account_info.owner != program_id {
+        return Err(ProgramError::IncorrectProgramId);
+    }
+    verify_pda(pda_account_info, program_id)?;
+
+    let token_account_data = spl_token::state::Account::unpack(&token_account_info.data.borrow())?;
+    let proceeds_data = spl_token::state::Account::unpack(&proceeds_account_info.data.borrow())?;
+    if token_account_data.owner != *owner_account_info.key
+        || proceeds_data.owner != *owner_account_info.key
+    {
+        msg!("The provided owner is not the real owner of the token accounts.");
+        return Err(ProgramError::IllegalOwner);
+    }
+    if proceeds_data.mint != *token_expected.key {
+        msg!(
+            "Proceeds account is expected in {} mint",
+            token_expected.key
+        );
+        return Err(ProgramError::InvalidArgument);
+    }
+    // Any delegated amount is offered, but there has to be one
+    verify_delegation(&token_account_data, pda_account_info.key, 1)?;
+
+    open_escrow(
+        pda_account_info,
+        owner_account_info.key,
+        token_account_info.key,
+        token_expected.key,
+        &token_account_data,
+        |escrow, _, token_account| {
+            escrow.amount_expected = amount_expected;
+            escrow.kind = EscrowKind::Delegated;
+            escrow.set_terms(&DelegatedTerms {
+                amount: token_account.delegated_amount,
+                proceeds_account: *proceeds_account_info.key,
+            });
+            Ok(())
+        },
+    )?;
+    msg!(
+        "{} delegated tokens deposited, the account stays with its owner",
+        token_account_data.delegated_amount
+    );
+    Ok(())
+}
+
+/// Taker pays for a delegated deposit, which the registry transfers as the delegate.
+/// Fails with `InsufficientDelegation` or `DelegatedTokensSpent` if the maker has revoked the
+/// delegation or moved the tokens since the deposit.
+fn execute_delegated(
+    accounts: &[AccountInfo],
+    program_id: &Pubkey,
+    amount_expected: u64,
+    sequence: u64,
+) -> ProgramResult {
+    let account_info_iter = &mut accounts.iter();
+    let pda_account_info = next_account_info(account_info_iter)?;
+    let taker_account_info = next_account_info(account_info_iter)?;
+    let taker_source_info = next_account_info(account_info_iter)?;
+    let taker_destination_info = next_account_info(account_info_iter)?;
+    let holding_account_info = next_account_info(account_info_iter)?;
+    let proceeds_account_info = next_account_info(account_info_iter)?;
+    let token_program = next_account_info(account_info_iter)?;
+
+    verify_signer(taker_account_info)?;
+    verify_writable(pda_account_info)?;
+    verify_writable(holding_account_info)?;
+    let (_pda, bump_seed) = verify_pda(pda_account_info, program_id)?;
+    if pda_account_info.owner != program_id {
+        return Err(ProgramError::IncorrectProgramId);
+    }
+
+    let mut escrow_accounts = Box::new(EscrowCollection::unpack_from_slice(
+        &pda_account_info.try_borrow_data()?,
+    )?);
+    let target_escrow_account = escrow_accounts
+        .find_by_token_account(holding_account_info.key)
+        .ok_or_else(|| throw_and_log(EscrowError::NoAvailableEscrowAccounts))?;
+    verify_kind(target_escrow_account, EscrowKind::Delegated)?;
+    let terms: DelegatedTerms = target_escrow_account.terms()?;
+
+    if target_escrow_account.sequence != sequence {
+        msg!("Executor expected escrow sequence: {}", sequence);
+        return Err(throw_and_log(EscrowError::SequenceMismatch));
+    }
+    if terms.amount != amount_expected {
+        msg!("Executor expected: {} tokens", amount_expected);
+        msg!("Depositor provided: {} tokens", terms.amount);
+        return Err(throw_and_log(EscrowError::DepositTokenAmtMismatch));
+    }
+    if terms.proceeds_account != *proceeds_account_info.key {
+        msg!("Proceeds go to {}", terms.proceeds_account);
+        return Err(ProgramError::InvalidArgument);
+    }
+
+    let holding_data = spl_token::state::Account::unpack(&holding_account_info.data.borrow())?;
+    verify_delegation(&holding_data, pda_account_info.key, terms.amount)?;
+    if holding_data.amount < terms.amount {
+        msg!(
+            "Holding account has {} tokens, {} offered",
+            holding_data.amount,
+            terms.amount
+        );
+        return Err(throw_and_log(EscrowError::DelegatedTokensSpent));
+    }
+
+    msg!("Paying the maker...");
+    invoke(
+        &transfer(
+            token_program.key,
+            taker_source_info.key,
+            proceeds_account_info.key,
+            taker_account_info.key,
+            &[],
+            target_escrow_account.amount_expected as u64,
+        )?,
+        &[
+            taker_source_info.clone(),
+            proceeds_account_info.clone(),
+            taker_account_info.clone(),
+            token_program.clone(),
+        ],
+    )?;
+    msg!("Transferring the delegated maker tokens...");
+    invoke_signed(
+        &transfer(
+            token_program.key,
+            holding_account_info.key,
+            taker_destination_info.key,
+            pda_account_info.key,
+            &[],
+            terms.amount,
+        )?,
+        &[
+            holding_account_info.clone(),
+            taker_destination_info.clone(),
+            pda_account_info.clone(),
+            token_program.clone(),
+        ],
+        &[&[SEED, &[bump_seed]]],
+    )?;
+
+    target_escrow_account.reset();
+    escrow_accounts.pack_into_slice(&mut pda_account_info.try_borrow_mut_data()?);
+    Ok(())
+}
+
+/// Maker deposits up to `MAX_BUNDLE_LEGS` token accounts, passed after the fixed accounts,
+/// asking for a taker account of each of `wanted` in return
+fn create_bundle(
+    accounts: &[AccountInfo],
+    program_id: &Pubkey,
+    wanted: &[BundleAsset],
+) -> ProgramResult {
+    let account_info_iter = &mut accounts.iter();
+    let pda_account_info = next_account_info(account_info_iter)?;
+    let owner_account_info = next_account_info(account_info_iter)?;
+    let token_program = next_account_info(account_info_iter)?;
+    let holding_infos = account_info_iter.as_slice();
+
+    verify_signer(owner_account_info)?;
+    verify_writable(pda_account_info)?;
+    if pda_account_info.owner != program_id {
+        return Err(ProgramError::IncorrectProgramId);
+    }
+    verify_pda(pda_account_info, program_id)?;
+    if wanted.is_empty() || holding_infos.is_empty() || holding_infos.len() > MAX_BUNDLE_LEGS {
+        msg!(
+            "Bundles have 1 to {} accounts on each side, got {} deposited and {} wanted",
+            MAX_BUNDLE_LEGS,
+            holding_infos.len(),
+            wanted.len()
+        );
+        return Err(ProgramError::InvalidArgument);
+    }
+
+    let mut first_holding_data = None;
+    for (i, holding_info) in holding_infos.iter().enumerate() {
+        if *holding_info.owner != spl_token::id() {
+            return Err(ProgramError::IncorrectProgramId);
+        }
+        verify_writable(holding_info)?;
+        verify_rent_exemption(holding_info)?;
+        if holding_infos[..i]
+            .iter()
+            .any(|other| other.key == holding_info.key)
+        {
+            msg!("Account {} is deposited twice", holding_info.key);
+            return Err(ProgramError::InvalidArgument);
+        }
+        let holding_data = spl_token::state::Account::unpack(&holding_info.data.borrow())?;
+        if holding_data.owner != *owner_account_info.key {
+            msg!("The provided owner is not the real owner of this token account.");
+            return Err(ProgramError::IllegalOwner);
+        }
+        first_holding_data.get_or_insert(holding_data);
+    }
+
+    let mut terms = BundleTerms {
+        extra_holding_count: (holding_infos.len() - 1) as u8,
+        wanted_count: wanted.len() as u8,
+        ..BundleTerms::default()
+    };
+    for (holding, info) in terms.extra_holdings.iter_mut().zip(&holding_infos[1..]) {
+        *holding = *info.key;
+    }
+    terms.wanted[..wanted.len()].copy_from_slice(wanted);
+    open_escrow(
+        pda_account_info,
+        owner_account_info.key,
+        holding_infos[0].key,
+        &wanted[0].mint,
+        &first_holding_data.unwrap_or_default(),
+        |escrow, _, _| {
+            escrow.amount_expected = wanted[0].amount;
+            escrow.kind = EscrowKind::Bundle;
+            escrow.set_terms(&terms);
+            Ok(())
+        },
+    )?;
+
+    msg!("Calling the token program to transfer bundle token account ownership...");
+    for holding_info in holding_infos {
+        invoke(
+            &set_authority(
+                token_program.key,
+                holding_info.key,
+                Some(pda_account_info.key),
+                spl_token::instruction::AuthorityType::AccountOwner,
+                owner_account_info.key,
+                &[owner_account_info.key],
+            )?,
+            &[
+                holding_info.clone(),
+                owner_account_info.clone(),
+                token_program.clone(),
+            ],
+        )?;
+    }
+    msg!("Bundle of {} accounts deposited", holding_infos.len());
+    Ok(())
+}
+
+/// Taker swaps a bundle as a whole. The holding accounts, starting with the one the escrow is
+/// recorded by, come after the fixed accounts, followed by a taker account per wanted asset.
+fn execute_bundle(accounts: &[AccountInfo], program_id: &Pubkey, sequence: u64) -> ProgramResult {
+    let account_info_iter = &mut accounts.iter();
+    let pda_account_info = next_account_info(account_info_iter)?;
+    let taker_account_info = next_account_info(account_info_iter)?;
+    let token_program = next_account_info(account_info_iter)?;
+    let leg_infos = account_info_iter.as_slice();
+
+    verify_signer(taker_account_info)?;
+    verify_writable(pda_account_info)?;
+    let (_pda, bump_seed) = verify_pda(pda_account_info, program_id)?;
+    if pda_account_info.owner != program_id {
+        return Err(ProgramError::IncorrectProgramId);
+    }
+
+    let mut escrow_accounts = Box::new(EscrowCollection::unpack_from_slice(
+        &pda_account_info.try_borrow_data()?,
+    )?);
+    let holding_account = leg_infos
+        .first()
+        .ok_or(ProgramError::NotEnoughAccountKeys)?;
+    let target_escrow_account = escrow_accounts
+        .find_by_token_account(holding_account.key)
+        .ok_or_else(|| throw_and_log(EscrowError::NoAvailableEscrowAccounts))?;
+    verify_kind(target_escrow_account, EscrowKind::Bundle)?;
+    if target_escrow_account.sequence != sequence {
+        msg!("Executor expected escrow sequence: {}", sequence);
+        return Err(throw_and_log(EscrowError::SequenceMismatch));
+    }
+    let terms: BundleTerms = target_escrow_account.terms()?;
+
+    let holding_count = 1 + terms.extra_holdings().len();
+    if leg_infos.len() != holding_count + terms.wanted().len() {
+        msg!(
+            "Expected {} holding and {} taker accounts, got {} accounts",
+            holding_count,
+            terms.wanted().len(),
+            leg_infos.len()
+        );
+        return Err(ProgramError::NotEnoughAccountKeys);
+    }
+    let (holding_infos, taker_infos) = leg_infos.split_at(holding_count);
+    if holding_infos[1..]
+        .iter()
+        .map(|info| info.key)
+        .ne(terms.extra_holdings())
+    {
+        msg!("Holding accounts differ from the deposited ones");
+        return Err(ProgramError::InvalidArgument);
+    }
+
+    for (i, (taker_info, asset)) in taker_infos.iter().zip(terms.wanted()).enumerate() {
+        verify_writable(taker_info)?;
+        verify_rent_exemption(taker_info)?;
+        if taker_infos[..i]
+            .iter()
+            .any(|other| other.key == taker_info.key)
+        {
+            msg!("Account {} is offered twice", taker_info.key);
+            return Err(ProgramError::InvalidArgument);
+        }
+        let taker_data = spl_token::state::Account::unpack(&taker_info.data.borrow())?;
+        if taker_data.mint != asset.mint {
+            msg!("Leg {} expects {} mint", i, asset.mint);
+            return Err(throw_and_log(EscrowError::ExecutorTokenMintMismatch));
+        }
+        if taker_data.amount as u32 != asset.amount {
+            msg!("Leg {} expects {} tokens", i, asset.amount);
+            return Err(throw_and_log(EscrowError::ExecutorTokenAmtMismatch));
+        }
+    }
+
+    msg!("Calling the token program to transfer bundle token account ownership...");
+    for taker_info in taker_infos {
+        invoke(
+            &set_authority(
+                token_program.key,
+                taker_info.key,
+                Some(&target_escrow_account.owner_account),
+                spl_token::instruction::AuthorityType::AccountOwner,
+                taker_account_info.key,
+                &[taker_account_info.key],
+            )?,
+            &[
+                taker_info.clone(),
+                taker_account_info.clone(),
+                token_program.clone(),
+            ],
+        )?;
+    }
+    for holding_info in holding_infos {
+        release_account(
+            token_program,
+            holding_info,
+            pda_account_info,
+            bump_seed,
+            taker_account_info.key,
+        )?;
+    }
+    msg!("Bundle swapped successfully!");
+
+    target_escrow_account.reset();
+    escrow_accounts.pack_into_slice(&mut pda_account_info.try_borrow_mut_data()?);
+    Ok(())
+}
+
+/// Taker side of a single swap, `amount_expected` is the taker requirement for the deposit.
+/// `sequence` is the one of the escrow the taker saw.
+struct ExecuteLeg<'a, 'info> {
+    token_account_info: &'a AccountInfo<'info>,
+    token_expected: &'a AccountInfo<'info>,
+    deposit_account_info: &'a AccountInfo<'info>,
+    amount_expected: u32,
+    sequence: u64,
+}
+
 /// Execution is performed by the second party of the escrow transaction.
 /// In the same way, this party has to pass a prepared token account along with requirements and public key of first party's account.
 /// Once all verifications are passed, the transaction makes accounts swap and cleans up the escrow registry in PDA.
-fn execute(accounts: &[AccountInfo], program_id: &Pubkey, amount_expected: u32) -> ProgramResult {
+fn execute(
+    accounts: &[AccountInfo],
+    program_id: &Pubkey,
+    amount_expected: u32,
+    sequence: u64,
+) -> ProgramResult {
     let account_info_iter = &mut accounts.iter();
     let pda_account_info = next_account_info(account_info_iter)?;
     let owner_account_info = next_account_info(account_info_iter)?;
@@ -144,13 +654,108 @@ fn execute(accounts: &[AccountInfo], program_id: &Pubkey, amount_expected: u32)
     let token_expected = next_account_info(account_info_iter)?;
     let deposit_account_info = next_account_info(account_info_iter)?;
 
-    assert!(deposit_account_info.is_writable);
-    assert!(token_account_info.is_writable);
-    verify_rent_exemption(token_account_info)?;
+    verify_signer(owner_account_info)?;
+    let (_pda, bump_seed) = verify_pda(pda_account_info, program_id)?;
+
+    let mut escrow_accounts = Box::new(EscrowCollection::unpack_from_slice(
+        &pda_account_info.try_borrow_mut_data()?,
+    )?);
+
+    execute_leg(
+        pda_account_info,
+        bump_seed,
+        owner_account_info,
+        token_program,
+        &mut escrow_accounts,
+        ExecuteLeg {
+            token_account_info,
+            token_expected,
+            deposit_account_info,
+            amount_expected,
+            sequence,
+        },
+    )?;
+
+    escrow_accounts.pack_into_slice(&mut pda_account_info.try_borrow_mut_data()?);
+
+    Ok(())
+}
+
+/// Same as `execute` for several deposits at once, the remaining accounts come in
+/// (token account, token expected, deposit account) groups, one per `amounts_expected` item.
+/// Any failing leg fails the whole instruction, so either all the swaps happen or none.
+fn execute_batch(
+    accounts: &[AccountInfo],
+    program_id: &Pubkey,
+    amounts_expected: &[u32],
+    sequences: &[u64],
+) -> ProgramResult {
+    let account_info_iter = &mut accounts.iter();
+    let pda_account_info = next_account_info(account_info_iter)?;
+    let owner_account_info = next_account_info(account_info_iter)?;
+    let token_program = next_account_info(account_info_iter)?;
+
+    verify_signer(owner_account_info)?;
+    let (_pda, bump_seed) = verify_pda(pda_account_info, program_id)?;
+
+    if account_info_iter.len() != amounts_expected.len() * 3 {
+        msg!(
+            "Expected {} account groups for the batch, got {} accounts",
+            amounts_expected.len(),
+            account_info_iter.len()
+        );
+        return Err(ProgramError::NotEnoughAccountKeys);
+    }
 
     let mut escrow_accounts = Box::new(EscrowCollection::unpack_from_slice(
         &pda_account_info.try_borrow_mut_data()?,
     )?);
+
+    for (i, (&amount_expected, &sequence)) in amounts_expected.iter().zip(sequences).enumerate() {
+        msg!("Executing batch leg {}...", i);
+        execute_leg(
+            pda_account_info,
+            bump_seed,
+            owner_account_info,
+            token_program,
+            &mut escrow_accounts,
+            ExecuteLeg {
+                token_account_info: next_account_info(account_info_iter)?,
+                token_expected: next_account_info(account_info_iter)?,
+                deposit_account_info: next_account_info(account_info_iter)?,
+                amount_expected,
+                sequence,
+            },
+        )?;
+    }
+
+    escrow_accounts.pack_into_slice(&mut pda_account_info.try_borrow_mut_data()?);
+
+    Ok(())
+}
+
+/// Verifies both sides of a swap and exchanges the account ownership.
+/// The escrow record is reset in `escrow_accounts`, storing it is up to the caller.
+fn execute_leg<'info>(
+    pda_account_info: &AccountInfo<'info>,
+    bump_seed: u8,
+    owner_account_info: &AccountInfo<'info>,
+    token_program: &AccountInfo<'info>,
+    escrow_accounts: &mut EscrowCollection,
+    leg: ExecuteLeg<'_, 'info>,
+) -> ProgramResult {
+    let ExecuteLeg {
+        token_account_info,
+        token_expected,
+        deposit_account_info,
+        amount_expected,
+        sequence,
+    } = leg;
+
+    verify_writable(deposit_account_info)?;
+    verify_writable(token_account_info)?;
+    verify_rent_exemption(token_account_info)?;
+
     let token_account_data = spl_token::state::Account::unpack(&token_account_info.data.borrow())?;
 
     let escrow_opt = escrow_accounts.find_by_token_account(deposit_account_info.key);
@@ -160,8 +765,9 @@ fn execute(accounts: &[AccountInfo], program_id: &Pubkey, amount_expected: u32)
     };
 
     let target_escrow_account = escrow_opt.unwrap();
+    verify_kind(target_escrow_account, EscrowKind::Swap)?;
 
-    let (pda, bump_seed) = verify_pda(pda_account_info, program_id)?;
+    let pda = *pda_account_info.key;
     let deposit_account_data =
         spl_token::state::Account::unpack(&deposit_account_info.data.borrow())?;
 
@@ -174,12 +780,24 @@ fn execute(accounts: &[AccountInfo], program_id: &Pubkey, amount_expected: u32)
         return Err(throw_and_log(EscrowError::NotInitialized));
     }
 
-    // Actors' expectations checks
-    if target_escrow_account.amount_expected != token_account_data.amount as u32 {
+    if target_escrow_account.sequence != sequence {
+        msg!("Executor expected escrow sequence: {}", sequence);
+        msg!(
+            "Current escrow sequence: {}",
+            target_escrow_account.sequence
+        );
+        return Err(throw_and_log(EscrowError::SequenceMismatch));
+    }
+
+    // Actors' expectations checks, the executor may pay in any mint the depositor accepts
+    let accepted_amount = target_escrow_account.amount_expected_in(&token_account_data.mint);
+    let amount_expected_by_depositor =
+        accepted_amount.unwrap_or(target_escrow_account.amount_expected);
+    if amount_expected_by_depositor != token_account_data.amount as u32 {
         msg!("Error: Depositor and executor expectations are not met");
         msg!(
             "Depositor expected: {} tokens",
-            target_escrow_account.amount_expected
+            amount_expected_by_depositor
         );
         msg!(
             "Executor provided: {} tokens",
@@ -198,7 +816,7 @@ fn execute(accounts: &[AccountInfo], program_id: &Pubkey, amount_expected: u32)
         return Err(throw_and_log(EscrowError::DepositTokenAmtMismatch));
     }
 
-    if target_escrow_account.token_expected != token_account_data.mint {
+    if accepted_amount.is_none() {
         msg!("Error: Depositor and executor expectations are not met");
         msg!(
             "Depositor expected: {} mint",
@@ -271,27 +889,1689 @@ fn execute(accounts: &[AccountInfo], program_id: &Pubkey, amount_expected: u32)
     );
 
     target_escrow_account.reset();
-    escrow_accounts.pack_into_slice(&mut pda_account_info.try_borrow_mut_data()?);
 
     Ok(())
 }
 
-/// First party user may revoke and reclaim the token account if the swap hasn't happen
-fn reclaim(accounts: &[AccountInfo], program_id: &Pubkey) -> ProgramResult {
+/// Registry admin updates the registry settings
+fn configure(
+    accounts: &[AccountInfo],
+    program_id: &Pubkey,
+    match_reward_lamports: u64,
+    reclaim_delay: ReclaimDelay,
+) -> ProgramResult {
     let account_info_iter = &mut accounts.iter();
     let pda_account_info = next_account_info(account_info_iter)?;
-    let owner_account_info = next_account_info(account_info_iter)?;
-    let token_account_info = next_account_info(account_info_iter)?;
-    let token_program = next_account_info(account_info_iter)?;
+    let admin_account_info = next_account_info(account_info_iter)?;
 
-    assert!(token_account_info.is_writable);
-    let (_pda, bump_seed) = verify_pda(pda_account_info, program_id)?;
+    verify_signer(admin_account_info)?;
+    verify_pda(pda_account_info, program_id)?;
+    if pda_account_info.owner != program_id {
+        return Err(ProgramError::IncorrectProgramId);
+    }
+
+    let mut escrow_accounts = Box::new(EscrowCollection::unpack_from_slice(
+        &pda_account_info.try_borrow_data()?,
+    )?);
+    if escrow_accounts.config.admin != *admin_account_info.key {
+        msg!("Only the registry admin may change its settings");
+        msg!("Real admin: {}", escrow_accounts.config.admin);
+        return Err(ProgramError::IllegalOwner);
+    }
+
+    if !reclaim_delay.is_within_bounds() {
+        msg!(
+            "Reclaim delay {:?} exceeds the allowed maximum",
+            reclaim_delay
+        );
+        return Err(ProgramError::InvalidArgument);
+    }
+
+    escrow_accounts.config.match_reward_lamports = match_reward_lamports;
+    escrow_accounts.config.reclaim_delay = reclaim_delay;
+    escrow_accounts.pack_into_slice(&mut pda_account_info.try_borrow_mut_data()?);
+
+    msg!("Match reward is set to {} lamports", match_reward_lamports);
+    msg!("Reclaim delay is set to {:?}", reclaim_delay);
+    Ok(())
+}
+
+/// Anyone may match two deposits whose terms satisfy each other.
+/// Each holding account goes to the owner of the other escrow and the matcher gets the configured reward
+/// out of the registry balance above its rent exemption.
+fn match_escrows(accounts: &[AccountInfo], program_id: &Pubkey) -> ProgramResult {
+    let account_info_iter = &mut accounts.iter();
+    let pda_account_info = next_account_info(account_info_iter)?;
+    let matcher_account_info = next_account_info(account_info_iter)?;
+    let token_program = next_account_info(account_info_iter)?;
+    let first_account_info = next_account_info(account_info_iter)?;
+    let second_account_info = next_account_info(account_info_iter)?;
+
+    verify_signer(matcher_account_info)?;
+    verify_writable(pda_account_info)?;
+    verify_writable(first_account_info)?;
+    verify_writable(second_account_info)?;
+    let (pda, bump_seed) = verify_pda(pda_account_info, program_id)?;
+    if pda_account_info.owner != program_id {
+        return Err(ProgramError::IncorrectProgramId);
+    }
+    if first_account_info.key == second_account_info.key {
+        msg!("An escrow can't be matched with itself");
+        return Err(ProgramError::InvalidArgument);
+    }
+
+    let mut escrow_accounts = Box::new(EscrowCollection::unpack_from_slice(
+        &pda_account_info.try_borrow_data()?,
+    )?);
+    let first = *escrow_accounts
+        .find_by_token_account(first_account_info.key)
+        .ok_or_else(|| throw_and_log(EscrowError::NoAvailableEscrowAccounts))?;
+    let second = *escrow_accounts
+        .find_by_token_account(second_account_info.key)
+        .ok_or_else(|| throw_and_log(EscrowError::NoAvailableEscrowAccounts))?;
+    verify_kind(&first, EscrowKind::Swap)?;
+    verify_kind(&second, EscrowKind::Swap)?;
+
+    let first_data = spl_token::state::Account::unpack(&first_account_info.data.borrow())?;
+    let second_data = spl_token::state::Account::unpack(&second_account_info.data.borrow())?;
+    if first_data.owner != pda || second_data.owner != pda {
+        msg!("Matched token accounts are expected to be owned by the registry");
+        return Err(ProgramError::IllegalOwner);
+    }
+
+    // The first escrow is checked as a depositor, the second one as an executor
+    let first_accepts = first.amount_expected_in(&second_data.mint);
+    let second_accepts = second.amount_expected_in(&first_data.mint);
+    let first_expects = first_accepts.unwrap_or(first.amount_expected);
+    if first_expects != second_data.amount as u32 {
+        msg!("First escrow expected: {} tokens", first_expects);
+        msg!("Second escrow provides: {} tokens", second_data.amount);
+        return Err(throw_and_log(EscrowError::ExecutorTokenAmtMismatch));
+    }
+    if first_accepts.is_none() {
+        msg!("First escrow expected: {} mint", first.token_expected);
+        msg!("Second escrow provides: {} mint", second_data.mint);
+        return Err(throw_and_log(EscrowError::ExecutorTokenMintMismatch));
+    }
+    let second_expects = second_accepts.unwrap_or(second.amount_expected);
+    if second_expects != first_data.amount as u32 {
+        msg!("Second escrow expected: {} tokens", second_expects);
+        msg!("First escrow provides: {} tokens", first_data.amount);
+        return Err(throw_and_log(EscrowError::DepositTokenAmtMismatch));
+    }
+    if second_accepts.is_none() {
+        msg!("Second escrow expected: {} mint", second.token_expected);
+        msg!("First escrow provides: {} mint", first_data.mint);
+        return Err(throw_and_log(EscrowError::DepositTokenMintMismatch));
+    }
+
+    for (token_account_info, new_owner) in [
+        (first_account_info, &second.owner_account),
+        (second_account_info, &first.owner_account),
+    ] {
+        let owner_change_ix = set_authority(
+            token_program.key,
+            token_account_info.key,
+            Some(new_owner),
+            spl_token::instruction::AuthorityType::AccountOwner,
+            &pda,
+            &[],
+        )?;
+
+        msg!("Calling the token program to transfer matched token account ownership...");
+        invoke_signed(
+            &owner_change_ix,
+            &[
+                token_account_info.clone(),
+                pda_account_info.clone(),
+                token_program.clone(),
+            ],
+            &[&[SEED, &[bump_seed]]],
+        )?;
+    }
+
+    let reward = escrow_accounts.config.match_reward_lamports;
+    for holding_account in [first_account_info.key, second_account_info.key] {
+        if let Some(escrow) = escrow_accounts.find_by_token_account(holding_account) {
+            escrow.reset();
+        }
+    }
+    escrow_accounts.pack_into_slice(&mut pda_account_info.try_borrow_mut_data()?);
+    pay_match_reward(pda_account_info, matcher_account_info, reward)?;
+
+    msg!(
+        "Escrows {} and {} matched successfully!",
+        first_account_info.key,
+        second_account_info.key
+    );
+    Ok(())
+}
+
+/// Pays `reward` to `matcher_account_info` out of the registry balance above its rent exemption,
+/// if there is enough of it
+fn pay_match_reward(
+    pda_account_info: &AccountInfo,
+    matcher_account_info: &AccountInfo,
+    reward: u64,
+) -> ProgramResult {
+    if reward > 0 {
+        let rent_exempt = Rent::default().minimum_balance(pda_account_info.data_len());
+        let available = pda_account_info.lamports().saturating_sub(rent_exempt);
+        if available < reward {
+            msg!(
+                "Registry holds {} spare lamports, the match reward is not paid",
+                available
+            );
+        } else {
+            **pda_account_info.try_borrow_mut_lamports()? -= reward;
+            **matcher_account_info.try_borrow_mut_lamports()? += reward;
+            msg!("Matcher is rewarded with {} lamports", reward);
+        }
+    }
+    Ok(())
+}
+
+/// Anyone may settle a ring of three or more swap deposits, passed after the fixed accounts in
+/// ring order: each holding account goes to the owner of the next escrow, whose terms it has to
+/// satisfy, and the last one to the owner of the first. The executor gets the match reward.
+fn execute_ring(accounts: &[AccountInfo], program_id: &Pubkey) -> ProgramResult {
+    let account_info_iter = &mut accounts.iter();
+    let pda_account_info = next_account_info(account_info_iter)?;
+    let executor_account_info = next_account_info(account_info_iter)?;
+    let token_program = next_account_info(account_info_iter)?;
+    let holding_infos = account_info_iter.as_slice();
+
+    verify_signer(executor_account_info)?;
+    verify_writable(pda_account_info)?;
+    let (pda, bump_seed) = verify_pda(pda_account_info, program_id)?;
+    if pda_account_info.owner != program_id {
+        return Err(ProgramError::IncorrectProgramId);
+    }
+    if holding_infos.len() < 3 {
+        msg!("A ring takes at least 3 deposits, two are settled by Match");
+        return Err(ProgramError::InvalidArgument);
+    }
+    for (i, holding_info) in holding_infos.iter().enumerate() {
+        verify_writable(holding_info)?;
+        if holding_infos[..i]
+            .iter()
+            .any(|other| other.key == holding_info.key)
+        {
+            msg!("Deposit {} is in the ring twice", holding_info.key);
+            return Err(ProgramError::InvalidArgument);
+        }
+    }
+
+    let mut escrow_accounts = Box::new(EscrowCollection::unpack_from_slice(
+        &pda_account_info.try_borrow_data()?,
+    )?);
+    let legs = holding_infos
+        .iter()
+        .map(|holding_info| {
+            let escrow = *escrow_accounts
+                .find_by_token_account(holding_info.key)
+                .ok_or_else(|| throw_and_log(EscrowError::NoAvailableEscrowAccounts))?;
+            verify_kind(&escrow, EscrowKind::Swap)?;
+            Ok(escrow)
+        })
+        .collect::<Result<Vec<_>, ProgramError>>()?;
+
+    for (i, holding_info) in holding_infos.iter().enumerate() {
+        let holding_data = spl_token::state::Account::unpack(&holding_info.data.borrow())?;
+        if holding_data.owner != pda {
+            msg!("Ring token accounts are expected to be owned by the registry");
+            return Err(ProgramError::IllegalOwner);
+        }
+        let receiver = &legs[(i + 1) % legs.len()];
+        let accepted_amount = receiver.amount_expected_in(&holding_data.mint);
+        let amount_expected = accepted_amount.unwrap_or(receiver.amount_expected);
+        if amount_expected != holding_data.amount as u32 {
+            msg!("Leg {} provides {} tokens", i, holding_data.amount);
+            msg!("Next escrow expected: {} tokens", amount_expected);
+            return Err(throw_and_log(EscrowError::ExecutorTokenAmtMismatch));
+        }
+        if accepted_amount.is_none() {
+            msg!("Leg {} provides {} mint", i, holding_data.mint);
+            msg!("Next escrow expected: {} mint", receiver.token_expected);
+            return Err(throw_and_log(EscrowError::ExecutorTokenMintMismatch));
+        }
+    }
+
+    msg!("Calling the token program to transfer ring token account ownership...");
+    for (i, holding_info) in holding_infos.iter().enumerate() {
+        release_account(
+            token_program,
+            holding_info,
+            pda_account_info,
+            bump_seed,
+            &legs[(i + 1) % legs.len()].owner_account,
+        )?;
+    }
+
+    let reward = escrow_accounts.config.match_reward_lamports;
+    for holding_info in holding_infos {
+        if let Some(escrow) = escrow_accounts.find_by_token_account(holding_info.key) {
+            escrow.reset();
+        }
+    }
+    escrow_accounts.pack_into_slice(&mut pda_account_info.try_borrow_mut_data()?);
+    pay_match_reward(pda_account_info, executor_account_info, reward)?;
+
+    msg!(
+        "Ring of {} deposits settled successfully!",
+        holding_infos.len()
+    );
+    Ok(())
+}
+
+/// Depositor changes the terms of an active escrow in place, keeping the holding account in escrow.
+/// The escrow gets a new sequence so that executions aimed at the old terms fail.
+fn amend(accounts: &[AccountInfo], program_id: &Pubkey, amount_expected: u32) -> ProgramResult {
+    let account_info_iter = &mut accounts.iter();
+    let pda_account_info = next_account_info(account_info_iter)?;
+    let owner_account_info = next_account_info(account_info_iter)?;
+    let token_account_info = next_account_info(account_info_iter)?;
+    let token_expected = next_account_info(account_info_iter)?;
+
+    verify_signer(owner_account_info)?;
+    verify_writable(pda_account_info)?;
+    verify_pda(pda_account_info, program_id)?;
+    if pda_account_info.owner != program_id {
+        return Err(ProgramError::IncorrectProgramId);
+    }
+
+    let mut escrow_accounts = Box::new(EscrowCollection::unpack_from_slice(
+        &pda_account_info.try_borrow_data()?,
+    )?);
+    let sequence = escrow_accounts.config.next_sequence;
+    escrow_accounts.config.next_sequence = sequence + 1;
+
+    let target_escrow_account = escrow_accounts
+        .find_by_token_account(token_account_info.key)
+        .ok_or_else(|| throw_and_log(EscrowError::NoAvailableEscrowAccounts))?;
+
+    if target_escrow_account.owner_account != *owner_account_info.key {
+        msg!("Attempt to amend non-owned escrow");
+        msg!("Claimed owner: {}", owner_account_info.key);
+        msg!("Real owner: {}", target_escrow_account.owner_account);
+        return Err(ProgramError::IllegalOwner);
+    }
+    verify_kind(target_escrow_account, EscrowKind::Swap)?;
+
+    msg!(
+        "Escrow terms: {} tokens of {} mint -> {} tokens of {} mint",
+        target_escrow_account.amount_expected,
+        target_escrow_account.token_expected,
+        amount_expected,
+        token_expected.key
+    );
+    target_escrow_account.amount_expected = amount_expected;
+    target_escrow_account.token_expected = *token_expected.key;
+    target_escrow_account.sequence = sequence;
+    // Alternatives were quoted against the old terms
+    target_escrow_account.set_terms(&SwapTerms::default());
+    events::emit(events::ESCROW_AMENDED, target_escrow_account);
+
+    escrow_accounts.pack_into_slice(&mut pda_account_info.try_borrow_mut_data()?);
+    msg!("Escrow amended, new sequence: {}", sequence);
+    Ok(())
+}
+
+/// Depositor adds tokens to the escrowed account (`withdraw` is false) or takes some of them back,
+/// setting `amount_expected` for the new deposit size.
+/// Withdrawals are subject to the reclaim delay as a withdrawal of everything amounts to a reclaim.
+fn adjust_deposit(
+    accounts: &[AccountInfo],
+    program_id: &Pubkey,
+    amount: u64,
+    amount_expected: u32,
+    withdraw: bool,
+) -> ProgramResult {
+    let account_info_iter = &mut accounts.iter();
+    let pda_account_info = next_account_info(account_info_iter)?;
+    let owner_account_info = next_account_info(account_info_iter)?;
+    let token_account_info = next_account_info(account_info_iter)?;
+    let counterpart_account_info = next_account_info(account_info_iter)?;
+    let token_program = next_account_info(account_info_iter)?;
+
+    verify_signer(owner_account_info)?;
+    verify_writable(pda_account_info)?;
+    verify_writable(token_account_info)?;
+    verify_writable(counterpart_account_info)?;
+    let (pda, bump_seed) = verify_pda(pda_account_info, program_id)?;
+    if pda_account_info.owner != program_id {
+        return Err(ProgramError::IncorrectProgramId);
+    }
+
+    let mut escrow_accounts = Box::new(EscrowCollection::unpack_from_slice(
+        &pda_account_info.try_borrow_data()?,
+    )?);
+    let reclaim_delay = escrow_accounts.config.reclaim_delay;
+    let sequence = escrow_accounts.config.next_sequence;
+    escrow_accounts.config.next_sequence = sequence + 1;
+
+    let target_escrow_account = escrow_accounts
+        .find_by_token_account(token_account_info.key)
+        .ok_or_else(|| throw_and_log(EscrowError::NoAvailableEscrowAccounts))?;
+
+    if target_escrow_account.owner_account != *owner_account_info.key {
+        msg!("Attempt to change non-owned escrow");
+        msg!("Claimed owner: {}", owner_account_info.key);
+        msg!("Real owner: {}", target_escrow_account.owner_account);
+        return Err(ProgramError::IllegalOwner);
+    }
+    verify_kind(target_escrow_account, EscrowKind::Swap)?;
+
+    let transfer_ix = if withdraw {
+        verify_reclaim_delay(target_escrow_account, reclaim_delay)?;
+        transfer(
+            token_program.key,
+            token_account_info.key,
+            counterpart_account_info.key,
+            &pda,
+            &[],
+            amount,
+        )?
+    } else {
+        transfer(
+            token_program.key,
+            counterpart_account_info.key,
+            token_account_info.key,
+            owner_account_info.key,
+            &[],
+            amount,
+        )?
+    };
+
+    msg!("Calling the token program to transfer {} tokens...", amount);
+    if withdraw {
+        invoke_signed(
+            &transfer_ix,
+            &[
+                token_account_info.clone(),
+                counterpart_account_info.clone(),
+                pda_account_info.clone(),
+                token_program.clone(),
+            ],
+            &[&[SEED, &[bump_seed]]],
+        )?;
+    } else {
+        invoke(
+            &transfer_ix,
+            &[
+                counterpart_account_info.clone(),
+                token_account_info.clone(),
+                owner_account_info.clone(),
+                token_program.clone(),
+            ],
+        )?;
+    }
+
+    target_escrow_account.amount_expected = amount_expected;
+    target_escrow_account.sequence = sequence;
+    // Alternatives were quoted for the old deposit size
+    target_escrow_account.set_terms(&SwapTerms::default());
+    events::emit(
+        if withdraw {
+            events::ESCROW_WITHDRAWN
+        } else {
+            events::ESCROW_TOPPED_UP
+        },
+        target_escrow_account,
+    );
+
+    escrow_accounts.pack_into_slice(&mut pda_account_info.try_borrow_mut_data()?);
+    msg!(
+        "Escrow now expects {} tokens, new sequence: {}",
+        amount_expected,
+        sequence
+    );
+    Ok(())
+}
+
+/// Passes the ownership of a token account held by the registry to `new_owner`
+fn release_account<'info>(
+    token_program: &AccountInfo<'info>,
+    token_account_info: &AccountInfo<'info>,
+    pda_account_info: &AccountInfo<'info>,
+    bump_seed: u8,
+    new_owner: &Pubkey,
+) -> ProgramResult {
+    let owner_change_ix = set_authority(
+        token_program.key,
+        token_account_info.key,
+        Some(new_owner),
+        spl_token::instruction::AuthorityType::AccountOwner,
+        pda_account_info.key,
+        &[],
+    )?;
+    invoke_signed(
+        &owner_change_ix,
+        &[
+            token_account_info.clone(),
+            pda_account_info.clone(),
+            token_program.clone(),
+        ],
+        &[&[SEED, &[bump_seed]]],
+    )
+}
+
+/// Bidder offers the whole `bid_account` for the auctioned holding account.
+/// In an English auction the bid account is locked by the registry until outbid, when it returns
+/// to the bidder, or the auction end. The previous highest bid account is to be passed last.
+/// In a Dutch auction a bid covering the current price takes the holding account at once.
+fn bid(accounts: &[AccountInfo], program_id: &Pubkey) -> ProgramResult {
+    let account_info_iter = &mut accounts.iter();
+    let pda_account_info = next_account_info(account_info_iter)?;
+    let bidder_account_info = next_account_info(account_info_iter)?;
+    let bid_account_info = next_account_info(account_info_iter)?;
+    let token_program = next_account_info(account_info_iter)?;
+    let holding_account_info = next_account_info(account_info_iter)?;
+    let previous_bid_account_info = account_info_iter.next();
+
+    verify_signer(bidder_account_info)?;
+    verify_writable(pda_account_info)?;
+    verify_writable(bid_account_info)?;
+    verify_writable(holding_account_info)?;
+    verify_rent_exemption(bid_account_info)?;
+    let (_pda, bump_seed) = verify_pda(pda_account_info, program_id)?;
+    if pda_account_info.owner != program_id {
+        return Err(ProgramError::IncorrectProgramId);
+    }
+
+    let mut escrow_accounts = Box::new(EscrowCollection::unpack_from_slice(
+        &pda_account_info.try_borrow_data()?,
+    )?);
+    let auction = escrow_accounts
+        .find_by_token_account(holding_account_info.key)
+        .ok_or_else(|| throw_and_log(EscrowError::NoAvailableEscrowAccounts))?;
+    let mut terms: AuctionTerms = auction.terms()?;
+
+    let bid_data = spl_token::state::Account::unpack(&bid_account_info.data.borrow())?;
+    if bid_data.owner != *bidder_account_info.key {
+        msg!("The provided owner is not the real owner of the bid account.");
+        return Err(ProgramError::IllegalOwner);
+    }
+    if bid_data.mint != auction.token_expected {
+        msg!("Auction expects bids in {} mint", auction.token_expected);
+        return Err(throw_and_log(EscrowError::ExecutorTokenMintMismatch));
+    }
+
+    let now = Clock::get()?.unix_timestamp;
+    if now >= terms.end_timestamp {
+        return Err(throw_and_log(EscrowError::AuctionEnded));
+    }
+
+    let bid_owner_change_ix = |new_owner: &Pubkey| {
+        set_authority(
+            token_program.key,
+            bid_account_info.key,
+            Some(new_owner),
+            spl_token::instruction::AuthorityType::AccountOwner,
+            bidder_account_info.key,
+            &[],
+        )
+    };
+    let bid_accounts = [
+        bid_account_info.clone(),
+        bidder_account_info.clone(),
+        token_program.clone(),
+    ];
+
+    match auction.kind {
+        EscrowKind::EnglishAuction => {
+            let minimum = terms.reserve_price.max(terms.highest_bid.saturating_add(1));
+            if bid_data.amount < minimum {
+                msg!(
+                    "Bid of {} tokens, at least {} expected",
+                    bid_data.amount,
+                    minimum
+                );
+                return Err(throw_and_log(EscrowError::BidTooLow));
+            }
+
+            if terms.highest_bid > 0 {
+                let previous_bid_account_info = previous_bid_account_info
+                    .filter(|info| *info.key == terms.bid_account)
+                    .ok_or_else(|| {
+                        msg!("Outbid account {} is to be refunded", terms.bid_account);
+                        ProgramError::NotEnoughAccountKeys
+                    })?;
+                msg!("Refunding the outbid account...");
+                release_account(
+                    token_program,
+                    previous_bid_account_info,
+                    pda_account_info,
+                    bump_seed,
+                    &terms.highest_bidder,
+                )?;
+            }
+
+            msg!("Locking the bid account...");
+            invoke(&bid_owner_change_ix(pda_account_info.key)?, &bid_accounts)?;
+
+            terms.highest_bid = bid_data.amount;
+            terms.highest_bidder = *bidder_account_info.key;
+            terms.bid_account = *bid_account_info.key;
+            auction.set_terms(&terms);
+            msg!("Highest bid: {} tokens", bid_data.amount);
+        }
+        EscrowKind::DutchAuction => {
+            let price = terms.current_price(auction.deposit_timestamp, now);
+            if bid_data.amount < price {
+                msg!(
+                    "Bid of {} tokens, current price is {}",
+                    bid_data.amount,
+                    price
+                );
+                return Err(throw_and_log(EscrowError::BidTooLow));
+            }
+
+            msg!("Passing the bid account to the seller...");
+            invoke(&bid_owner_change_ix(&auction.owner_account)?, &bid_accounts)?;
+            release_account(
+                token_program,
+                holding_account_info,
+                pda_account_info,
+                bump_seed,
+                bidder_account_info.key,
+            )?;
+            msg!("Auction won for {} tokens", bid_data.amount);
+            auction.reset();
+        }
+        _ => return Err(throw_and_log(EscrowError::WrongEscrowKind)),
+    }
+
+    escrow_accounts.pack_into_slice(&mut pda_account_info.try_borrow_mut_data()?);
+    Ok(())
+}
+
+/// Anyone may close an English auction after its end time.
+/// The holding account goes to the highest bidder and the bid account to the seller,
+/// without bids the holding account returns to the seller.
+fn settle_auction(accounts: &[AccountInfo], program_id: &Pubkey) -> ProgramResult {
+    let account_info_iter = &mut accounts.iter();
+    let pda_account_info = next_account_info(account_info_iter)?;
+    let token_program = next_account_info(account_info_iter)?;
+    let holding_account_info = next_account_info(account_info_iter)?;
+    let bid_account_info = account_info_iter.next();
+
+    verify_writable(pda_account_info)?;
+    verify_writable(holding_account_info)?;
+    let (_pda, bump_seed) = verify_pda(pda_account_info, program_id)?;
+    if pda_account_info.owner != program_id {
+        return Err(ProgramError::IncorrectProgramId);
+    }
+
+    let mut escrow_accounts = Box::new(EscrowCollection::unpack_from_slice(
+        &pda_account_info.try_borrow_data()?,
+    )?);
+    let auction = escrow_accounts
+        .find_by_token_account(holding_account_info.key)
+        .ok_or_else(|| throw_and_log(EscrowError::NoAvailableEscrowAccounts))?;
+    verify_kind(auction, EscrowKind::EnglishAuction)?;
+    let terms: AuctionTerms = auction.terms()?;
+    if Clock::get()?.unix_timestamp < terms.end_timestamp {
+        return Err(throw_and_log(EscrowError::AuctionNotEnded));
+    }
+
+    if terms.highest_bid > 0 {
+        let bid_account_info = bid_account_info
+            .filter(|info| *info.key == terms.bid_account)
+            .ok_or_else(|| {
+                msg!("Winning bid account {} is expected", terms.bid_account);
+                ProgramError::NotEnoughAccountKeys
+            })?;
+        msg!("Passing the winning bid to the seller...");
+        release_account(
+            token_program,
+            bid_account_info,
+            pda_account_info,
+            bump_seed,
+            &auction.owner_account,
+        )?;
+        release_account(
+            token_program,
+            holding_account_info,
+            pda_account_info,
+            bump_seed,
+            &terms.highest_bidder,
+        )?;
+        msg!(
+            "Auction won by {} for {} tokens",
+            terms.highest_bidder,
+            terms.highest_bid
+        );
+    } else {
+        release_account(
+            token_program,
+            holding_account_info,
+            pda_account_info,
+            bump_seed,
+            &auction.owner_account,
+        )?;
+        msg!("Auction ended without bids");
+    }
+
+    auction.reset();
+    escrow_accounts.pack_into_slice(&mut pda_account_info.try_borrow_mut_data()?);
+    Ok(())
+}
+
+/// Checks `vault_account_info` is the bid vault PDA of `holding_account` and `bidder`
+fn verify_bid_vault(
+    vault_account_info: &AccountInfo,
+    program_id: &Pubkey,
+    holding_account: &Pubkey,
+    bidder: &Pubkey,
+) -> Result<u8, ProgramError> {
+    let (vault, bump_seed) = find_bid_vault_address(program_id, holding_account, bidder);
+    if vault != *vault_account_info.key {
+        msg!("Incorrect bid vault account provided to the instruction");
+        return Err(ProgramError::InvalidAccountData);
+    }
+    Ok(bump_seed)
+}
+
+/// Passes `bid_account` locked in the vault to `new_owner` and closes the vault,
+/// its rent goes back to `bidder_account_info`
+fn release_bid<'info>(
+    token_program: &AccountInfo<'info>,
+    bid_account_info: &AccountInfo<'info>,
+    vault_account_info: &AccountInfo<'info>,
+    bidder_account_info: &AccountInfo<'info>,
+    record: &BidRecord,
+    bump_seed: u8,
+    new_owner: &Pubkey,
+) -> ProgramResult {
+    if record.bid_account != *bid_account_info.key {
+        msg!("Bid is locked in {}", record.bid_account);
+        return Err(ProgramError::InvalidAccountData);
+    }
+    let owner_change_ix = set_authority(
+        token_program.key,
+        bid_account_info.key,
+        Some(new_owner),
+        spl_token::instruction::AuthorityType::AccountOwner,
+        vault_account_info.key,
+        &[],
+    )?;
+    invoke_signed(
+        &owner_change_ix,
+        &[
+            bid_account_info.clone(),
+            vault_account_info.clone(),
+            token_program.clone(),
+        ],
+        &[&[
+            BID_SEED,
+            record.holding_account.as_ref(),
+            record.bidder.as_ref(),
+            &[bump_seed],
+        ]],
+    )?;
+
+    let vault_lamports = vault_account_info.lamports();
+    **vault_account_info.try_borrow_mut_lamports()? = 0;
+    **bidder_account_info.try_borrow_mut_lamports()? += vault_lamports;
+    vault_account_info.try_borrow_mut_data()?.fill(0);
+    Ok(())
+}
+
+/// Creates the `new_account_info` PDA signed by `signer_seeds`, paid by `payer_account_info`.
+/// Lamports someone has already sent to the address don't block the creation: the account is
+/// topped up to rent exemption, allocated and assigned instead, as the associated token program does.
+fn create_pda_account<'info>(
+    payer_account_info: &AccountInfo<'info>,
+    new_account_info: &AccountInfo<'info>,
+    system_account: &AccountInfo<'info>,
+    space: usize,
+    owner: &Pubkey,
+    signer_seeds: &[&[u8]],
+) -> ProgramResult {
+    let rent_lamports = Rent::default().minimum_balance(space);
+    let accounts = [
+        payer_account_info.clone(),
+        new_account_info.clone(),
+        system_account.clone(),
+    ];
+    if new_account_info.lamports() == 0 {
+        return invoke_signed(
+            &system_instruction::create_account(
+                payer_account_info.key,
+                new_account_info.key,
+                rent_lamports,
+                space as u64,
+                owner,
+            ),
+            &accounts,
+            &[signer_seeds],
+        );
+    }
+
+    let missing_lamports = rent_lamports.saturating_sub(new_account_info.lamports());
+    if missing_lamports > 0 {
+        invoke(
+            &system_instruction::transfer(
+                payer_account_info.key,
+                new_account_info.key,
+                missing_lamports,
+            ),
+            &accounts,
+        )?;
+    }
+    invoke_signed(
+        &system_instruction::allocate(new_account_info.key, space as u64),
+        &accounts,
+        &[signer_seeds],
+    )?;
+    invoke_signed(
+        &system_instruction::assign(new_account_info.key, owner),
+        &accounts,
+        &[signer_seeds],
+    )
+}
+
+/// Taker locks the whole `bid_account` in a vault PDA as a quote for an open RFQ.
+/// The bidder pays the vault rent, refunded when the bid is accepted or withdrawn.
+fn submit_bid(accounts: &[AccountInfo], program_id: &Pubkey) -> ProgramResult {
+    let account_info_iter = &mut accounts.iter();
+    let pda_account_info = next_account_info(account_info_iter)?;
+    let bidder_account_info = next_account_info(account_info_iter)?;
+    let bid_account_info = next_account_info(account_info_iter)?;
+    let vault_account_info = next_account_info(account_info_iter)?;
+    let holding_account_info = next_account_info(account_info_iter)?;
+    let token_program = next_account_info(account_info_iter)?;
+    let system_account = next_account_info(account_info_iter)?;
+
+    verify_signer(bidder_account_info)?;
+    verify_writable(bidder_account_info)?;
+    verify_writable(bid_account_info)?;
+    verify_writable(vault_account_info)?;
+    verify_rent_exemption(bid_account_info)?;
+    if !system_program::check_id(system_account.key) {
+        return Err(ProgramError::IncorrectProgramId);
+    }
+    verify_pda(pda_account_info, program_id)?;
+    if pda_account_info.owner != program_id {
+        return Err(ProgramError::IncorrectProgramId);
+    }
+    let bump_seed = verify_bid_vault(
+        vault_account_info,
+        program_id,
+        holding_account_info.key,
+        bidder_account_info.key,
+    )?;
+
+    let escrow_accounts = Box::new(EscrowCollection::unpack_from_slice(
+        &pda_account_info.try_borrow_data()?,
+    )?);
+    let rfq = escrow_accounts
+        .items
+        .iter()
+        .find(|escrow| escrow.active && escrow.holding_account == *holding_account_info.key)
+        .ok_or_else(|| throw_and_log(EscrowError::NoAvailableEscrowAccounts))?;
+    verify_kind(rfq, EscrowKind::Rfq)?;
+
+    let bid_data = spl_token::state::Account::unpack(&bid_account_info.data.borrow())?;
+    if bid_data.owner != *bidder_account_info.key {
+        msg!("The provided owner is not the real owner of the bid account.");
+        return Err(ProgramError::IllegalOwner);
+    }
+    if bid_data.mint != rfq.token_expected {
+        msg!("RFQ expects bids in {} mint", rfq.token_expected);
+        return Err(throw_and_log(EscrowError::ExecutorTokenMintMismatch));
+    }
+
+    create_pda_account(
+        bidder_account_info,
+        vault_account_info,
+        system_account,
+        BidRecord::LEN,
+        program_id,
+        &[
+            BID_SEED,
+            holding_account_info.key.as_ref(),
+            bidder_account_info.key.as_ref(),
+            &[bump_seed],
+        ],
+    )?;
+    let record = BidRecord {
+        holding_account: *holding_account_info.key,
+        bidder: *bidder_account_info.key,
+        bid_account: *bid_account_info.key,
+        amount: bid_data.amount,
+        sequence: rfq.sequence,
+    };
+    record.pack_into_slice(&mut vault_account_info.try_borrow_mut_data()?);
+
+    msg!("Locking the bid account...");
+    invoke(
+        &set_authority(
+            token_program.key,
+            bid_account_info.key,
+            Some(vault_account_info.key),
+            spl_token::instruction::AuthorityType::AccountOwner,
+            bidder_account_info.key,
+            &[],
+        )?,
+        &[
+            bid_account_info.clone(),
+            bidder_account_info.clone(),
+            token_program.clone(),
+        ],
+    )?;
+    msg!("Bid of {} tokens submitted", bid_data.amount);
+    Ok(())
+}
+
+/// Maker settles the RFQ with one of the bids: the holding account goes to the bidder
+/// and the bid account to the maker. Bids left in other vaults become withdrawable.
+fn accept_bid(accounts: &[AccountInfo], program_id: &Pubkey) -> ProgramResult {
+    let account_info_iter = &mut accounts.iter();
+    let pda_account_info = next_account_info(account_info_iter)?;
+    let owner_account_info = next_account_info(account_info_iter)?;
+    let holding_account_info = next_account_info(account_info_iter)?;
+    let vault_account_info = next_account_info(account_info_iter)?;
+    let bid_account_info = next_account_info(account_info_iter)?;
+    let bidder_account_info = next_account_info(account_info_iter)?;
+    let token_program = next_account_info(account_info_iter)?;
+
+    verify_signer(owner_account_info)?;
+    verify_writable(pda_account_info)?;
+    verify_writable(holding_account_info)?;
+    verify_writable(vault_account_info)?;
+    verify_writable(bid_account_info)?;
+    verify_writable(bidder_account_info)?;
+    let (_pda, bump_seed) = verify_pda(pda_account_info, program_id)?;
+    if pda_account_info.owner != program_id || vault_account_info.owner != program_id {
+        return Err(ProgramError::IncorrectProgramId);
+    }
+    let vault_bump_seed = verify_bid_vault(
+        vault_account_info,
+        program_id,
+        holding_account_info.key,
+        bidder_account_info.key,
+    )?;
+    let record = BidRecord::unpack_from_slice(&vault_account_info.try_borrow_data()?)?;
+
+    let mut escrow_accounts = Box::new(EscrowCollection::unpack_from_slice(
+        &pda_account_info.try_borrow_data()?,
+    )?);
+    let rfq = escrow_accounts
+        .find_by_token_account(holding_account_info.key)
+        .ok_or_else(|| throw_and_log(EscrowError::NoAvailableEscrowAccounts))?;
+    verify_kind(rfq, EscrowKind::Rfq)?;
+    if rfq.owner_account != *owner_account_info.key {
+        msg!("Attempt to accept a bid on non-owned RFQ");
+        msg!("Claimed owner: {}", owner_account_info.key);
+        msg!("Real owner: {}", rfq.owner_account);
+        return Err(ProgramError::IllegalOwner);
+    }
+    if record.sequence != rfq.sequence {
+        msg!("Bid was made on RFQ with sequence {}", record.sequence);
+        return Err(throw_and_log(EscrowError::SequenceMismatch));
+    }
+
+    msg!("Passing the accepted bid to the maker...");
+    release_bid(
+        token_program,
+        bid_account_info,
+        vault_account_info,
+        bidder_account_info,
+        &record,
+        vault_bump_seed,
+        &rfq.owner_account,
+    )?;
+    release_account(
+        token_program,
+        holding_account_info,
+        pda_account_info,
+        bump_seed,
+        bidder_account_info.key,
+    )?;
+    msg!(
+        "Accepted bid of {} tokens by {}",
+        record.amount,
+        record.bidder
+    );
+
+    rfq.reset();
+    escrow_accounts.pack_into_slice(&mut pda_account_info.try_borrow_mut_data()?);
+    Ok(())
+}
+
+/// Bidder takes the bid account back once the RFQ it was made on is no longer open
+fn withdraw_bid(accounts: &[AccountInfo], program_id: &Pubkey) -> ProgramResult {
+    let account_info_iter = &mut accounts.iter();
+    let pda_account_info = next_account_info(account_info_iter)?;
+    let bidder_account_info = next_account_info(account_info_iter)?;
+    let vault_account_info = next_account_info(account_info_iter)?;
+    let bid_account_info = next_account_info(account_info_iter)?;
+    let token_program = next_account_info(account_info_iter)?;
+
+    verify_signer(bidder_account_info)?;
+    verify_writable(bidder_account_info)?;
+    verify_writable(vault_account_info)?;
+    verify_writable(bid_account_info)?;
+    verify_pda(pda_account_info, program_id)?;
+    if pda_account_info.owner != program_id || vault_account_info.owner != program_id {
+        return Err(ProgramError::IncorrectProgramId);
+    }
+    let record = BidRecord::unpack_from_slice(&vault_account_info.try_borrow_data()?)?;
+    let bump_seed = verify_bid_vault(
+        vault_account_info,
+        program_id,
+        &record.holding_account,
+        bidder_account_info.key,
+    )?;
+
+    let escrow_accounts = Box::new(EscrowCollection::unpack_from_slice(
+        &pda_account_info.try_borrow_data()?,
+    )?);
+    let rfq_open = escrow_accounts.items.iter().any(|escrow| {
+        escrow.active
+            && escrow.kind == EscrowKind::Rfq
+            && escrow.holding_account == record.holding_account
+            && escrow.sequence == record.sequence
+    });
+    if rfq_open {
+        return Err(throw_and_log(EscrowError::BidLocked));
+    }
+
+    release_bid(
+        token_program,
+        bid_account_info,
+        vault_account_info,
+        bidder_account_info,
+        &record,
+        bump_seed,
+        bidder_account_info.key,
+    )?;
+    msg!("Bid of {} tokens withdrawn", record.amount);
+    Ok(())
+}
+
+/// Buyer releases the payment to the seller, or the seller refunds it to the buyer.
+/// Either is allowed during a dispute too, as it concedes the dispute.
+fn close_arbitrated(accounts: &[AccountInfo], program_id: &Pubkey, release: bool) -> ProgramResult {
+    let account_info_iter = &mut accounts.iter();
+    let pda_account_info = next_account_info(account_info_iter)?;
+    let party_account_info = next_account_info(account_info_iter)?;
+    let token_account_info = next_account_info(account_info_iter)?;
+    let token_program = next_account_info(account_info_iter)?;
+
+    verify_signer(party_account_info)?;
+    verify_writable(pda_account_info)?;
+    verify_writable(token_account_info)?;
+    let (_pda, bump_seed) = verify_pda(pda_account_info, program_id)?;
+    if pda_account_info.owner != program_id {
+        return Err(ProgramError::IncorrectProgramId);
+    }
+
+    let mut escrow_accounts = Box::new(EscrowCollection::unpack_from_slice(
+        &pda_account_info.try_borrow_data()?,
+    )?);
+    let target_escrow_account = escrow_accounts
+        .find_by_token_account(token_account_info.key)
+        .ok_or_else(|| throw_and_log(EscrowError::NoAvailableEscrowAccounts))?;
+    verify_kind(target_escrow_account, EscrowKind::Arbitrated)?;
+    let terms: ArbiterTerms = target_escrow_account.terms()?;
+
+    let (signer, recipient) = if release {
+        (target_escrow_account.owner_account, terms.seller)
+    } else {
+        (terms.seller, target_escrow_account.owner_account)
+    };
+    if *party_account_info.key != signer {
+        msg!("Payment is to be released by the buyer or refunded by the seller");
+        msg!("Claimed party: {}", party_account_info.key);
+        msg!("Expected party: {}", signer);
+        return Err(ProgramError::IllegalOwner);
+    }
+
+    release_account(
+        token_program,
+        token_account_info,
+        pda_account_info,
+        bump_seed,
+        &recipient,
+    )?;
+    msg!("Payment passed to {}", recipient);
+
+    target_escrow_account.reset();
+    escrow_accounts.pack_into_slice(&mut pda_account_info.try_borrow_mut_data()?);
+    Ok(())
+}
+
+/// Buyer or seller freezes the escrow until the arbiter resolves it
+fn dispute(accounts: &[AccountInfo], program_id: &Pubkey) -> ProgramResult {
+    let account_info_iter = &mut accounts.iter();
+    let pda_account_info = next_account_info(account_info_iter)?;
+    let party_account_info = next_account_info(account_info_iter)?;
+    let token_account_info = next_account_info(account_info_iter)?;
+
+    verify_signer(party_account_info)?;
+    verify_writable(pda_account_info)?;
+    verify_pda(pda_account_info, program_id)?;
+    if pda_account_info.owner != program_id {
+        return Err(ProgramError::IncorrectProgramId);
+    }
+
+    let mut escrow_accounts = Box::new(EscrowCollection::unpack_from_slice(
+        &pda_account_info.try_borrow_data()?,
+    )?);
+    let target_escrow_account = escrow_accounts
+        .find_by_token_account(token_account_info.key)
+        .ok_or_else(|| throw_and_log(EscrowError::NoAvailableEscrowAccounts))?;
+    verify_kind(target_escrow_account, EscrowKind::Arbitrated)?;
+    let mut terms: ArbiterTerms = target_escrow_account.terms()?;
+
+    if *party_account_info.key != target_escrow_account.owner_account
+        && *party_account_info.key != terms.seller
+    {
+        msg!("Only the buyer or the seller may open a dispute");
+        return Err(ProgramError::IllegalOwner);
+    }
+
+    terms.disputed = true;
+    target_escrow_account.set_terms(&terms);
+    escrow_accounts.pack_into_slice(&mut pda_account_info.try_borrow_mut_data()?);
+    msg!("Dispute opened by {}", party_account_info.key);
+    Ok(())
+}
+
+/// Arbiter splits a disputed payment: `seller_bps` basis points of it are transferred to the
+/// seller token account and the holding account with the rest returns to the buyer
+fn resolve(accounts: &[AccountInfo], program_id: &Pubkey, seller_bps: u16) -> ProgramResult {
+    let account_info_iter = &mut accounts.iter();
+    let pda_account_info = next_account_info(account_info_iter)?;
+    let arbiter_account_info = next_account_info(account_info_iter)?;
+    let token_account_info = next_account_info(account_info_iter)?;
+    let seller_token_account_info = next_account_info(account_info_iter)?;
+    let token_program = next_account_info(account_info_iter)?;
+
+    verify_signer(arbiter_account_info)?;
+    verify_writable(pda_account_info)?;
+    verify_writable(token_account_info)?;
+    verify_writable(seller_token_account_info)?;
+    let (pda, bump_seed) = verify_pda(pda_account_info, program_id)?;
+    if pda_account_info.owner != program_id {
+        return Err(ProgramError::IncorrectProgramId);
+    }
+    if seller_bps > 10_000 {
+        msg!("Seller share of {} bps is over 100%", seller_bps);
+        return Err(ProgramError::InvalidArgument);
+    }
+
+    let mut escrow_accounts = Box::new(EscrowCollection::unpack_from_slice(
+        &pda_account_info.try_borrow_data()?,
+    )?);
+    let target_escrow_account = escrow_accounts
+        .find_by_token_account(token_account_info.key)
+        .ok_or_else(|| throw_and_log(EscrowError::NoAvailableEscrowAccounts))?;
+    verify_kind(target_escrow_account, EscrowKind::Arbitrated)?;
+    let terms: ArbiterTerms = target_escrow_account.terms()?;
+
+    if *arbiter_account_info.key != terms.arbiter {
+        msg!(
+            "Attempt to resolve by non-arbiter {}",
+            arbiter_account_info.key
+        );
+        return Err(ProgramError::IllegalOwner);
+    }
+    if !terms.disputed {
+        return Err(throw_and_log(EscrowError::NotDisputed));
+    }
+
+    let seller_token_account_data =
+        spl_token::state::Account::unpack(&seller_token_account_info.data.borrow())?;
+    if seller_token_account_data.owner != terms.seller {
+        msg!(
+            "Seller share is to be paid to an account of {}",
+            terms.seller
+        );
+        return Err(ProgramError::IllegalOwner);
+    }
+
+    let token_account_data = spl_token::state::Account::unpack(&token_account_info.data.borrow())?;
+    let seller_amount = (token_account_data.amount as u128 * seller_bps as u128 / 10_000) as u64;
+    if seller_amount > 0 {
+        msg!(
+            "Calling the token program to transfer {} tokens...",
+            seller_amount
+        );
+        invoke_signed(
+            &transfer(
+                token_program.key,
+                token_account_info.key,
+                seller_token_account_info.key,
+                &pda,
+                &[],
+                seller_amount,
+            )?,
+            &[
+                token_account_info.clone(),
+                seller_token_account_info.clone(),
+                pda_account_info.clone(),
+                token_program.clone(),
+            ],
+            &[&[SEED, &[bump_seed]]],
+        )?;
+    }
+    release_account(
+        token_program,
+        token_account_info,
+        pda_account_info,
+        bump_seed,
+        &target_escrow_account.owner_account,
+    )?;
+    msg!(
+        "Dispute resolved: {} tokens to the seller, {} to the buyer",
+        seller_amount,
+        token_account_data.amount - seller_amount
+    );
+
+    target_escrow_account.reset();
+    escrow_accounts.pack_into_slice(&mut pda_account_info.try_borrow_mut_data()?);
+    Ok(())
+}
+
+/// Approver of a multisig escrow approves the release or the refund, or revokes the approval when
+/// `refund` is `None`. Approving one outcome withdraws the approval of the other one.
+/// The approval reaching the threshold passes the deposit to the recipient or back to the depositor.
+fn approve(accounts: &[AccountInfo], program_id: &Pubkey, refund: Option<bool>) -> ProgramResult {
+    let account_info_iter = &mut accounts.iter();
+    let pda_account_info = next_account_info(account_info_iter)?;
+    let approver_account_info = next_account_info(account_info_iter)?;
+    let token_account_info = next_account_info(account_info_iter)?;
+
+    verify_signer(approver_account_info)?;
+    verify_writable(pda_account_info)?;
+    let (_pda, bump_seed) = verify_pda(pda_account_info, program_id)?;
+    if pda_account_info.owner != program_id {
+        return Err(ProgramError::IncorrectProgramId);
+    }
+
+    let mut escrow_accounts = Box::new(EscrowCollection::unpack_from_slice(
+        &pda_account_info.try_borrow_data()?,
+    )?);
+    let target_escrow_account = escrow_accounts
+        .find_by_token_account(token_account_info.key)
+        .ok_or_else(|| throw_and_log(EscrowError::NoAvailableEscrowAccounts))?;
+    verify_kind(target_escrow_account, EscrowKind::Multisig)?;
+    let mut terms: MultisigTerms = target_escrow_account.terms()?;
+
+    let approver_bit = terms
+        .approver_index(approver_account_info.key)
+        .map(|index| 1u8 << index)
+        .ok_or_else(|| {
+            msg!(
+                "{} is not an approver of this escrow",
+                approver_account_info.key
+            );
+            ProgramError::IllegalOwner
+        })?;
+    terms.release_approvals &= !approver_bit;
+    terms.refund_approvals &= !approver_bit;
+    let approvals = match refund {
+        Some(false) => &mut terms.release_approvals,
+        Some(true) => &mut terms.refund_approvals,
+        None => {
+            target_escrow_account.set_terms(&terms);
+            escrow_accounts.pack_into_slice(&mut pda_account_info.try_borrow_mut_data()?);
+            msg!("Approval revoked by {}", approver_account_info.key);
+            return Ok(());
+        }
+    };
+    *approvals |= approver_bit;
+    let approval_count = approvals.count_ones();
+    msg!(
+        "{} of {} approvals to {}",
+        approval_count,
+        terms.threshold,
+        if refund == Some(true) {
+            "refund"
+        } else {
+            "release"
+        }
+    );
+
+    if approval_count < terms.threshold as u32 {
+        target_escrow_account.set_terms(&terms);
+        escrow_accounts.pack_into_slice(&mut pda_account_info.try_borrow_mut_data()?);
+        return Ok(());
+    }
+
+    let token_program = next_account_info(account_info_iter)?;
+    verify_writable(token_account_info)?;
+    let recipient = if refund == Some(true) {
+        target_escrow_account.owner_account
+    } else {
+        terms.recipient
+    };
+    release_account(
+        token_program,
+        token_account_info,
+        pda_account_info,
+        bump_seed,
+        &recipient,
+    )?;
+    msg!("Threshold reached, deposit passed to {}", recipient);
+
+    target_escrow_account.reset();
+    escrow_accounts.pack_into_slice(&mut pda_account_info.try_borrow_mut_data()?);
+    Ok(())
+}
+
+/// Depositor or the arbiter pays the next milestone out of the holding account to a payee token account.
+/// Once the last one is paid, the holding account returns to the depositor.
+fn release_milestone(accounts: &[AccountInfo], program_id: &Pubkey, index: u8) -> ProgramResult {
+    let account_info_iter = &mut accounts.iter();
+    let pda_account_info = next_account_info(account_info_iter)?;
+    let releaser_account_info = next_account_info(account_info_iter)?;
+    let token_account_info = next_account_info(account_info_iter)?;
+    let payee_token_account_info = next_account_info(account_info_iter)?;
+    let token_program = next_account_info(account_info_iter)?;
+
+    verify_signer(releaser_account_info)?;
+    verify_writable(pda_account_info)?;
+    verify_writable(token_account_info)?;
+    verify_writable(payee_token_account_info)?;
+    let (pda, bump_seed) = verify_pda(pda_account_info, program_id)?;
+    if pda_account_info.owner != program_id {
+        return Err(ProgramError::IncorrectProgramId);
+    }
+
+    let mut escrow_accounts = Box::new(EscrowCollection::unpack_from_slice(
+        &pda_account_info.try_borrow_data()?,
+    )?);
+    let target_escrow_account = escrow_accounts
+        .find_by_token_account(token_account_info.key)
+        .ok_or_else(|| throw_and_log(EscrowError::NoAvailableEscrowAccounts))?;
+    verify_kind(target_escrow_account, EscrowKind::Milestones)?;
+    let mut terms: MilestoneTerms = target_escrow_account.terms()?;
+
+    if *releaser_account_info.key != target_escrow_account.owner_account
+        && Some(*releaser_account_info.key) != terms.arbiter
+    {
+        msg!("Milestones are released by the depositor or the arbiter");
+        return Err(ProgramError::IllegalOwner);
+    }
+    if index != terms.released {
+        msg!("Next milestone to release is {}", terms.released);
+        return Err(ProgramError::InvalidArgument);
+    }
+    let payee_token_account_data =
+        spl_token::state::Account::unpack(&payee_token_account_info.data.borrow())?;
+    if payee_token_account_data.owner != terms.payee {
+        msg!("Milestones are to be paid to an account of {}", terms.payee);
+        return Err(ProgramError::IllegalOwner);
+    }
+
+    let milestone = terms.milestones[index as usize];
+    msg!(
+        "Calling the token program to transfer {} tokens...",
+        milestone.amount
+    );
+    invoke_signed(
+        &transfer(
+            token_program.key,
+            token_account_info.key,
+            payee_token_account_info.key,
+            &pda,
+            &[],
+            milestone.amount,
+        )?,
+        &[
+            token_account_info.clone(),
+            payee_token_account_info.clone(),
+            pda_account_info.clone(),
+            token_program.clone(),
+        ],
+        &[&[SEED, &[bump_seed]]],
+    )?;
+    msg!(
+        "Milestone {} released, description hash: {}",
+        index,
+        to_hex(&milestone.description_hash)
+    );
+
+    terms.released += 1;
+    if terms.released == terms.milestone_count {
+        release_account(
+            token_program,
+            token_account_info,
+            pda_account_info,
+            bump_seed,
+            &target_escrow_account.owner_account,
+        )?;
+        msg!("All milestones released");
+        target_escrow_account.reset();
+    } else {
+        target_escrow_account.set_terms(&terms);
+    }
+    escrow_accounts.pack_into_slice(&mut pda_account_info.try_borrow_mut_data()?);
+    Ok(())
+}
+
+/// Anyone presenting the preimage of the hashlock before the timeout passes the holding account
+/// to the recipient. The preimage is logged, so the counterparty of a cross-chain swap can use it.
+fn claim(accounts: &[AccountInfo], program_id: &Pubkey, preimage: &[u8]) -> ProgramResult {
+    let account_info_iter = &mut accounts.iter();
+    let pda_account_info = next_account_info(account_info_iter)?;
+    let token_account_info = next_account_info(account_info_iter)?;
+    let token_program = next_account_info(account_info_iter)?;
+
+    verify_writable(pda_account_info)?;
+    verify_writable(token_account_info)?;
+    let (_pda, bump_seed) = verify_pda(pda_account_info, program_id)?;
+    if pda_account_info.owner != program_id {
+        return Err(ProgramError::IncorrectProgramId);
+    }
+
+    let mut escrow_accounts = Box::new(EscrowCollection::unpack_from_slice(
+        &pda_account_info.try_borrow_data()?,
+    )?);
+    let target_escrow_account = escrow_accounts
+        .find_by_token_account(token_account_info.key)
+        .ok_or_else(|| throw_and_log(EscrowError::NoAvailableEscrowAccounts))?;
+    verify_kind(target_escrow_account, EscrowKind::Htlc)?;
+    let terms: HtlcTerms = target_escrow_account.terms()?;
+
+    if Clock::get()?.unix_timestamp >= terms.timeout {
+        return Err(throw_and_log(EscrowError::HtlcExpired));
+    }
+    if hash(preimage).to_bytes() != terms.hashlock {
+        return Err(throw_and_log(EscrowError::HashlockMismatch));
+    }
+
+    release_account(
+        token_program,
+        token_account_info,
+        pda_account_info,
+        bump_seed,
+        &terms.recipient,
+    )?;
+    msg!("HTLC claimed, preimage: {}", to_hex(preimage));
+
+    target_escrow_account.reset();
+    escrow_accounts.pack_into_slice(&mut pda_account_info.try_borrow_mut_data()?);
+    Ok(())
+}
+
+/// Creates the nonce PDA of `maker`, paid by `payer`, so that `nonce` can't be used again
+fn use_nonce<'info>(
+    program_id: &Pubkey,
+    payer_account_info: &AccountInfo<'info>,
+    nonce_account_info: &AccountInfo<'info>,
+    system_account: &AccountInfo<'info>,
+    maker: &Pubkey,
+    nonce: u64,
+) -> ProgramResult {
+    verify_writable(nonce_account_info)?;
+    if !system_program::check_id(system_account.key) {
+        return Err(ProgramError::IncorrectProgramId);
+    }
+    let (nonce_address, bump_seed) = find_nonce_address(program_id, maker, nonce);
+    if nonce_address != *nonce_account_info.key {
+        msg!("Incorrect nonce account provided to the instruction");
+        return Err(ProgramError::InvalidAccountData);
+    }
+    if nonce_account_info.owner == program_id {
+        msg!("Nonce {} of {} is used", nonce, maker);
+        return Err(throw_and_log(EscrowError::NonceAlreadyUsed));
+    }
+
+    create_pda_account(
+        payer_account_info,
+        nonce_account_info,
+        system_account,
+        0,
+        program_id,
+        &[
+            NONCE_SEED,
+            maker.as_ref(),
+            &nonce.to_le_bytes(),
+            &[bump_seed],
+        ],
+    )
+}
+
+/// Taker fills an offer signed off-chain by its maker. The maker's tokens move by delegation
+/// to the registry, the taker's by the taker's signature; the nonce PDA paid by the taker
+/// prevents filling the offer twice.
+fn fill_offer(accounts: &[AccountInfo], program_id: &Pubkey, offer: &SignedOffer) -> ProgramResult {
+    let account_info_iter = &mut accounts.iter();
+    let pda_account_info = next_account_info(account_info_iter)?;
+    let taker_account_info = next_account_info(account_info_iter)?;
+    let taker_source_info = next_account_info(account_info_iter)?;
+    let taker_destination_info = next_account_info(account_info_iter)?;
+    let maker_source_info = next_account_info(account_info_iter)?;
+    let maker_destination_info = next_account_info(account_info_iter)?;
+    let nonce_account_info = next_account_info(account_info_iter)?;
+    let instructions_sysvar = next_account_info(account_info_iter)?;
+    let token_program = next_account_info(account_info_iter)?;
+    let system_account = next_account_info(account_info_iter)?;
+
+    verify_signer(taker_account_info)?;
+    verify_writable(taker_account_info)?;
+    let (_pda, bump_seed) = verify_pda(pda_account_info, program_id)?;
+    if pda_account_info.owner != program_id {
+        return Err(ProgramError::IncorrectProgramId);
+    }
+
+    if Clock::get()?.unix_timestamp >= offer.expiry {
+        return Err(throw_and_log(EscrowError::OfferExpired));
+    }
+    verify_ed25519_signature(
+        instructions_sysvar,
+        &offer.maker,
+        &offer.message(program_id),
+    )?;
+
+    let maker_source = spl_token::state::Account::unpack(&maker_source_info.data.borrow())?;
+    let maker_destination =
+        spl_token::state::Account::unpack(&maker_destination_info.data.borrow())?;
+    if maker_source.owner != offer.maker || maker_destination.owner != offer.maker {
+        msg!(
+            "Maker token accounts are expected to be owned by {}",
+            offer.maker
+        );
+        return Err(ProgramError::IllegalOwner);
+    }
+    if maker_source.mint != offer.maker_mint {
+        return Err(throw_and_log(EscrowError::DepositTokenMintMismatch));
+    }
+    if maker_destination.mint != offer.taker_mint {
+        return Err(throw_and_log(EscrowError::ExecutorTokenMintMismatch));
+    }
+    verify_delegation(&maker_source, pda_account_info.key, offer.maker_amount)?;
+
+    use_nonce(
+        program_id,
+        taker_account_info,
+        nonce_account_info,
+        system_account,
+        &offer.maker,
+        offer.nonce,
+    )?;
+
+    msg!("Paying the maker...");
+    invoke(
+        &transfer(
+            token_program.key,
+            taker_source_info.key,
+            maker_destination_info.key,
+            taker_account_info.key,
+            &[],
+            offer.taker_amount,
+        )?,
+        &[
+            taker_source_info.clone(),
+            maker_destination_info.clone(),
+            taker_account_info.clone(),
+            token_program.clone(),
+        ],
+    )?;
+    msg!("Transferring the delegated maker tokens...");
+    invoke_signed(
+        &transfer(
+            token_program.key,
+            maker_source_info.key,
+            taker_destination_info.key,
+            pda_account_info.key,
+            &[],
+            offer.maker_amount,
+        )?,
+        &[
+            maker_source_info.clone(),
+            taker_destination_info.clone(),
+            pda_account_info.clone(),
+            token_program.clone(),
+        ],
+        &[&[SEED, &[bump_seed]]],
+    )
+}
+
+/// Maker invalidates the offers signed with `nonce`
+fn cancel_offer(accounts: &[AccountInfo], program_id: &Pubkey, nonce: u64) -> ProgramResult {
+    let account_info_iter = &mut accounts.iter();
+    let maker_account_info = next_account_info(account_info_iter)?;
+    let nonce_account_info = next_account_info(account_info_iter)?;
+    let system_account = next_account_info(account_info_iter)?;
+
+    verify_signer(maker_account_info)?;
+    verify_writable(maker_account_info)?;
+    use_nonce(
+        program_id,
+        maker_account_info,
+        nonce_account_info,
+        system_account,
+        maker_account_info.key,
+        nonce,
+    )
+}
+
+/// Depositor takes the holding account back after the deadline of an escrow of `kind`:
+/// the unreleased milestones of a milestone escrow, or an unclaimed HTLC after its timeout
+fn refund_after_deadline(
+    accounts: &[AccountInfo],
+    program_id: &Pubkey,
+    kind: EscrowKind,
+) -> ProgramResult {
+    let account_info_iter = &mut accounts.iter();
+    let pda_account_info = next_account_info(account_info_iter)?;
+    let owner_account_info = next_account_info(account_info_iter)?;
+    let token_account_info = next_account_info(account_info_iter)?;
+    let token_program = next_account_info(account_info_iter)?;
+
+    verify_signer(owner_account_info)?;
+    verify_writable(pda_account_info)?;
+    verify_writable(token_account_info)?;
+    let (_pda, bump_seed) = verify_pda(pda_account_info, program_id)?;
+    if pda_account_info.owner != program_id {
+        return Err(ProgramError::IncorrectProgramId);
+    }
+
+    let mut escrow_accounts = Box::new(EscrowCollection::unpack_from_slice(
+        &pda_account_info.try_borrow_data()?,
+    )?);
+    let target_escrow_account = escrow_accounts
+        .find_by_token_account(token_account_info.key)
+        .ok_or_else(|| throw_and_log(EscrowError::NoAvailableEscrowAccounts))?;
+    verify_kind(target_escrow_account, kind)?;
+    let deadline = match kind {
+        EscrowKind::Milestones => target_escrow_account.terms::<MilestoneTerms>()?.deadline,
+        EscrowKind::Htlc => Some(target_escrow_account.terms::<HtlcTerms>()?.timeout),
+        _ => None,
+    };
+
+    if target_escrow_account.owner_account != *owner_account_info.key {
+        msg!("Attempt to refund non-owned escrow");
+        msg!("Claimed owner: {}", owner_account_info.key);
+        msg!("Real owner: {}", target_escrow_account.owner_account);
+        return Err(ProgramError::IllegalOwner);
+    }
+    let now = Clock::get()?.unix_timestamp;
+    if deadline.map_or(true, |deadline| now < deadline) {
+        msg!("Refund deadline: {:?}", deadline);
+        return Err(throw_and_log(EscrowError::DeadlineNotReached));
+    }
+
+    release_account(
+        token_program,
+        token_account_info,
+        pda_account_info,
+        bump_seed,
+        owner_account_info.key,
+    )?;
+    msg!("Escrow refunded to {}", owner_account_info.key);
+
+    target_escrow_account.reset();
+    escrow_accounts.pack_into_slice(&mut pda_account_info.try_borrow_mut_data()?);
+    Ok(())
+}
+
+/// Read-only lookup of active escrows, the packed records are returned via the return data.
+/// A holding account matches a single escrow, an owner may have several of them.
+/// Nothing is returned when no escrow matches.
+fn query(accounts: &[AccountInfo], program_id: &Pubkey, query: EscrowQuery) -> ProgramResult {
+    let account_info_iter = &mut accounts.iter();
+    let pda_account_info = next_account_info(account_info_iter)?;
+
+    verify_pda(pda_account_info, program_id)?;
+    if pda_account_info.owner != program_id {
+        return Err(ProgramError::IncorrectProgramId);
+    }
+
+    let mut escrow_accounts = Box::new(EscrowCollection::unpack_from_slice(
+        &pda_account_info.try_borrow_data()?,
+    )?);
+    let found: Vec<Escrow> = match query {
+        EscrowQuery::HoldingAccount(holding_account) => escrow_accounts
+            .find_by_token_account(&holding_account)
+            .map(|escrow| *escrow)
+            .into_iter()
+            .collect(),
+        EscrowQuery::Owner(owner) => escrow_accounts.find_by_owner(&owner).copied().collect(),
+    };
+    if found.len() > MAX_QUERY_RESULTS {
+        msg!(
+            "Found {} escrows, only the first {} fit into the return data",
+            found.len(),
+            MAX_QUERY_RESULTS
+        );
+    }
+    let found = &found[..found.len().min(MAX_QUERY_RESULTS)];
+
+    let mut data = vec![0u8; found.len() * Escrow::LEN];
+    for (escrow, dst) in found.iter().zip(data.chunks_exact_mut(Escrow::LEN)) {
+        escrow.pack_into_slice(dst);
+    }
+    msg!("Found {} escrows", found.len());
+    set_return_data(&data);
+    Ok(())
+}
+
+/// First party user may revoke and reclaim the token account if the swap hasn't happen
+fn reclaim(accounts: &[AccountInfo], program_id: &Pubkey) -> ProgramResult {
+    let account_info_iter = &mut accounts.iter();
+    let pda_account_info = next_account_info(account_info_iter)?;
+    let owner_account_info = next_account_info(account_info_iter)?;
+    let token_account_info = next_account_info(account_info_iter)?;
+    let token_program = next_account_info(account_info_iter)?;
+
+    verify_signer(owner_account_info)?;
+    verify_writable(token_account_info)?;
+    let (_pda, bump_seed) = verify_pda(pda_account_info, program_id)?;
 
     let mut escrow_accounts = Box::new(EscrowCollection::unpack_from_slice(
         &pda_account_info.try_borrow_mut_data()?,
     )?);
     let token_account_data = spl_token::state::Account::unpack(&token_account_info.data.borrow())?;
 
+    let reclaim_delay = escrow_accounts.config.reclaim_delay;
     let escrow_opt = escrow_accounts.find_by_token_account(token_account_info.key);
 
     if escrow_opt.is_none() {
@@ -299,9 +2579,11 @@ fn reclaim(accounts: &[AccountInfo], program_id: &Pubkey) -> ProgramResult {
     };
 
     let target_escrow_account = escrow_opt.unwrap();
+    // A delegated deposit has never left its owner
+    let custodial = target_escrow_account.kind != EscrowKind::Delegated;
 
     // Check if the owner matches the expected owner
-    if token_account_data.owner != *pda_account_info.key {
+    if custodial && token_account_data.owner != *pda_account_info.key {
         msg!("The provided owner is not the real owner of this token account.");
         return Err(ProgramError::IllegalOwner);
     }
@@ -314,25 +2596,60 @@ fn reclaim(accounts: &[AccountInfo], program_id: &Pubkey) -> ProgramResult {
         return Err(ProgramError::IllegalOwner);
     }
 
-    let reclaim_ix = set_authority(
-        token_program.key,
-        token_account_info.key,
-        Some(owner_account_info.key),
-        spl_token::instruction::AuthorityType::AccountOwner,
-        pda_account_info.key,
-        &[],
-    )?;
+    verify_reclaim_delay(target_escrow_account, reclaim_delay)?;
+    if matches!(
+        target_escrow_account.kind,
+        EscrowKind::Arbitrated | EscrowKind::Multisig | EscrowKind::Milestones | EscrowKind::Htlc
+    ) {
+        msg!("Deposit returns to the owner by a refund only");
+        return Err(throw_and_log(EscrowError::WrongEscrowKind));
+    }
+    if target_escrow_account.kind == EscrowKind::EnglishAuction
+        && target_escrow_account.terms::<AuctionTerms>()?.highest_bid > 0
+    {
+        return Err(throw_and_log(EscrowError::AuctionHasBids));
+    }
 
-    msg!("Calling the token program to transfer depositor token account ownership...");
-    invoke_signed(
-        &reclaim_ix,
-        &[
-            token_account_info.clone(),
-            pda_account_info.clone(),
-            token_program.clone(),
-        ],
-        &[&[SEED, &[bump_seed]]],
-    )?;
+    if custodial {
+        let reclaim_ix = set_authority(
+            token_program.key,
+            token_account_info.key,
+            Some(owner_account_info.key),
+            spl_token::instruction::AuthorityType::AccountOwner,
+            pda_account_info.key,
+            &[],
+        )?;
+
+        msg!("Calling the token program to transfer depositor token account ownership...");
+        invoke_signed(
+            &reclaim_ix,
+            &[
+                token_account_info.clone(),
+                pda_account_info.clone(),
+                token_program.clone(),
+            ],
+            &[&[SEED, &[bump_seed]]],
+        )?;
+    }
+
+    // The other accounts of a bundle follow the fixed ones
+    if target_escrow_account.kind == EscrowKind::Bundle {
+        let terms: BundleTerms = target_escrow_account.terms()?;
+        for extra_holding in terms.extra_holdings() {
+            let holding_info = next_account_info(account_info_iter)?;
+            if holding_info.key != extra_holding {
+                msg!("Bundle account {} is expected", extra_holding);
+                return Err(ProgramError::InvalidArgument);
+            }
+            release_account(
+                token_program,
+                holding_info,
+                pda_account_info,
+                bump_seed,
+                owner_account_info.key,
+            )?;
+        }
+    }
 
     msg!("Account deposited successfully! You can retrieve it using Revoke instruction.");
 
@@ -356,11 +2673,311 @@ pub fn parse_execute_instruction(
         }
         EscrowInstruction::Deposit { amount_expected } => {
             msg!("Deposit instruction...");
-            deposit(accounts, program_id, amount_expected)
+            deposit(accounts, program_id, |escrow, _, _| {
+                escrow.amount_expected = amount_expected;
+                Ok(())
+            })
+        }
+        EscrowInstruction::DepositAlternatives {
+            amount_expected,
+            alternatives,
+        } => {
+            msg!("Deposit with alternatives instruction...");
+            deposit(accounts, program_id, |escrow, _, _| {
+                let mut terms = SwapTerms {
+                    alternative_count: alternatives.len() as u8,
+                    ..SwapTerms::default()
+                };
+                for (i, alternative) in alternatives.iter().enumerate() {
+                    if alternative.mint == escrow.token_expected
+                        || alternatives[..i]
+                            .iter()
+                            .any(|other| other.mint == alternative.mint)
+                    {
+                        msg!("Mint {} is accepted twice", alternative.mint);
+                        return Err(ProgramError::InvalidArgument);
+                    }
+                    terms.alternatives[i] = *alternative;
+                }
+                escrow.amount_expected = amount_expected;
+                escrow.set_terms(&terms);
+                Ok(())
+            })
+        }
+        EscrowInstruction::ExecuteRing => {
+            msg!("Execute ring request...");
+            execute_ring(accounts, program_id)
+        }
+        EscrowInstruction::MigrateRegistry => {
+            msg!("Migrate registry request...");
+            migrate_registry(accounts, program_id)
+        }
+        EscrowInstruction::CreateBundle { wanted } => {
+            msg!("Create bundle request...");
+            create_bundle(accounts, program_id, &wanted)
+        }
+        EscrowInstruction::ExecuteBundle { sequence } => {
+            msg!("Execute bundle request...");
+            execute_bundle(accounts, program_id, sequence)
         }
-        EscrowInstruction::Execute { amount_expected } => {
+        EscrowInstruction::CreateAuction {
+            kind,
+            reserve_price,
+            start_price,
+            end_timestamp,
+        } => {
+            msg!("Create auction request...");
+            deposit(accounts, program_id, |escrow, clock, _| {
+                if end_timestamp <= clock.unix_timestamp {
+                    msg!("Auction end time {} has already passed", end_timestamp);
+                    return Err(ProgramError::InvalidArgument);
+                }
+                if kind == EscrowKind::DutchAuction && start_price < reserve_price {
+                    msg!("Dutch auction price can't grow over time");
+                    return Err(ProgramError::InvalidArgument);
+                }
+                escrow.kind = kind;
+                escrow.set_terms(&AuctionTerms {
+                    reserve_price,
+                    start_price,
+                    end_timestamp,
+                    ..AuctionTerms::default()
+                });
+                Ok(())
+            })
+        }
+        EscrowInstruction::Bid => {
+            msg!("Auction bid request...");
+            bid(accounts, program_id)
+        }
+        EscrowInstruction::SettleAuction => {
+            msg!("Settle auction request...");
+            settle_auction(accounts, program_id)
+        }
+        EscrowInstruction::CreateRfq => {
+            msg!("Create RFQ request...");
+            deposit(accounts, program_id, |escrow, _, _| {
+                escrow.kind = EscrowKind::Rfq;
+                Ok(())
+            })
+        }
+        EscrowInstruction::SubmitBid => {
+            msg!("Submit bid request...");
+            submit_bid(accounts, program_id)
+        }
+        EscrowInstruction::AcceptBid => {
+            msg!("Accept bid request...");
+            accept_bid(accounts, program_id)
+        }
+        EscrowInstruction::WithdrawBid => {
+            msg!("Withdraw bid request...");
+            withdraw_bid(accounts, program_id)
+        }
+        EscrowInstruction::CreateArbitrated { seller, arbiter } => {
+            msg!("Create arbitrated escrow request...");
+            deposit(accounts, program_id, |escrow, _, _| {
+                escrow.kind = EscrowKind::Arbitrated;
+                escrow.set_terms(&ArbiterTerms {
+                    seller,
+                    arbiter,
+                    disputed: false,
+                });
+                Ok(())
+            })
+        }
+        EscrowInstruction::Release => {
+            msg!("Release request...");
+            close_arbitrated(accounts, program_id, true)
+        }
+        EscrowInstruction::Refund => {
+            msg!("Refund request...");
+            close_arbitrated(accounts, program_id, false)
+        }
+        EscrowInstruction::Dispute => {
+            msg!("Dispute request...");
+            dispute(accounts, program_id)
+        }
+        EscrowInstruction::Resolve { seller_bps } => {
+            msg!("Resolve request...");
+            resolve(accounts, program_id, seller_bps)
+        }
+        EscrowInstruction::CreateMultisig {
+            recipient,
+            threshold,
+            approvers,
+        } => {
+            msg!("Create multisig escrow request...");
+            deposit(accounts, program_id, |escrow, _, _| {
+                let unique = approvers
+                    .iter()
+                    .enumerate()
+                    .all(|(i, approver)| !approvers[..i].contains(approver));
+                if threshold == 0 || threshold as usize > approvers.len() || !unique {
+                    msg!(
+                        "Threshold {} of {} distinct approvers expected",
+                        threshold,
+                        approvers.len()
+                    );
+                    return Err(ProgramError::InvalidArgument);
+                }
+                let mut terms = MultisigTerms {
+                    recipient,
+                    threshold,
+                    approver_count: approvers.len() as u8,
+                    ..MultisigTerms::default()
+                };
+                terms.approvers[..approvers.len()].copy_from_slice(&approvers);
+                escrow.kind = EscrowKind::Multisig;
+                escrow.set_terms(&terms);
+                Ok(())
+            })
+        }
+        EscrowInstruction::Approve { refund } => {
+            msg!("Approve request...");
+            approve(accounts, program_id, Some(refund))
+        }
+        EscrowInstruction::Revoke => {
+            msg!("Revoke approval request...");
+            approve(accounts, program_id, None)
+        }
+        EscrowInstruction::CreateMilestones {
+            payee,
+            arbiter,
+            deadline,
+            milestones,
+        } => {
+            msg!("Create milestone escrow request...");
+            deposit(accounts, program_id, |escrow, clock, token_account| {
+                let total = milestones
+                    .iter()
+                    .try_fold(0u64, |total, milestone| total.checked_add(milestone.amount));
+                if milestones.is_empty() || total != Some(token_account.amount) {
+                    msg!(
+                        "Milestones are expected to split all {} deposited tokens",
+                        token_account.amount
+                    );
+                    return Err(ProgramError::InvalidArgument);
+                }
+                if deadline.is_some_and(|deadline| deadline <= clock.unix_timestamp) {
+                    msg!("Milestone deadline has already passed");
+                    return Err(ProgramError::InvalidArgument);
+                }
+                let mut terms = MilestoneTerms {
+                    payee,
+                    arbiter,
+                    deadline,
+                    milestone_count: milestones.len() as u8,
+                    ..MilestoneTerms::default()
+                };
+                terms.milestones[..milestones.len()].copy_from_slice(&milestones);
+                escrow.kind = EscrowKind::Milestones;
+                escrow.set_terms(&terms);
+                Ok(())
+            })
+        }
+        EscrowInstruction::ReleaseMilestone { index } => {
+            msg!("Release milestone request...");
+            release_milestone(accounts, program_id, index)
+        }
+        EscrowInstruction::RefundMilestones => {
+            msg!("Refund milestones request...");
+            refund_after_deadline(accounts, program_id, EscrowKind::Milestones)
+        }
+        EscrowInstruction::CreateHtlc {
+            recipient,
+            hashlock,
+            timeout,
+        } => {
+            msg!("Create HTLC request...");
+            deposit(accounts, program_id, |escrow, clock, _| {
+                if timeout <= clock.unix_timestamp {
+                    msg!("HTLC timeout {} has already passed", timeout);
+                    return Err(ProgramError::InvalidArgument);
+                }
+                escrow.kind = EscrowKind::Htlc;
+                escrow.set_terms(&HtlcTerms {
+                    recipient,
+                    hashlock,
+                    timeout,
+                });
+                Ok(())
+            })
+        }
+        EscrowInstruction::Claim { preimage } => {
+            msg!("Claim request...");
+            claim(accounts, program_id, preimage)
+        }
+        EscrowInstruction::RefundHtlc => {
+            msg!("Refund HTLC request...");
+            refund_after_deadline(accounts, program_id, EscrowKind::Htlc)
+        }
+        EscrowInstruction::FillOffer { offer } => {
+            msg!("Fill signed offer request...");
+            fill_offer(accounts, program_id, &offer)
+        }
+        EscrowInstruction::CancelOffer { nonce } => {
+            msg!("Cancel signed offer request...");
+            cancel_offer(accounts, program_id, nonce)
+        }
+        EscrowInstruction::DepositDelegated { amount_expected } => {
+            msg!("Delegated deposit request...");
+            deposit_delegated(accounts, program_id, amount_expected)
+        }
+        EscrowInstruction::ExecuteDelegated {
+            amount_expected,
+            sequence,
+        } => {
+            msg!("Execute delegated escrow request...");
+            execute_delegated(accounts, program_id, amount_expected, sequence)
+        }
+        EscrowInstruction::Execute {
+            amount_expected,
+            sequence,
+        } => {
             msg!("Execute escrow request...");
-            execute(accounts, program_id, amount_expected)
+            execute(accounts, program_id, amount_expected, sequence)
+        }
+        EscrowInstruction::ExecuteBatch {
+            amounts_expected,
+            sequences,
+        } => {
+            msg!("Execute escrow batch request...");
+            execute_batch(accounts, program_id, &amounts_expected, &sequences)
+        }
+        EscrowInstruction::Configure {
+            match_reward_lamports,
+            reclaim_delay,
+        } => {
+            msg!("Configure escrow registry request...");
+            configure(accounts, program_id, match_reward_lamports, reclaim_delay)
+        }
+        EscrowInstruction::Match => {
+            msg!("Match escrows request...");
+            match_escrows(accounts, program_id)
+        }
+        EscrowInstruction::Query {
+            query: escrow_query,
+        } => {
+            msg!("Query escrow request...");
+            query(accounts, program_id, escrow_query)
+        }
+        EscrowInstruction::Amend { amount_expected } => {
+            msg!("Amend escrow request...");
+            amend(accounts, program_id, amount_expected)
+        }
+        EscrowInstruction::TopUp {
+            amount,
+            amount_expected,
+        } => {
+            msg!("Top up escrow request...");
+            adjust_deposit(accounts, program_id, amount, amount_expected, false)
+        }
+        EscrowInstruction::Withdraw {
+            amount,
+            amount_expected,
+        } => {
+            msg!("Withdraw from escrow request...");
+            adjust_deposit(accounts, program_id, amount, amount_expected, true)
         }
         EscrowInstruction::Reclaim => {
             msg!("Escrow account is closed, tokens returned to");
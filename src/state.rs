@@ -1,23 +1,189 @@
+use crate::error::EscrowError;
 use arrayref::{array_mut_ref, array_ref, array_refs, mut_array_refs};
+use num_derive::FromPrimitive;
+use num_traits::FromPrimitive;
 use solana_program::program_error::ProgramError;
 use solana_program::program_pack::{Pack, Sealed};
 use solana_program::pubkey::Pubkey;
 
 pub const SEED: &[u8; 6] = b"escrow";
-const DATA_LEN: usize = 101;
+pub const BID_SEED: &[u8; 3] = b"bid";
+pub const NONCE_SEED: &[u8; 5] = b"nonce";
+const DATA_LEN: usize = 126 + TERMS_LEN;
+const CONFIG_LEN: usize = 57;
 
-const COLLECTION_WIDTH: usize = 5;
+pub const COLLECTION_WIDTH: usize = 5;
 
-#[derive(Default, Copy, Clone)]
+/// Escrow records of the first registry layout: active, amount_expected, token_expected,
+/// holding_account and owner_account, without the registry config
+const LEGACY_DATA_LEN: usize = 101;
+pub const LEGACY_REGISTRY_LEN: usize = LEGACY_DATA_LEN * COLLECTION_WIDTH;
+
+/// Size of the kind specific part of an escrow record
+pub const TERMS_LEN: usize = 256;
+
+/// Most approvers a multisig escrow may have
+pub const MAX_APPROVERS: usize = 6;
+
+/// Most milestones a milestone escrow may be split into
+pub const MAX_MILESTONES: usize = 4;
+
+/// Most (mint, amount) pairs a swap accepts besides `token_expected`
+pub const MAX_ALTERNATIVES: usize = 3;
+
+/// Most token accounts on either side of a bundle
+pub const MAX_BUNDLE_LEGS: usize = 4;
+
+/// Upper bounds of the reclaim delay an admin may set, about a day
+pub const MAX_RECLAIM_DELAY_SLOTS: u64 = 216_000;
+pub const MAX_RECLAIM_DELAY_SECONDS: u64 = 86_400;
+
+/// Derives the address of the registry PDA keeping all escrow records of the program
+pub fn find_registry_address(program_id: &Pubkey) -> (Pubkey, u8) {
+    Pubkey::find_program_address(&[SEED], program_id)
+}
+
+/// Derives the vault PDA locking the bid of `bidder` on the RFQ of `holding_account`
+pub fn find_bid_vault_address(
+    program_id: &Pubkey,
+    holding_account: &Pubkey,
+    bidder: &Pubkey,
+) -> (Pubkey, u8) {
+    Pubkey::find_program_address(
+        &[BID_SEED, holding_account.as_ref(), bidder.as_ref()],
+        program_id,
+    )
+}
+
+/// Derives the PDA marking `nonce` of `maker` as used by a filled or cancelled signed offer
+pub fn find_nonce_address(program_id: &Pubkey, maker: &Pubkey, nonce: u64) -> (Pubkey, u8) {
+    Pubkey::find_program_address(
+        &[NONCE_SEED, maker.as_ref(), &nonce.to_le_bytes()],
+        program_id,
+    )
+}
+
+/// Decides how an escrow is filled and how its `terms` are read
+#[derive(Default, Copy, Clone, Debug, PartialEq, Eq, FromPrimitive)]
+pub enum EscrowKind {
+    /// Fixed price swap, filled by `Execute` or `Match`
+    #[default]
+    Swap = 0,
+    /// Highest bid at the end time wins, terms are `AuctionTerms`
+    EnglishAuction = 1,
+    /// Price decays from the start price to the reserve one, terms are `AuctionTerms`
+    DutchAuction = 2,
+    /// Request for quote, filled by `AcceptBid` with a bid of the maker choice, no terms
+    Rfq = 3,
+    /// Payment released by the buyer or refunded by the seller, disputes are resolved by the
+    /// arbiter, terms are `ArbiterTerms`
+    Arbitrated = 4,
+    /// Released or refunded once enough approvers agree, terms are `MultisigTerms`
+    Multisig = 5,
+    /// Paid to the payee milestone by milestone, terms are `MilestoneTerms`
+    Milestones = 6,
+    /// Released to the recipient by the preimage of a SHA-256 hashlock, terms are `HtlcTerms`
+    Htlc = 7,
+    /// Holding account stays with the maker, who delegates the offered tokens to the registry,
+    /// filled by `ExecuteDelegated`, terms are `DelegatedTerms`
+    Delegated = 8,
+    /// Several holding accounts swapped for several taker accounts at once by `ExecuteBundle`,
+    /// terms are `BundleTerms`
+    Bundle = 9,
+}
+
+#[derive(Copy, Clone, Debug, PartialEq, Eq)]
 pub struct Escrow {
     pub active: bool,
     pub amount_expected: u32,
     pub token_expected: Pubkey,
     pub holding_account: Pubkey,
     pub owner_account: Pubkey,
+    /// Registry-wide increasing number assigned on deposit, tells a replaced offer apart
+    pub sequence: u64,
+    /// Clock at the deposit time, used to enforce the reclaim delay
+    pub deposit_slot: u64,
+    pub deposit_timestamp: i64,
+    pub kind: EscrowKind,
+    /// Kind specific terms, see `Escrow::terms`
+    pub terms: [u8; TERMS_LEN],
+}
+
+impl Default for Escrow {
+    fn default() -> Self {
+        Self {
+            active: false,
+            amount_expected: 0,
+            token_expected: Pubkey::default(),
+            holding_account: Pubkey::default(),
+            owner_account: Pubkey::default(),
+            sequence: 0,
+            deposit_slot: 0,
+            deposit_timestamp: 0,
+            kind: EscrowKind::Swap,
+            terms: [0; TERMS_LEN],
+        }
+    }
+}
+
+/// Registry settings, `admin` is the user who initialized the registry
+#[derive(Default, Copy, Clone, Debug, PartialEq, Eq)]
+pub struct RegistryConfig {
+    pub admin: Pubkey,
+    /// Paid out of the registry balance to whoever matches two escrows
+    pub match_reward_lamports: u64,
+    /// Sequence number given to the next deposit
+    pub next_sequence: u64,
+    pub reclaim_delay: ReclaimDelay,
+}
+
+/// Minimum time an escrow stays open before its owner may reclaim it
+#[derive(Default, Copy, Clone, Debug, PartialEq, Eq)]
+pub enum ReclaimDelay {
+    #[default]
+    None,
+    Slots(u64),
+    Seconds(u64),
+}
+
+impl ReclaimDelay {
+    pub const LEN: usize = 9;
+
+    pub fn is_within_bounds(&self) -> bool {
+        match *self {
+            ReclaimDelay::None => true,
+            ReclaimDelay::Slots(slots) => slots <= MAX_RECLAIM_DELAY_SLOTS,
+            ReclaimDelay::Seconds(seconds) => seconds <= MAX_RECLAIM_DELAY_SECONDS,
+        }
+    }
+
+    pub fn pack(&self) -> [u8; Self::LEN] {
+        let (kind, value) = match *self {
+            ReclaimDelay::None => (0, 0),
+            ReclaimDelay::Slots(slots) => (1, slots),
+            ReclaimDelay::Seconds(seconds) => (2, seconds),
+        };
+        let mut dst = [0u8; Self::LEN];
+        dst[0] = kind;
+        dst[1..].copy_from_slice(&value.to_le_bytes());
+        dst
+    }
+
+    pub fn unpack(src: &[u8; Self::LEN]) -> Result<Self, ProgramError> {
+        let (kind, value) = array_refs![src, 1, 8];
+        let value = u64::from_le_bytes(*value);
+        match kind[0] {
+            0 => Ok(ReclaimDelay::None),
+            1 => Ok(ReclaimDelay::Slots(value)),
+            2 => Ok(ReclaimDelay::Seconds(value)),
+            _ => Err(ProgramError::InvalidAccountData),
+        }
+    }
 }
 
+#[derive(Debug, PartialEq, Eq)]
 pub struct EscrowCollection {
+    pub config: RegistryConfig,
     pub items: [Escrow; COLLECTION_WIDTH],
 }
 
@@ -29,23 +195,73 @@ impl EscrowCollection {
             .find(|item| item.active && item.holding_account == *token_account)
     }
 
+    pub fn find_by_owner<'a>(&'a self, owner: &'a Pubkey) -> impl Iterator<Item = &'a Escrow> {
+        self.items
+            .iter()
+            .filter(move |item| item.active && item.owner_account == *owner)
+    }
+
     pub fn find_next_available(&mut self) -> Option<&mut Escrow> {
         self.items.iter_mut().find(|item| !item.active)
     }
+
+    /// Reads a registry of `LEGACY_REGISTRY_LEN` bytes, its escrows become swaps numbered from 1
+    pub fn unpack_legacy(src: &[u8]) -> Result<Self, ProgramError> {
+        if src.len() != LEGACY_REGISTRY_LEN {
+            return Err(ProgramError::InvalidAccountData);
+        }
+        let mut obj = Self {
+            config: RegistryConfig {
+                next_sequence: 1,
+                ..RegistryConfig::default()
+            },
+            items: [Escrow::default(); COLLECTION_WIDTH],
+        };
+        for (item, src) in obj.items.iter_mut().zip(src.chunks_exact(LEGACY_DATA_LEN)) {
+            let src = array_ref![src, 0, LEGACY_DATA_LEN];
+            let (active, amount_expected, token_expected, holding_account, owner_account) =
+                array_refs![src, 1, 4, 32, 32, 32];
+            if active[0] == 0 {
+                continue;
+            }
+            *item = Escrow {
+                active: true,
+                amount_expected: u32::from_le_bytes(*amount_expected),
+                token_expected: Pubkey::new_from_array(*token_expected),
+                holding_account: Pubkey::new_from_array(*holding_account),
+                owner_account: Pubkey::new_from_array(*owner_account),
+                sequence: obj.config.next_sequence,
+                ..Escrow::default()
+            };
+            obj.config.next_sequence += 1;
+        }
+        Ok(obj)
+    }
 }
 impl Sealed for crate::state::EscrowCollection {}
 impl Pack for crate::state::EscrowCollection {
-    const LEN: usize = DATA_LEN * COLLECTION_WIDTH;
+    const LEN: usize = CONFIG_LEN + DATA_LEN * COLLECTION_WIDTH;
     fn pack_into_slice(&self, dst: &mut [u8]) {
+        self.config.pack_into_slice(&mut dst[..CONFIG_LEN]);
+        let dst = &mut dst[CONFIG_LEN..];
         for (i, item) in self.items.iter().enumerate() {
             let subdst = &mut dst[i * DATA_LEN..(i + 1) * DATA_LEN];
             item.pack_into_slice(subdst)
         }
     }
     fn unpack_from_slice(src: &[u8]) -> Result<Self, ProgramError> {
+        if src.is_empty() {
+            return Err(ProgramError::InvalidAccountData);
+        }
+        // Registries of earlier versions are shorter, see `MigrateRegistry`
+        if src.len() < Self::LEN {
+            return Err(EscrowError::OutdatedRegistryLayout.into());
+        }
         let mut obj = Self {
+            config: RegistryConfig::unpack_from_slice(&src[..CONFIG_LEN])?,
             items: [Escrow::default(); COLLECTION_WIDTH],
         };
+        let src = &src[CONFIG_LEN..];
         for (i, item) in obj.items.iter_mut().enumerate() {
             let subdst = &src[i * DATA_LEN..(i + 1) * DATA_LEN];
             *item = Escrow::unpack_from_slice(subdst)?;
@@ -54,6 +270,37 @@ impl Pack for crate::state::EscrowCollection {
     }
 }
 
+impl Sealed for RegistryConfig {}
+impl Pack for RegistryConfig {
+    const LEN: usize = CONFIG_LEN;
+
+    fn pack_into_slice(&self, dst: &mut [u8]) {
+        let dst = array_mut_ref![dst, 0, RegistryConfig::LEN];
+        let (admin_dst, match_reward_lamports_dst, next_sequence_dst, reclaim_delay_dst) =
+            mut_array_refs![dst, 32, 8, 8, ReclaimDelay::LEN];
+
+        admin_dst.copy_from_slice(self.admin.as_ref());
+        *match_reward_lamports_dst = self.match_reward_lamports.to_le_bytes();
+        *next_sequence_dst = self.next_sequence.to_le_bytes();
+        *reclaim_delay_dst = self.reclaim_delay.pack();
+    }
+
+    fn unpack_from_slice(src: &[u8]) -> Result<Self, ProgramError> {
+        if src.len() < RegistryConfig::LEN {
+            return Err(ProgramError::InvalidAccountData);
+        }
+        let src = array_ref![src, 0, RegistryConfig::LEN];
+        let (admin_src, match_reward_lamports_src, next_sequence_src, reclaim_delay_src) =
+            array_refs![src, 32, 8, 8, ReclaimDelay::LEN];
+        Ok(Self {
+            admin: Pubkey::new_from_array(*admin_src),
+            match_reward_lamports: u64::from_le_bytes(*match_reward_lamports_src),
+            next_sequence: u64::from_le_bytes(*next_sequence_src),
+            reclaim_delay: ReclaimDelay::unpack(reclaim_delay_src)?,
+        })
+    }
+}
+
 /// Storage structure created for every escrow swap
 impl Escrow {
     pub fn reset(&mut self) {
@@ -62,8 +309,664 @@ impl Escrow {
         self.token_expected = Pubkey::default();
         self.holding_account = Pubkey::default();
         self.owner_account = Pubkey::default();
+        self.sequence = 0;
+        self.deposit_slot = 0;
+        self.deposit_timestamp = 0;
+        self.kind = EscrowKind::Swap;
+        self.terms = [0; TERMS_LEN];
+    }
+
+    /// Reads the kind specific terms
+    pub fn terms<T: Pack>(&self) -> Result<T, ProgramError> {
+        T::unpack_from_slice(&self.terms)
+    }
+
+    pub fn set_terms<T: Pack>(&mut self, terms: &T) {
+        terms.pack_into_slice(&mut self.terms[..T::LEN]);
+    }
+
+    /// Amount a swap expects in `mint`, either `amount_expected` of `token_expected` or the amount
+    /// of an alternative, `None` if the mint isn't accepted
+    pub fn amount_expected_in(&self, mint: &Pubkey) -> Option<u32> {
+        if *mint == self.token_expected {
+            return Some(self.amount_expected);
+        }
+        let terms: SwapTerms = self.terms().ok()?;
+        terms
+            .alternatives()
+            .iter()
+            .find(|alternative| alternative.mint == *mint)
+            .map(|alternative| alternative.amount)
     }
 }
+
+/// A mint and amount accepted instead of `token_expected` and `amount_expected`
+#[derive(Default, Copy, Clone, Debug, PartialEq, Eq)]
+pub struct Alternative {
+    pub mint: Pubkey,
+    pub amount: u32,
+}
+
+impl Alternative {
+    pub const LEN: usize = 36;
+}
+
+/// Terms of a swap, empty unless it was deposited with alternatives
+#[derive(Default, Copy, Clone, Debug, PartialEq, Eq)]
+pub struct SwapTerms {
+    pub alternative_count: u8,
+    pub alternatives: [Alternative; MAX_ALTERNATIVES],
+}
+
+impl SwapTerms {
+    /// The alternatives in use
+    pub fn alternatives(&self) -> &[Alternative] {
+        &self.alternatives[..(self.alternative_count as usize).min(MAX_ALTERNATIVES)]
+    }
+}
+
+impl Sealed for SwapTerms {}
+impl Pack for SwapTerms {
+    const LEN: usize = 1 + Alternative::LEN * MAX_ALTERNATIVES;
+
+    fn pack_into_slice(&self, dst: &mut [u8]) {
+        let dst = array_mut_ref![dst, 0, SwapTerms::LEN];
+        let (count_dst, alternatives_dst) =
+            mut_array_refs![dst, 1, Alternative::LEN * MAX_ALTERNATIVES];
+
+        count_dst[0] = self.alternative_count;
+        for (alternative, dst) in self
+            .alternatives
+            .iter()
+            .zip(alternatives_dst.chunks_exact_mut(Alternative::LEN))
+        {
+            dst[..32].copy_from_slice(alternative.mint.as_ref());
+            dst[32..].copy_from_slice(&alternative.amount.to_le_bytes());
+        }
+    }
+
+    fn unpack_from_slice(src: &[u8]) -> Result<Self, ProgramError> {
+        if src.len() < SwapTerms::LEN {
+            return Err(ProgramError::InvalidAccountData);
+        }
+        let src = array_ref![src, 0, SwapTerms::LEN];
+        let (count_src, alternatives_src) =
+            array_refs![src, 1, Alternative::LEN * MAX_ALTERNATIVES];
+        let mut alternatives = [Alternative::default(); MAX_ALTERNATIVES];
+        for (alternative, src) in alternatives
+            .iter_mut()
+            .zip(alternatives_src.chunks_exact(Alternative::LEN))
+        {
+            let src = array_ref![src, 0, Alternative::LEN];
+            let (mint_src, amount_src) = array_refs![src, 32, 4];
+            *alternative = Alternative {
+                mint: Pubkey::new_from_array(*mint_src),
+                amount: u32::from_le_bytes(*amount_src),
+            };
+        }
+        Ok(Self {
+            alternative_count: count_src[0],
+            alternatives,
+        })
+    }
+}
+
+/// Terms of English and Dutch auctions, prices are in `token_expected` tokens.
+/// A Dutch auction starts at `start_price` on deposit and ends at `reserve_price` by `end_timestamp`.
+#[derive(Default, Copy, Clone, Debug, PartialEq, Eq)]
+pub struct AuctionTerms {
+    pub reserve_price: u64,
+    pub start_price: u64,
+    pub end_timestamp: i64,
+    /// Highest English auction bid so far, locked in `bid_account`
+    pub highest_bid: u64,
+    pub highest_bidder: Pubkey,
+    pub bid_account: Pubkey,
+}
+
+impl AuctionTerms {
+    /// Dutch auction price at `now`, decaying linearly since `start_timestamp`
+    pub fn current_price(&self, start_timestamp: i64, now: i64) -> u64 {
+        if now >= self.end_timestamp {
+            return self.reserve_price;
+        }
+        let duration = self.end_timestamp.saturating_sub(start_timestamp).max(1) as u128;
+        let elapsed = now.saturating_sub(start_timestamp).max(0) as u128;
+        let decay = self.start_price.saturating_sub(self.reserve_price) as u128;
+        self.start_price - (decay * elapsed / duration) as u64
+    }
+}
+
+impl Sealed for AuctionTerms {}
+impl Pack for AuctionTerms {
+    const LEN: usize = 96;
+
+    fn pack_into_slice(&self, dst: &mut [u8]) {
+        let dst = array_mut_ref![dst, 0, AuctionTerms::LEN];
+        let (
+            reserve_price_dst,
+            start_price_dst,
+            end_timestamp_dst,
+            highest_bid_dst,
+            highest_bidder_dst,
+            bid_account_dst,
+        ) = mut_array_refs![dst, 8, 8, 8, 8, 32, 32];
+
+        *reserve_price_dst = self.reserve_price.to_le_bytes();
+        *start_price_dst = self.start_price.to_le_bytes();
+        *end_timestamp_dst = self.end_timestamp.to_le_bytes();
+        *highest_bid_dst = self.highest_bid.to_le_bytes();
+        highest_bidder_dst.copy_from_slice(self.highest_bidder.as_ref());
+        bid_account_dst.copy_from_slice(self.bid_account.as_ref());
+    }
+
+    fn unpack_from_slice(src: &[u8]) -> Result<Self, ProgramError> {
+        if src.len() < AuctionTerms::LEN {
+            return Err(ProgramError::InvalidAccountData);
+        }
+        let src = array_ref![src, 0, AuctionTerms::LEN];
+        let (
+            reserve_price_src,
+            start_price_src,
+            end_timestamp_src,
+            highest_bid_src,
+            highest_bidder_src,
+            bid_account_src,
+        ) = array_refs![src, 8, 8, 8, 8, 32, 32];
+        Ok(Self {
+            reserve_price: u64::from_le_bytes(*reserve_price_src),
+            start_price: u64::from_le_bytes(*start_price_src),
+            end_timestamp: i64::from_le_bytes(*end_timestamp_src),
+            highest_bid: u64::from_le_bytes(*highest_bid_src),
+            highest_bidder: Pubkey::new_from_array(*highest_bidder_src),
+            bid_account: Pubkey::new_from_array(*bid_account_src),
+        })
+    }
+}
+/// Parties of an arbitrated escrow, the depositor is the buyer
+#[derive(Default, Copy, Clone, Debug, PartialEq, Eq)]
+pub struct ArbiterTerms {
+    pub seller: Pubkey,
+    pub arbiter: Pubkey,
+    pub disputed: bool,
+}
+
+impl Sealed for ArbiterTerms {}
+impl Pack for ArbiterTerms {
+    const LEN: usize = 65;
+
+    fn pack_into_slice(&self, dst: &mut [u8]) {
+        let dst = array_mut_ref![dst, 0, ArbiterTerms::LEN];
+        let (seller_dst, arbiter_dst, disputed_dst) = mut_array_refs![dst, 32, 32, 1];
+
+        seller_dst.copy_from_slice(self.seller.as_ref());
+        arbiter_dst.copy_from_slice(self.arbiter.as_ref());
+        disputed_dst[0] = self.disputed as u8;
+    }
+
+    fn unpack_from_slice(src: &[u8]) -> Result<Self, ProgramError> {
+        if src.len() < ArbiterTerms::LEN {
+            return Err(ProgramError::InvalidAccountData);
+        }
+        let src = array_ref![src, 0, ArbiterTerms::LEN];
+        let (seller_src, arbiter_src, disputed_src) = array_refs![src, 32, 32, 1];
+        Ok(Self {
+            seller: Pubkey::new_from_array(*seller_src),
+            arbiter: Pubkey::new_from_array(*arbiter_src),
+            disputed: disputed_src[0] != 0,
+        })
+    }
+}
+
+/// Approvers of a multisig escrow, `threshold` of them release the deposit to `recipient`
+/// or refund it to the depositor. Approvals are bitmasks over `approvers`.
+#[derive(Default, Copy, Clone, Debug, PartialEq, Eq)]
+pub struct MultisigTerms {
+    pub recipient: Pubkey,
+    pub threshold: u8,
+    pub approver_count: u8,
+    pub release_approvals: u8,
+    pub refund_approvals: u8,
+    pub approvers: [Pubkey; MAX_APPROVERS],
+}
+
+impl MultisigTerms {
+    pub fn approver_index(&self, approver: &Pubkey) -> Option<usize> {
+        self.approvers[..self.approver_count as usize]
+            .iter()
+            .position(|key| key == approver)
+    }
+}
+
+impl Sealed for MultisigTerms {}
+impl Pack for MultisigTerms {
+    const LEN: usize = 36 + 32 * MAX_APPROVERS;
+
+    fn pack_into_slice(&self, dst: &mut [u8]) {
+        let dst = array_mut_ref![dst, 0, MultisigTerms::LEN];
+        let (
+            recipient_dst,
+            threshold_dst,
+            approver_count_dst,
+            release_approvals_dst,
+            refund_approvals_dst,
+            approvers_dst,
+        ) = mut_array_refs![dst, 32, 1, 1, 1, 1, 32 * MAX_APPROVERS];
+
+        recipient_dst.copy_from_slice(self.recipient.as_ref());
+        threshold_dst[0] = self.threshold;
+        approver_count_dst[0] = self.approver_count;
+        release_approvals_dst[0] = self.release_approvals;
+        refund_approvals_dst[0] = self.refund_approvals;
+        for (approver_dst, approver) in approvers_dst.chunks_exact_mut(32).zip(&self.approvers) {
+            approver_dst.copy_from_slice(approver.as_ref());
+        }
+    }
+
+    fn unpack_from_slice(src: &[u8]) -> Result<Self, ProgramError> {
+        if src.len() < MultisigTerms::LEN {
+            return Err(ProgramError::InvalidAccountData);
+        }
+        let src = array_ref![src, 0, MultisigTerms::LEN];
+        let (
+            recipient_src,
+            threshold_src,
+            approver_count_src,
+            release_approvals_src,
+            refund_approvals_src,
+            approvers_src,
+        ) = array_refs![src, 32, 1, 1, 1, 1, 32 * MAX_APPROVERS];
+        if approver_count_src[0] as usize > MAX_APPROVERS {
+            return Err(ProgramError::InvalidAccountData);
+        }
+        let mut approvers = [Pubkey::default(); MAX_APPROVERS];
+        for (approver, approver_src) in approvers.iter_mut().zip(approvers_src.chunks_exact(32)) {
+            *approver = Pubkey::try_from(approver_src).unwrap();
+        }
+        Ok(Self {
+            recipient: Pubkey::new_from_array(*recipient_src),
+            threshold: threshold_src[0],
+            approver_count: approver_count_src[0],
+            release_approvals: release_approvals_src[0],
+            refund_approvals: refund_approvals_src[0],
+            approvers,
+        })
+    }
+}
+
+/// Part of a milestone escrow, `description_hash` refers to the off-chain description of the work
+#[derive(Default, Copy, Clone, Debug, PartialEq, Eq)]
+pub struct Milestone {
+    pub amount: u64,
+    pub description_hash: [u8; 32],
+}
+
+impl Milestone {
+    pub const LEN: usize = 40;
+}
+
+/// Ordered milestones paid to `payee`, released by the depositor or by `arbiter` if set.
+/// Milestones not released by `deadline`, if set, are refundable to the depositor.
+#[derive(Default, Copy, Clone, Debug, PartialEq, Eq)]
+pub struct MilestoneTerms {
+    pub payee: Pubkey,
+    pub arbiter: Option<Pubkey>,
+    pub deadline: Option<i64>,
+    pub milestone_count: u8,
+    /// Index of the next milestone to release
+    pub released: u8,
+    pub milestones: [Milestone; MAX_MILESTONES],
+}
+
+impl Sealed for MilestoneTerms {}
+impl Pack for MilestoneTerms {
+    const LEN: usize = 74 + Milestone::LEN * MAX_MILESTONES;
+
+    fn pack_into_slice(&self, dst: &mut [u8]) {
+        let dst = array_mut_ref![dst, 0, MilestoneTerms::LEN];
+        let (payee_dst, arbiter_dst, deadline_dst, count_dst, released_dst, milestones_dst) =
+            mut_array_refs![dst, 32, 32, 8, 1, 1, Milestone::LEN * MAX_MILESTONES];
+
+        payee_dst.copy_from_slice(self.payee.as_ref());
+        arbiter_dst.copy_from_slice(self.arbiter.unwrap_or_default().as_ref());
+        *deadline_dst = self.deadline.unwrap_or_default().to_le_bytes();
+        count_dst[0] = self.milestone_count;
+        released_dst[0] = self.released;
+        for (milestone_dst, milestone) in milestones_dst
+            .chunks_exact_mut(Milestone::LEN)
+            .zip(&self.milestones)
+        {
+            milestone_dst[..8].copy_from_slice(&milestone.amount.to_le_bytes());
+            milestone_dst[8..].copy_from_slice(&milestone.description_hash);
+        }
+    }
+
+    fn unpack_from_slice(src: &[u8]) -> Result<Self, ProgramError> {
+        if src.len() < MilestoneTerms::LEN {
+            return Err(ProgramError::InvalidAccountData);
+        }
+        let src = array_ref![src, 0, MilestoneTerms::LEN];
+        let (payee_src, arbiter_src, deadline_src, count_src, released_src, milestones_src) =
+            array_refs![src, 32, 32, 8, 1, 1, Milestone::LEN * MAX_MILESTONES];
+        if count_src[0] as usize > MAX_MILESTONES {
+            return Err(ProgramError::InvalidAccountData);
+        }
+        let arbiter = Pubkey::new_from_array(*arbiter_src);
+        let deadline = i64::from_le_bytes(*deadline_src);
+        let mut milestones = [Milestone::default(); MAX_MILESTONES];
+        for (milestone, milestone_src) in milestones
+            .iter_mut()
+            .zip(milestones_src.chunks_exact(Milestone::LEN))
+        {
+            milestone.amount = u64::from_le_bytes(milestone_src[..8].try_into().unwrap());
+            milestone
+                .description_hash
+                .copy_from_slice(&milestone_src[8..]);
+        }
+        Ok(Self {
+            payee: Pubkey::new_from_array(*payee_src),
+            arbiter: (arbiter != Pubkey::default()).then_some(arbiter),
+            deadline: (deadline != 0).then_some(deadline),
+            milestone_count: count_src[0],
+            released: released_src[0],
+            milestones,
+        })
+    }
+}
+
+/// Hash time-locked escrow, claimable by the preimage of `hashlock` before `timeout`
+/// and refundable to the depositor after it
+#[derive(Default, Copy, Clone, Debug, PartialEq, Eq)]
+pub struct HtlcTerms {
+    pub recipient: Pubkey,
+    pub hashlock: [u8; 32],
+    pub timeout: i64,
+}
+
+impl Sealed for HtlcTerms {}
+impl Pack for HtlcTerms {
+    const LEN: usize = 72;
+
+    fn pack_into_slice(&self, dst: &mut [u8]) {
+        let dst = array_mut_ref![dst, 0, HtlcTerms::LEN];
+        let (recipient_dst, hashlock_dst, timeout_dst) = mut_array_refs![dst, 32, 32, 8];
+
+        recipient_dst.copy_from_slice(self.recipient.as_ref());
+        *hashlock_dst = self.hashlock;
+        *timeout_dst = self.timeout.to_le_bytes();
+    }
+
+    fn unpack_from_slice(src: &[u8]) -> Result<Self, ProgramError> {
+        if src.len() < HtlcTerms::LEN {
+            return Err(ProgramError::InvalidAccountData);
+        }
+        let src = array_ref![src, 0, HtlcTerms::LEN];
+        let (recipient_src, hashlock_src, timeout_src) = array_refs![src, 32, 32, 8];
+        Ok(Self {
+            recipient: Pubkey::new_from_array(*recipient_src),
+            hashlock: *hashlock_src,
+            timeout: i64::from_le_bytes(*timeout_src),
+        })
+    }
+}
+
+/// A whole token account of `amount` tokens of `mint` asked for by a bundle
+#[derive(Default, Copy, Clone, Debug, PartialEq, Eq)]
+pub struct BundleAsset {
+    pub mint: Pubkey,
+    pub amount: u32,
+}
+
+impl BundleAsset {
+    pub const LEN: usize = 36;
+}
+
+/// Terms of a bundle: the holding accounts deposited besides `holding_account` and the assets
+/// asked for in return, one taker account each
+#[derive(Default, Copy, Clone, Debug, PartialEq, Eq)]
+pub struct BundleTerms {
+    pub extra_holding_count: u8,
+    pub extra_holdings: [Pubkey; MAX_BUNDLE_LEGS - 1],
+    pub wanted_count: u8,
+    pub wanted: [BundleAsset; MAX_BUNDLE_LEGS],
+}
+
+impl BundleTerms {
+    /// The holding accounts deposited besides `holding_account`
+    pub fn extra_holdings(&self) -> &[Pubkey] {
+        &self.extra_holdings[..(self.extra_holding_count as usize).min(MAX_BUNDLE_LEGS - 1)]
+    }
+
+    /// The assets asked for, in the order the taker accounts are passed
+    pub fn wanted(&self) -> &[BundleAsset] {
+        &self.wanted[..(self.wanted_count as usize).min(MAX_BUNDLE_LEGS)]
+    }
+}
+
+impl Sealed for BundleTerms {}
+impl Pack for BundleTerms {
+    const LEN: usize = 2 + 32 * (MAX_BUNDLE_LEGS - 1) + BundleAsset::LEN * MAX_BUNDLE_LEGS;
+
+    fn pack_into_slice(&self, dst: &mut [u8]) {
+        let dst = array_mut_ref![dst, 0, BundleTerms::LEN];
+        let (extra_holding_count_dst, extra_holdings_dst, wanted_count_dst, wanted_dst) = mut_array_refs![
+            dst,
+            1,
+            32 * (MAX_BUNDLE_LEGS - 1),
+            1,
+            BundleAsset::LEN * MAX_BUNDLE_LEGS
+        ];
+
+        extra_holding_count_dst[0] = self.extra_holding_count;
+        for (holding, dst) in self
+            .extra_holdings
+            .iter()
+            .zip(extra_holdings_dst.chunks_exact_mut(32))
+        {
+            dst.copy_from_slice(holding.as_ref());
+        }
+        wanted_count_dst[0] = self.wanted_count;
+        for (asset, dst) in self
+            .wanted
+            .iter()
+            .zip(wanted_dst.chunks_exact_mut(BundleAsset::LEN))
+        {
+            dst[..32].copy_from_slice(asset.mint.as_ref());
+            dst[32..].copy_from_slice(&asset.amount.to_le_bytes());
+        }
+    }
+
+    fn unpack_from_slice(src: &[u8]) -> Result<Self, ProgramError> {
+        if src.len() < BundleTerms::LEN {
+            return Err(ProgramError::InvalidAccountData);
+        }
+        let src = array_ref![src, 0, BundleTerms::LEN];
+        let (extra_holding_count_src, extra_holdings_src, wanted_count_src, wanted_src) = array_refs![
+            src,
+            1,
+            32 * (MAX_BUNDLE_LEGS - 1),
+            1,
+            BundleAsset::LEN * MAX_BUNDLE_LEGS
+        ];
+        let mut terms = Self {
+            extra_holding_count: extra_holding_count_src[0],
+            wanted_count: wanted_count_src[0],
+            ..Self::default()
+        };
+        for (holding, src) in terms
+            .extra_holdings
+            .iter_mut()
+            .zip(extra_holdings_src.chunks_exact(32))
+        {
+            *holding = Pubkey::new_from_array(*array_ref![src, 0, 32]);
+        }
+        for (asset, src) in terms
+            .wanted
+            .iter_mut()
+            .zip(wanted_src.chunks_exact(BundleAsset::LEN))
+        {
+            let src = array_ref![src, 0, BundleAsset::LEN];
+            let (mint_src, amount_src) = array_refs![src, 32, 4];
+            *asset = BundleAsset {
+                mint: Pubkey::new_from_array(*mint_src),
+                amount: u32::from_le_bytes(*amount_src),
+            };
+        }
+        Ok(terms)
+    }
+}
+
+/// Non-custodial deposit: the registry transfers `amount` tokens of the holding account as its
+/// delegate, the taker pays `amount_expected` into `proceeds_account` of the maker
+#[derive(Default, Copy, Clone, Debug, PartialEq, Eq)]
+pub struct DelegatedTerms {
+    pub amount: u64,
+    pub proceeds_account: Pubkey,
+}
+
+impl Sealed for DelegatedTerms {}
+impl Pack for DelegatedTerms {
+    const LEN: usize = 40;
+
+    fn pack_into_slice(&self, dst: &mut [u8]) {
+        let dst = array_mut_ref![dst, 0, DelegatedTerms::LEN];
+        let (amount_dst, proceeds_account_dst) = mut_array_refs![dst, 8, 32];
+
+        *amount_dst = self.amount.to_le_bytes();
+        proceeds_account_dst.copy_from_slice(self.proceeds_account.as_ref());
+    }
+
+    fn unpack_from_slice(src: &[u8]) -> Result<Self, ProgramError> {
+        if src.len() < DelegatedTerms::LEN {
+            return Err(ProgramError::InvalidAccountData);
+        }
+        let src = array_ref![src, 0, DelegatedTerms::LEN];
+        let (amount_src, proceeds_account_src) = array_refs![src, 8, 32];
+        Ok(Self {
+            amount: u64::from_le_bytes(*amount_src),
+            proceeds_account: Pubkey::new_from_array(*proceeds_account_src),
+        })
+    }
+}
+
+/// Record kept in a bid vault PDA, which owns `bid_account` while the bid is locked.
+/// `sequence` is the one of the RFQ escrow the bid was made on.
+#[derive(Default, Copy, Clone, Debug, PartialEq, Eq)]
+pub struct BidRecord {
+    pub holding_account: Pubkey,
+    pub bidder: Pubkey,
+    pub bid_account: Pubkey,
+    pub amount: u64,
+    pub sequence: u64,
+}
+
+impl Sealed for BidRecord {}
+impl Pack for BidRecord {
+    const LEN: usize = 112;
+
+    fn pack_into_slice(&self, dst: &mut [u8]) {
+        let dst = array_mut_ref![dst, 0, BidRecord::LEN];
+        let (holding_account_dst, bidder_dst, bid_account_dst, amount_dst, sequence_dst) =
+            mut_array_refs![dst, 32, 32, 32, 8, 8];
+
+        holding_account_dst.copy_from_slice(self.holding_account.as_ref());
+        bidder_dst.copy_from_slice(self.bidder.as_ref());
+        bid_account_dst.copy_from_slice(self.bid_account.as_ref());
+        *amount_dst = self.amount.to_le_bytes();
+        *sequence_dst = self.sequence.to_le_bytes();
+    }
+
+    fn unpack_from_slice(src: &[u8]) -> Result<Self, ProgramError> {
+        if src.len() < BidRecord::LEN {
+            return Err(ProgramError::InvalidAccountData);
+        }
+        let src = array_ref![src, 0, BidRecord::LEN];
+        let (holding_account_src, bidder_src, bid_account_src, amount_src, sequence_src) =
+            array_refs![src, 32, 32, 32, 8, 8];
+        Ok(Self {
+            holding_account: Pubkey::new_from_array(*holding_account_src),
+            bidder: Pubkey::new_from_array(*bidder_src),
+            bid_account: Pubkey::new_from_array(*bid_account_src),
+            amount: u64::from_le_bytes(*amount_src),
+            sequence: u64::from_le_bytes(*sequence_src),
+        })
+    }
+}
+
+/// Offer signed off-chain by `maker`: `maker_amount` of `maker_mint` for `taker_amount` of
+/// `taker_mint`, fillable once before `expiry` while the registry is a delegate of the maker
+#[derive(Default, Copy, Clone, Debug, PartialEq, Eq)]
+pub struct SignedOffer {
+    pub maker: Pubkey,
+    pub maker_mint: Pubkey,
+    pub maker_amount: u64,
+    pub taker_mint: Pubkey,
+    pub taker_amount: u64,
+    pub expiry: i64,
+    pub nonce: u64,
+}
+
+impl SignedOffer {
+    /// Message the maker signs, the offer bound to the escrow program
+    pub fn message(&self, program_id: &Pubkey) -> Vec<u8> {
+        let mut message = vec![0; 32 + SignedOffer::LEN];
+        message[..32].copy_from_slice(program_id.as_ref());
+        self.pack_into_slice(&mut message[32..]);
+        message
+    }
+}
+
+impl Sealed for SignedOffer {}
+impl Pack for SignedOffer {
+    const LEN: usize = 128;
+
+    fn pack_into_slice(&self, dst: &mut [u8]) {
+        let dst = array_mut_ref![dst, 0, SignedOffer::LEN];
+        let (
+            maker_dst,
+            maker_mint_dst,
+            maker_amount_dst,
+            taker_mint_dst,
+            taker_amount_dst,
+            expiry_dst,
+            nonce_dst,
+        ) = mut_array_refs![dst, 32, 32, 8, 32, 8, 8, 8];
+
+        maker_dst.copy_from_slice(self.maker.as_ref());
+        maker_mint_dst.copy_from_slice(self.maker_mint.as_ref());
+        *maker_amount_dst = self.maker_amount.to_le_bytes();
+        taker_mint_dst.copy_from_slice(self.taker_mint.as_ref());
+        *taker_amount_dst = self.taker_amount.to_le_bytes();
+        *expiry_dst = self.expiry.to_le_bytes();
+        *nonce_dst = self.nonce.to_le_bytes();
+    }
+
+    fn unpack_from_slice(src: &[u8]) -> Result<Self, ProgramError> {
+        if src.len() < SignedOffer::LEN {
+            return Err(ProgramError::InvalidAccountData);
+        }
+        let src = array_ref![src, 0, SignedOffer::LEN];
+        let (
+            maker_src,
+            maker_mint_src,
+            maker_amount_src,
+            taker_mint_src,
+            taker_amount_src,
+            expiry_src,
+            nonce_src,
+        ) = array_refs![src, 32, 32, 8, 32, 8, 8, 8];
+        Ok(Self {
+            maker: Pubkey::new_from_array(*maker_src),
+            maker_mint: Pubkey::new_from_array(*maker_mint_src),
+            maker_amount: u64::from_le_bytes(*maker_amount_src),
+            taker_mint: Pubkey::new_from_array(*taker_mint_src),
+            taker_amount: u64::from_le_bytes(*taker_amount_src),
+            expiry: i64::from_le_bytes(*expiry_src),
+            nonce: u64::from_le_bytes(*nonce_src),
+        })
+    }
+}
+
 impl Sealed for Escrow {}
 impl Pack for Escrow {
     const LEN: usize = DATA_LEN;
@@ -76,16 +979,29 @@ impl Pack for Escrow {
             token_expected_dst,
             holding_account_dst,
             owner_account_dst,
-        ) = mut_array_refs![dst, 1, 4, 32, 32, 32];
+            sequence_dst,
+            deposit_slot_dst,
+            deposit_timestamp_dst,
+            kind_dst,
+            terms_dst,
+        ) = mut_array_refs![dst, 1, 4, 32, 32, 32, 8, 8, 8, 1, TERMS_LEN];
 
         active_dst[0] = self.active as u8;
         *amount_expected_dst = self.amount_expected.to_le_bytes();
         token_expected_dst.copy_from_slice(self.token_expected.as_ref());
         holding_account_dst.copy_from_slice(self.holding_account.as_ref());
         owner_account_dst.copy_from_slice(self.owner_account.as_ref());
+        *sequence_dst = self.sequence.to_le_bytes();
+        *deposit_slot_dst = self.deposit_slot.to_le_bytes();
+        *deposit_timestamp_dst = self.deposit_timestamp.to_le_bytes();
+        kind_dst[0] = self.kind as u8;
+        *terms_dst = self.terms;
     }
 
     fn unpack_from_slice(src: &[u8]) -> Result<Self, ProgramError> {
+        if src.len() < Escrow::LEN {
+            return Err(ProgramError::InvalidAccountData);
+        }
         let src = array_ref![src, 0, Escrow::LEN];
         let (
             active_src,
@@ -93,13 +1009,23 @@ impl Pack for Escrow {
             token_expected_src,
             holding_account_src,
             owner_account_src,
-        ) = array_refs![src, 1, 4, 32, 32, 32];
+            sequence_src,
+            deposit_slot_src,
+            deposit_timestamp_src,
+            kind_src,
+            terms_src,
+        ) = array_refs![src, 1, 4, 32, 32, 32, 8, 8, 8, 1, TERMS_LEN];
         Ok(Self {
             active: active_src[0] != 0,
             amount_expected: u32::from_le_bytes(*amount_expected_src),
             token_expected: Pubkey::new_from_array(*token_expected_src),
             holding_account: Pubkey::new_from_array(*holding_account_src),
             owner_account: Pubkey::new_from_array(*owner_account_src),
+            sequence: u64::from_le_bytes(*sequence_src),
+            deposit_slot: u64::from_le_bytes(*deposit_slot_src),
+            deposit_timestamp: i64::from_le_bytes(*deposit_timestamp_src),
+            kind: EscrowKind::from_u8(kind_src[0]).ok_or(ProgramError::InvalidAccountData)?,
+            terms: *terms_src,
         })
     }
 }
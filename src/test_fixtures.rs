@@ -0,0 +1,513 @@
+//! Test fixtures for programs integrating with the escrow, built on `solana-program-test`
+
+use crate::error::EscrowError;
+use crate::instruction;
+use crate::state::{find_registry_address, Escrow, EscrowCollection, ReclaimDelay};
+use solana_program::clock::Clock;
+use solana_program::hash::Hash;
+use solana_program::instruction::{Instruction, InstructionError};
+use solana_program::program_pack::Pack;
+use solana_program::pubkey::Pubkey;
+use solana_program::system_instruction;
+use solana_program_test::{
+    processor, BanksClient, BanksClientError, ProgramTest, ProgramTestContext,
+};
+use solana_sdk::signature::{Keypair, Signer};
+use solana_sdk::transaction::{Transaction, TransactionError};
+use spl_token::state::{Account, Mint};
+
+pub struct UserAccounts {
+    pub mint_account: Keypair,
+    pub wallet_account: Keypair,
+    pub token_account: Keypair,
+}
+
+impl UserAccounts {
+    /// Creates a new mint along with a wallet holding `token_amount` tokens of it
+    pub async fn prepare(
+        client: &mut BanksClient,
+        blockhash: &Hash,
+        payer: &Keypair,
+        mint_authority: &Keypair,
+        token_amount: u64,
+    ) -> Self {
+        let user = UserAccounts {
+            mint_account: Keypair::new(),
+            wallet_account: Keypair::new(),
+            token_account: Keypair::new(),
+        };
+
+        create_mint_account(client, payer, &user.mint_account, mint_authority, blockhash).await;
+
+        create_ata(
+            client,
+            payer,
+            &user.token_account,
+            &user.wallet_account.pubkey(),
+            &user.mint_account.pubkey(),
+            blockhash,
+        )
+        .await;
+        mint_to_user_account(
+            client,
+            payer,
+            &user.mint_account.pubkey(),
+            mint_authority,
+            &user.token_account.pubkey(),
+            blockhash,
+            token_amount,
+        )
+        .await;
+
+        user
+    }
+}
+
+pub async fn check_account_property<F>(client: &mut BanksClient, account: &Pubkey, f: F)
+where
+    F: Fn(Account),
+{
+    let fetched_account = client
+        .get_account(*account)
+        .await
+        .unwrap()
+        .expect("Unable to read account");
+    let account_data = Account::unpack(&fetched_account.data).unwrap();
+    f(account_data);
+}
+
+pub async fn create_mint_account(
+    banks_client: &mut BanksClient,
+    payer: &Keypair,
+    mint_account: &Keypair,
+    mint_authority: &Keypair,
+    recent_blockhash: &Hash,
+) {
+    let rent = banks_client.get_rent().await.expect("Unable to read rent");
+    let mint_rent = rent.minimum_balance(Mint::LEN);
+
+    let create_mint_instruction = system_instruction::create_account(
+        &payer.pubkey(),
+        &mint_account.pubkey(),
+        mint_rent,
+        Mint::LEN as u64,
+        &spl_token::id(),
+    );
+    let init_mint_instruction = spl_token::instruction::initialize_mint(
+        &spl_token::id(),
+        &mint_account.pubkey(),
+        &mint_authority.pubkey(),
+        None,
+        9,
+    )
+    .expect("Unable to init mint account");
+    let mint_tx = Transaction::new_signed_with_payer(
+        &[create_mint_instruction, init_mint_instruction],
+        Some(&payer.pubkey()),
+        &[&payer, &mint_account],
+        *recent_blockhash,
+    );
+    banks_client
+        .process_transaction(mint_tx)
+        .await
+        .expect("Unable to create mint account");
+}
+
+/// Creates a token account of `mint_account_pk` owned by `user_owner_pk`
+pub async fn create_ata(
+    banks_client: &mut BanksClient,
+    payer: &Keypair,
+    user_account: &Keypair,
+    user_owner_pk: &Pubkey,
+    mint_account_pk: &Pubkey,
+    recent_blockhash: &Hash,
+) {
+    let rent = banks_client.get_rent().await.expect("Unable to read rent");
+    let account_rent = rent.minimum_balance(Account::LEN);
+    let create_user_account_ix = system_instruction::create_account(
+        &payer.pubkey(),
+        &user_account.pubkey(),
+        account_rent,
+        Account::LEN as u64,
+        &spl_token::id(),
+    );
+
+    let init_user_account_ix = spl_token::instruction::initialize_account(
+        &spl_token::id(),
+        &user_account.pubkey(),
+        mint_account_pk,
+        user_owner_pk,
+    )
+    .expect("Unable to init user token account");
+
+    let create_user_account_tx = Transaction::new_signed_with_payer(
+        &[create_user_account_ix, init_user_account_ix],
+        Some(&payer.pubkey()),
+        &[&payer, &user_account],
+        *recent_blockhash,
+    );
+    banks_client
+        .process_transaction(create_user_account_tx)
+        .await
+        .expect("Unable to create token account");
+}
+
+pub async fn mint_to_user_account(
+    banks_client: &mut BanksClient,
+    payer: &Keypair,
+    mint_account: &Pubkey,
+    mint_authority: &Keypair,
+    user_account: &Pubkey,
+    recent_blockhash: &Hash,
+    amount: u64,
+) {
+    let mint_to_ix = spl_token::instruction::mint_to(
+        &spl_token::id(),
+        mint_account,
+        user_account,
+        &mint_authority.pubkey(),
+        &[],
+        amount,
+    )
+    .expect("Unable to mint to user account");
+    let mint_to_tx = Transaction::new_signed_with_payer(
+        &[mint_to_ix],
+        Some(&payer.pubkey()),
+        &[&payer, &mint_authority],
+        *recent_blockhash,
+    );
+    banks_client
+        .process_transaction(mint_to_tx)
+        .await
+        .expect("Unable to mint tokens");
+}
+
+/// Builds an `EscrowTestEnv`
+pub struct EscrowTestEnvBuilder {
+    program_test: ProgramTest,
+    program_id: Pubkey,
+    init_registry: bool,
+}
+
+impl EscrowTestEnvBuilder {
+    /// Address the escrow program is loaded at, a unique one is used by default
+    pub fn program_id(mut self, program_id: Pubkey) -> Self {
+        self.program_id = program_id;
+        self
+    }
+
+    /// Leaves the registry uninitialized, e.g. to test the `Init` instruction itself
+    pub fn without_registry(mut self) -> Self {
+        self.init_registry = false;
+        self
+    }
+
+    /// Gives access to the underlying `ProgramTest`, e.g. to load the program under test
+    pub fn configure<F>(mut self, f: F) -> Self
+    where
+        F: FnOnce(&mut ProgramTest),
+    {
+        f(&mut self.program_test);
+        self
+    }
+
+    pub async fn build(mut self) -> EscrowTestEnv {
+        self.program_test.add_program(
+            "solana_escrow",
+            self.program_id,
+            processor!(crate::process_instruction),
+        );
+        let context = self.program_test.start_with_context().await;
+        let mut env = EscrowTestEnv {
+            banks_client: context.banks_client.clone(),
+            payer: context.payer.insecure_clone(),
+            recent_blockhash: context.last_blockhash,
+            program_id: self.program_id,
+            mint_authority: Keypair::new(),
+            context,
+        };
+        if self.init_registry {
+            env.init_registry()
+                .await
+                .expect("Unable to init an escrow program");
+        }
+        env
+    }
+}
+
+/// Running bank with the escrow program loaded.
+/// All transactions are paid by `payer`, mints are controlled by `mint_authority`.
+pub struct EscrowTestEnv {
+    pub banks_client: BanksClient,
+    pub payer: Keypair,
+    pub recent_blockhash: Hash,
+    pub program_id: Pubkey,
+    pub mint_authority: Keypair,
+    context: ProgramTestContext,
+}
+
+impl EscrowTestEnv {
+    pub fn builder() -> EscrowTestEnvBuilder {
+        EscrowTestEnvBuilder {
+            program_test: ProgramTest::default(),
+            program_id: Pubkey::new_unique(),
+            init_registry: true,
+        }
+    }
+
+    pub fn registry_address(&self) -> Pubkey {
+        find_registry_address(&self.program_id).0
+    }
+
+    /// Sends instructions signed by `payer` and `signers`
+    pub async fn process(
+        &mut self,
+        instructions: &[Instruction],
+        signers: &[&Keypair],
+    ) -> Result<(), BanksClientError> {
+        let transaction = Transaction::new_signed_with_payer(
+            instructions,
+            Some(&self.payer.pubkey()),
+            &[&[&self.payer], signers].concat(),
+            self.banks_client.get_latest_blockhash().await?,
+        );
+        self.banks_client.process_transaction(transaction).await
+    }
+
+    pub async fn init_registry(&mut self) -> Result<(), BanksClientError> {
+        let ix = instruction::init(&self.program_id, &self.payer.pubkey());
+        self.process(&[ix], &[]).await
+    }
+
+    pub async fn create_mint(&mut self) -> Keypair {
+        let mint = Keypair::new();
+        create_mint_account(
+            &mut self.banks_client,
+            &self.payer,
+            &mint,
+            &self.mint_authority,
+            &self.recent_blockhash,
+        )
+        .await;
+        mint
+    }
+
+    /// Creates an empty token account of `mint` owned by `owner`
+    pub async fn create_token_account(&mut self, owner: &Pubkey, mint: &Pubkey) -> Keypair {
+        let token_account = Keypair::new();
+        create_ata(
+            &mut self.banks_client,
+            &self.payer,
+            &token_account,
+            owner,
+            mint,
+            &self.recent_blockhash,
+        )
+        .await;
+        token_account
+    }
+
+    pub async fn mint_to(&mut self, mint: &Pubkey, token_account: &Pubkey, amount: u64) {
+        mint_to_user_account(
+            &mut self.banks_client,
+            &self.payer,
+            mint,
+            &self.mint_authority,
+            token_account,
+            &self.recent_blockhash,
+            amount,
+        )
+        .await;
+    }
+
+    /// Creates a user holding `token_amount` tokens of a new mint
+    pub async fn create_user(&mut self, token_amount: u64) -> UserAccounts {
+        UserAccounts::prepare(
+            &mut self.banks_client,
+            &self.recent_blockhash,
+            &self.payer,
+            &self.mint_authority,
+            token_amount,
+        )
+        .await
+    }
+
+    /// Deposits the user token account asking for `amount_expected` of `token_expected`
+    pub async fn deposit(
+        &mut self,
+        user: &UserAccounts,
+        token_expected: &Pubkey,
+        amount_expected: u32,
+    ) -> Result<(), BanksClientError> {
+        let ix = instruction::deposit(
+            &self.program_id,
+            &user.wallet_account.pubkey(),
+            &user.token_account.pubkey(),
+            token_expected,
+            amount_expected,
+        );
+        self.process(&[ix], &[&user.wallet_account]).await
+    }
+
+    /// Swaps the user token account with `deposit_account`, expecting the escrow currently holding it
+    pub async fn execute(
+        &mut self,
+        user: &UserAccounts,
+        deposit_account: &Pubkey,
+        token_expected: &Pubkey,
+        amount_expected: u32,
+    ) -> Result<(), BanksClientError> {
+        let sequence = self
+            .registry()
+            .await
+            .find_by_token_account(deposit_account)
+            .map_or(0, |escrow| escrow.sequence);
+        let ix = instruction::execute(
+            &self.program_id,
+            &user.wallet_account.pubkey(),
+            &user.token_account.pubkey(),
+            token_expected,
+            deposit_account,
+            amount_expected,
+            sequence,
+        );
+        self.process(&[ix], &[&user.wallet_account]).await
+    }
+
+    /// Swaps `owner` token accounts with several deposits in one instruction
+    pub async fn execute_batch(
+        &mut self,
+        owner: &Keypair,
+        legs: &[instruction::ExecuteLeg],
+    ) -> Result<(), BanksClientError> {
+        let ix = instruction::execute_batch(&self.program_id, &owner.pubkey(), legs);
+        self.process(&[ix], &[owner]).await
+    }
+
+    /// Sets the match reward and the reclaim delay, the registry admin is `payer`
+    pub async fn configure(
+        &mut self,
+        match_reward_lamports: u64,
+        reclaim_delay: ReclaimDelay,
+    ) -> Result<(), BanksClientError> {
+        let ix = instruction::configure(
+            &self.program_id,
+            &self.payer.pubkey(),
+            match_reward_lamports,
+            reclaim_delay,
+        );
+        self.process(&[ix], &[]).await
+    }
+
+    /// Matches two deposits on behalf of `matcher`
+    pub async fn match_escrows(
+        &mut self,
+        matcher: &Keypair,
+        first_deposit: &Pubkey,
+        second_deposit: &Pubkey,
+    ) -> Result<(), BanksClientError> {
+        let ix = instruction::match_escrows(
+            &self.program_id,
+            &matcher.pubkey(),
+            first_deposit,
+            second_deposit,
+        );
+        self.process(&[ix], &[matcher]).await
+    }
+
+    /// Changes the terms of the user escrow
+    pub async fn amend(
+        &mut self,
+        user: &UserAccounts,
+        token_expected: &Pubkey,
+        amount_expected: u32,
+    ) -> Result<(), BanksClientError> {
+        let ix = instruction::amend(
+            &self.program_id,
+            &user.wallet_account.pubkey(),
+            &user.token_account.pubkey(),
+            token_expected,
+            amount_expected,
+        );
+        self.process(&[ix], &[&user.wallet_account]).await
+    }
+
+    /// Takes the deposited user token account back
+    pub async fn reclaim(&mut self, user: &UserAccounts) -> Result<(), BanksClientError> {
+        let ix = instruction::reclaim(
+            &self.program_id,
+            &user.wallet_account.pubkey(),
+            &user.token_account.pubkey(),
+        );
+        self.process(&[ix], &[&user.wallet_account]).await
+    }
+
+    /// Moves the bank `slots` (at least one) and the clock `seconds` forward
+    pub async fn warp_clock(&mut self, slots: u64, seconds: i64) {
+        let clock: Clock = self.banks_client.get_sysvar().await.unwrap();
+        self.context
+            .warp_to_slot(clock.slot + slots.max(1))
+            .expect("Unable to warp to slot");
+        let mut warped: Clock = self.banks_client.get_sysvar().await.unwrap();
+        warped.unix_timestamp = clock.unix_timestamp + seconds;
+        self.context.set_sysvar(&warped);
+    }
+
+    pub async fn registry(&mut self) -> EscrowCollection {
+        let account = self
+            .banks_client
+            .get_account(self.registry_address())
+            .await
+            .unwrap()
+            .expect("Escrow registry is not initialized");
+        EscrowCollection::unpack_from_slice(&account.data).unwrap()
+    }
+
+    pub async fn token_account(&mut self, address: &Pubkey) -> Account {
+        let account = self
+            .banks_client
+            .get_account(*address)
+            .await
+            .unwrap()
+            .expect("Unable to read account");
+        Account::unpack(&account.data).unwrap()
+    }
+
+    /// Asserts `holding_account` is in escrow and returns its record
+    pub async fn assert_escrow(&mut self, holding_account: &Pubkey) -> Escrow {
+        *self
+            .registry()
+            .await
+            .find_by_token_account(holding_account)
+            .unwrap_or_else(|| panic!("No active escrow holds account {}", holding_account))
+    }
+
+    pub async fn assert_no_escrow(&mut self, holding_account: &Pubkey) {
+        assert!(
+            self.registry()
+                .await
+                .find_by_token_account(holding_account)
+                .is_none(),
+            "Account {} is expected not to be in escrow",
+            holding_account
+        );
+    }
+
+    pub async fn assert_token_owner(&mut self, token_account: &Pubkey, owner: &Pubkey) {
+        assert_eq!(self.token_account(token_account).await.owner, *owner);
+    }
+}
+
+/// Asserts the transaction failed with `expected` in one of its instructions
+pub fn assert_instruction_error(err: BanksClientError, expected: InstructionError) {
+    match err {
+        BanksClientError::TransactionError(TransactionError::InstructionError(_, err)) => {
+            assert_eq!(err, expected)
+        }
+        err => panic!("Unexpected error: {:?}", err),
+    }
+}
+
+pub fn assert_escrow_error(err: BanksClientError, expected: EscrowError) {
+    assert_instruction_error(err, InstructionError::Custom(expected as u32));
+}
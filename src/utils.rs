@@ -1,15 +1,31 @@
-use crate::state::SEED;
+use crate::error::{throw_and_log, EscrowError};
+use crate::state::{find_registry_address, Escrow, EscrowKind, ReclaimDelay};
 use solana_program::account_info::AccountInfo;
+use solana_program::clock::Clock;
+use solana_program::ed25519_program;
 use solana_program::msg;
 use solana_program::program_error::ProgramError;
+use solana_program::program_option::COption;
 use solana_program::pubkey::Pubkey;
 use solana_program::rent::Rent;
+use solana_program::sysvar::instructions::{
+    load_current_index_checked, load_instruction_at_checked,
+};
+use solana_program::sysvar::Sysvar;
+
+/// Start of the public key, signature and message in an ed25519 instruction with one signature
+const ED25519_DATA_START: usize = 16;
+
+/// Lowercase hex of `bytes` for logging hashes and preimages
+pub fn to_hex(bytes: &[u8]) -> String {
+    bytes.iter().map(|byte| format!("{:02x}", byte)).collect()
+}
 
 pub fn verify_pda(
     pda_account_info: &AccountInfo,
     program_id: &Pubkey,
 ) -> Result<(Pubkey, u8), ProgramError> {
-    let (pda, bump_seed) = Pubkey::find_program_address(&[SEED], program_id);
+    let (pda, bump_seed) = find_registry_address(program_id);
 
     if pda != *pda_account_info.key {
         msg!("Incorrect PDA account provided to the instruction");
@@ -26,3 +42,113 @@ pub fn verify_rent_exemption(account_info: &AccountInfo) -> Result<(), ProgramEr
     };
     Ok(())
 }
+
+pub fn verify_signer(account_info: &AccountInfo) -> Result<(), ProgramError> {
+    if !account_info.is_signer {
+        msg!(
+            "Account {} is required to sign the instruction",
+            account_info.key
+        );
+        return Err(ProgramError::MissingRequiredSignature);
+    }
+    Ok(())
+}
+
+pub fn verify_writable(account_info: &AccountInfo) -> Result<(), ProgramError> {
+    if !account_info.is_writable {
+        msg!("Account {} is required to be writable", account_info.key);
+        return Err(ProgramError::InvalidArgument);
+    }
+    Ok(())
+}
+
+pub fn verify_reclaim_delay(
+    escrow: &Escrow,
+    reclaim_delay: ReclaimDelay,
+) -> Result<(), ProgramError> {
+    let clock = Clock::get()?;
+    let too_early = match reclaim_delay {
+        ReclaimDelay::None => false,
+        ReclaimDelay::Slots(slots) => clock.slot < escrow.deposit_slot.saturating_add(slots),
+        ReclaimDelay::Seconds(seconds) => {
+            clock.unix_timestamp < escrow.deposit_timestamp.saturating_add(seconds as i64)
+        }
+    };
+    if too_early {
+        msg!(
+            "Escrow deposited at slot {} ({}), reclaim delay is {:?}",
+            escrow.deposit_slot,
+            escrow.deposit_timestamp,
+            reclaim_delay
+        );
+        return Err(throw_and_log(EscrowError::ReclaimTooEarly));
+    }
+    Ok(())
+}
+
+/// Checks that the instruction preceding the current one has the ed25519 program verify a
+/// signature of `signer` over `message`, both taken from that same instruction
+pub fn verify_ed25519_signature(
+    instructions_sysvar: &AccountInfo,
+    signer: &Pubkey,
+    message: &[u8],
+) -> Result<(), ProgramError> {
+    let current_index = load_current_index_checked(instructions_sysvar)?;
+    let verify_ix = current_index
+        .checked_sub(1)
+        .map(|index| load_instruction_at_checked(index as usize, instructions_sysvar))
+        .transpose()?
+        .filter(|ix| ed25519_program::check_id(&ix.program_id))
+        .ok_or_else(|| {
+            msg!("ed25519 instruction is expected right before this one");
+            throw_and_log(EscrowError::InvalidOfferSignature)
+        })?;
+
+    let data = &verify_ix.data;
+    let read_u16 = |offset: usize| {
+        data.get(offset..offset + 2)
+            .map(|bytes| u16::from_le_bytes([bytes[0], bytes[1]]) as usize)
+    };
+    let this_instruction = u16::MAX as usize;
+    let verified = data.len() >= ED25519_DATA_START
+        && data[0] == 1
+        // signature, public key and message are all read from the ed25519 instruction itself
+        && [4, 8, 14].iter().all(|&offset| read_u16(offset) == Some(this_instruction))
+        && read_u16(6).and_then(|key| data.get(key..key + 32)) == Some(signer.as_ref())
+        && read_u16(10)
+            .zip(read_u16(12))
+            .and_then(|(start, size)| data.get(start..start + size))
+            == Some(message);
+    if !verified {
+        return Err(throw_and_log(EscrowError::InvalidOfferSignature));
+    }
+    Ok(())
+}
+
+/// Checks that `delegate` may transfer `amount` tokens of `token_account` on behalf of its owner
+pub fn verify_delegation(
+    token_account: &spl_token::state::Account,
+    delegate: &Pubkey,
+    amount: u64,
+) -> Result<(), ProgramError> {
+    if token_account.delegate != COption::Some(*delegate) || token_account.delegated_amount < amount
+    {
+        msg!(
+            "{} tokens are delegated to {:?}, {} to {} required",
+            token_account.delegated_amount,
+            token_account.delegate,
+            amount,
+            delegate
+        );
+        return Err(throw_and_log(EscrowError::InsufficientDelegation));
+    }
+    Ok(())
+}
+
+pub fn verify_kind(escrow: &Escrow, kind: EscrowKind) -> Result<(), ProgramError> {
+    if escrow.kind != kind {
+        msg!("Escrow kind is {:?}, {:?} expected", escrow.kind, kind);
+        return Err(throw_and_log(EscrowError::WrongEscrowKind));
+    }
+    Ok(())
+}
@@ -0,0 +1,165 @@
+use solana_escrow::test_fixtures::{assert_instruction_error, EscrowTestEnv, UserAccounts};
+use solana_escrow::{instruction, Alternative, EscrowError, SwapTerms};
+use solana_program_test::{tokio, BanksClientError};
+use solana_sdk::instruction::InstructionError;
+use solana_sdk::signature::{Keypair, Signer};
+
+/// Deposits the account of `maker` for 6 tokens of `usdc` or 5 tokens of `usdt`
+async fn deposit_alternatives(
+    env: &mut EscrowTestEnv,
+    maker: &UserAccounts,
+    usdc: &UserAccounts,
+    usdt: &UserAccounts,
+    alternative_mint: Option<&UserAccounts>,
+) -> Result<(), BanksClientError> {
+    let ix = instruction::deposit_alternatives(
+        &env.program_id,
+        &maker.wallet_account.pubkey(),
+        &maker.token_account.pubkey(),
+        &usdc.mint_account.pubkey(),
+        6,
+        &[Alternative {
+            mint: alternative_mint.unwrap_or(usdt).mint_account.pubkey(),
+            amount: 5,
+        }],
+    );
+    env.process(&[ix], &[&maker.wallet_account]).await
+}
+
+#[tokio::test]
+async fn execute_alternative_test() {
+    let mut env = EscrowTestEnv::builder().build().await;
+    let alice = env.create_user(10).await;
+    let usdc = env.create_user(6).await;
+    let usdt = env.create_user(5).await;
+    let dave = env.create_user(6).await;
+    let holding = alice.token_account.pubkey();
+
+    let err = deposit_alternatives(&mut env, &alice, &usdc, &usdt, Some(&usdc))
+        .await
+        .unwrap_err();
+    assert_instruction_error(err, InstructionError::InvalidArgument);
+
+    deposit_alternatives(&mut env, &alice, &usdc, &usdt, None)
+        .await
+        .expect("Unable to deposit with alternatives");
+    let terms: SwapTerms = env.assert_escrow(&holding).await.terms().unwrap();
+    assert_eq!(terms.alternatives().len(), 1);
+
+    let alice_mint = alice.mint_account.pubkey();
+    let err = env
+        .execute(&dave, &holding, &alice_mint, 10)
+        .await
+        .unwrap_err();
+    assert_instruction_error(
+        err,
+        InstructionError::Custom(EscrowError::ExecutorTokenMintMismatch as u32),
+    );
+
+    // The alternative fills the escrow, which closes
+    env.execute(&usdt, &holding, &alice_mint, 10)
+        .await
+        .expect("Unable to execute with an alternative mint");
+    env.assert_no_escrow(&holding).await;
+    env.assert_token_owner(&holding, &usdt.wallet_account.pubkey())
+        .await;
+    env.assert_token_owner(&usdt.token_account.pubkey(), &alice.wallet_account.pubkey())
+        .await;
+
+    let err = env
+        .execute(&usdc, &holding, &alice_mint, 10)
+        .await
+        .unwrap_err();
+    assert_instruction_error(
+        err,
+        InstructionError::Custom(EscrowError::NoAvailableEscrowAccounts as u32),
+    );
+}
+
+#[tokio::test]
+async fn match_alternative_test() {
+    let mut env = EscrowTestEnv::builder().build().await;
+    let alice = env.create_user(10).await;
+    let usdc = env.create_user(6).await;
+    let usdt = env.create_user(5).await;
+
+    deposit_alternatives(&mut env, &alice, &usdc, &usdt, None)
+        .await
+        .expect("Unable to deposit with alternatives");
+    env.deposit(&usdt, &alice.mint_account.pubkey(), 10)
+        .await
+        .expect("Unable to deposit");
+
+    let matcher = Keypair::new();
+    env.match_escrows(
+        &matcher,
+        &alice.token_account.pubkey(),
+        &usdt.token_account.pubkey(),
+    )
+    .await
+    .expect("Unable to match an alternative");
+    env.assert_token_owner(&alice.token_account.pubkey(), &usdt.wallet_account.pubkey())
+        .await;
+    env.assert_token_owner(&usdt.token_account.pubkey(), &alice.wallet_account.pubkey())
+        .await;
+}
+
+#[tokio::test]
+async fn changed_terms_drop_alternatives_test() {
+    let mut env = EscrowTestEnv::builder().build().await;
+    let alice = env.create_user(10).await;
+    let usdc = env.create_user(6).await;
+    let usdt = env.create_user(5).await;
+    let holding = alice.token_account.pubkey();
+    let alice_mint = alice.mint_account.pubkey();
+
+    // Re-quoted in the expected mint, the old alternative is gone even at the same amount
+    deposit_alternatives(&mut env, &alice, &usdc, &usdt, None)
+        .await
+        .expect("Unable to deposit with alternatives");
+    env.amend(&alice, &usdc.mint_account.pubkey(), 5)
+        .await
+        .expect("Unable to amend");
+    let terms: SwapTerms = env.assert_escrow(&holding).await.terms().unwrap();
+    assert!(terms.alternatives().is_empty());
+    let err = env
+        .execute(&usdt, &holding, &alice_mint, 10)
+        .await
+        .unwrap_err();
+    assert_instruction_error(
+        err,
+        InstructionError::Custom(EscrowError::ExecutorTokenMintMismatch as u32),
+    );
+
+    env.reclaim(&alice).await.expect("Unable to reclaim");
+    deposit_alternatives(&mut env, &alice, &usdc, &usdt, None)
+        .await
+        .expect("Unable to deposit with alternatives again");
+
+    // So does a top-up
+    let spare = env
+        .create_token_account(&alice.wallet_account.pubkey(), &alice_mint)
+        .await;
+    env.mint_to(&alice_mint, &spare.pubkey(), 5).await;
+    let ix = instruction::top_up(
+        &env.program_id,
+        &alice.wallet_account.pubkey(),
+        &holding,
+        &spare.pubkey(),
+        5,
+        5,
+    );
+    env.process(&[ix], &[&alice.wallet_account])
+        .await
+        .expect("Unable to top up");
+    let terms: SwapTerms = env.assert_escrow(&holding).await.terms().unwrap();
+    assert!(terms.alternatives().is_empty());
+    let err = env
+        .execute(&usdt, &holding, &alice_mint, 15)
+        .await
+        .unwrap_err();
+    assert_instruction_error(
+        err,
+        InstructionError::Custom(EscrowError::ExecutorTokenMintMismatch as u32),
+    );
+}
@@ -0,0 +1,103 @@
+use solana_escrow::instruction;
+use solana_escrow::test_fixtures::EscrowTestEnv;
+use solana_escrow::EscrowError;
+use solana_program_test::{tokio, BanksClientError};
+use solana_sdk::instruction::InstructionError;
+use solana_sdk::signature::Signer;
+use solana_sdk::transaction::{Transaction, TransactionError};
+
+fn instruction_error(err: BanksClientError) -> InstructionError {
+    match err {
+        BanksClientError::TransactionError(TransactionError::InstructionError(_, err)) => err,
+        err => panic!("Unexpected error: {:?}", err),
+    }
+}
+
+#[tokio::test]
+async fn amend_test() {
+    let mut env = EscrowTestEnv::builder().build().await;
+    let alice = env.create_user(10).await;
+    let bob = env.create_user(5).await;
+
+    env.deposit(&alice, &bob.mint_account.pubkey(), 5)
+        .await
+        .expect("Unable to make a deposit");
+    let before = env.assert_escrow(&alice.token_account.pubkey()).await;
+
+    // Only the depositor amends the escrow
+    let ix = instruction::amend(
+        &env.program_id,
+        &bob.wallet_account.pubkey(),
+        &alice.token_account.pubkey(),
+        &bob.mint_account.pubkey(),
+        1,
+    );
+    let err = env
+        .process(&[ix], &[&bob.wallet_account])
+        .await
+        .unwrap_err();
+    assert_eq!(instruction_error(err), InstructionError::IllegalOwner);
+
+    let ix = instruction::amend(
+        &env.program_id,
+        &alice.wallet_account.pubkey(),
+        &alice.token_account.pubkey(),
+        &bob.mint_account.pubkey(),
+        6,
+    );
+    let tx = Transaction::new_signed_with_payer(
+        &[ix],
+        Some(&env.payer.pubkey()),
+        &[&env.payer, &alice.wallet_account],
+        env.banks_client.get_latest_blockhash().await.unwrap(),
+    );
+    let result = env
+        .banks_client
+        .process_transaction_with_metadata(tx)
+        .await
+        .unwrap();
+    result.result.expect("Unable to amend the escrow");
+    assert!(result
+        .metadata
+        .unwrap()
+        .log_messages
+        .iter()
+        .any(|log| log.ends_with("Escrow amended, new sequence: 2")));
+
+    let after = env.assert_escrow(&alice.token_account.pubkey()).await;
+    assert_eq!(after.amount_expected, 6);
+    assert!(after.sequence > before.sequence);
+    env.assert_token_owner(&alice.token_account.pubkey(), &env.registry_address())
+        .await;
+
+    // Executions aimed at the old terms fail
+    let stale = instruction::execute(
+        &env.program_id,
+        &bob.wallet_account.pubkey(),
+        &bob.token_account.pubkey(),
+        &alice.mint_account.pubkey(),
+        &alice.token_account.pubkey(),
+        10,
+        before.sequence,
+    );
+    let err = env
+        .process(&[stale], &[&bob.wallet_account])
+        .await
+        .unwrap_err();
+    assert_eq!(
+        instruction_error(err),
+        InstructionError::Custom(EscrowError::SequenceMismatch as u32)
+    );
+
+    env.mint_to(&bob.mint_account.pubkey(), &bob.token_account.pubkey(), 1)
+        .await;
+    env.execute(
+        &bob,
+        &alice.token_account.pubkey(),
+        &alice.mint_account.pubkey(),
+        10,
+    )
+    .await
+    .expect("Unable to make an escrow execution");
+    env.assert_no_escrow(&alice.token_account.pubkey()).await;
+}
@@ -0,0 +1,136 @@
+use solana_escrow::instruction;
+use solana_escrow::test_fixtures::{assert_instruction_error, EscrowTestEnv, UserAccounts};
+use solana_escrow::{ArbiterTerms, EscrowError};
+use solana_program::pubkey::Pubkey;
+use solana_program_test::tokio;
+use solana_sdk::instruction::InstructionError;
+use solana_sdk::signature::{Keypair, Signer};
+
+async fn create_arbitrated(
+    env: &mut EscrowTestEnv,
+    buyer: &UserAccounts,
+    seller: &Pubkey,
+    arbiter: &Pubkey,
+) {
+    let ix = instruction::create_arbitrated(
+        &env.program_id,
+        &buyer.wallet_account.pubkey(),
+        &buyer.token_account.pubkey(),
+        &buyer.mint_account.pubkey(),
+        seller,
+        arbiter,
+    );
+    env.process(&[ix], &[&buyer.wallet_account])
+        .await
+        .expect("Unable to create an arbitrated escrow");
+}
+
+#[tokio::test]
+async fn release_and_refund_test() {
+    let mut env = EscrowTestEnv::builder().build().await;
+    let alice = env.create_user(10).await;
+    let dave = env.create_user(10).await;
+    let seller = Keypair::new();
+    let arbiter = Keypair::new();
+
+    create_arbitrated(&mut env, &alice, &seller.pubkey(), &arbiter.pubkey()).await;
+    create_arbitrated(&mut env, &dave, &seller.pubkey(), &arbiter.pubkey()).await;
+
+    // Neither the buyer reclaims nor the seller releases the payment
+    let err = env.reclaim(&alice).await.unwrap_err();
+    assert_instruction_error(
+        err,
+        InstructionError::Custom(EscrowError::WrongEscrowKind as u32),
+    );
+    let ix = instruction::release(
+        &env.program_id,
+        &seller.pubkey(),
+        &alice.token_account.pubkey(),
+    );
+    let err = env.process(&[ix], &[&seller]).await.unwrap_err();
+    assert_instruction_error(err, InstructionError::IllegalOwner);
+
+    let ix = instruction::release(
+        &env.program_id,
+        &alice.wallet_account.pubkey(),
+        &alice.token_account.pubkey(),
+    );
+    env.process(&[ix], &[&alice.wallet_account])
+        .await
+        .expect("Unable to release the payment");
+    env.assert_no_escrow(&alice.token_account.pubkey()).await;
+    env.assert_token_owner(&alice.token_account.pubkey(), &seller.pubkey())
+        .await;
+
+    let ix = instruction::refund(
+        &env.program_id,
+        &seller.pubkey(),
+        &dave.token_account.pubkey(),
+    );
+    env.process(&[ix], &[&seller])
+        .await
+        .expect("Unable to refund the payment");
+    env.assert_no_escrow(&dave.token_account.pubkey()).await;
+    env.assert_token_owner(&dave.token_account.pubkey(), &dave.wallet_account.pubkey())
+        .await;
+}
+
+#[tokio::test]
+async fn dispute_test() {
+    let mut env = EscrowTestEnv::builder().build().await;
+    let alice = env.create_user(100).await;
+    let seller = Keypair::new();
+    let arbiter = Keypair::new();
+    let seller_account = env
+        .create_token_account(&seller.pubkey(), &alice.mint_account.pubkey())
+        .await;
+    let holding = alice.token_account.pubkey();
+
+    create_arbitrated(&mut env, &alice, &seller.pubkey(), &arbiter.pubkey()).await;
+
+    let program_id = env.program_id;
+    let resolve = |arbiter: &Keypair| {
+        instruction::resolve(
+            &program_id,
+            &arbiter.pubkey(),
+            &holding,
+            &seller_account.pubkey(),
+            2_500,
+        )
+    };
+    let err = env
+        .process(&[resolve(&arbiter)], &[&arbiter])
+        .await
+        .unwrap_err();
+    assert_instruction_error(
+        err,
+        InstructionError::Custom(EscrowError::NotDisputed as u32),
+    );
+
+    let stranger = Keypair::new();
+    let ix = instruction::dispute(&env.program_id, &stranger.pubkey(), &holding);
+    let err = env.process(&[ix], &[&stranger]).await.unwrap_err();
+    assert_instruction_error(err, InstructionError::IllegalOwner);
+
+    let ix = instruction::dispute(&env.program_id, &seller.pubkey(), &holding);
+    env.process(&[ix], &[&seller])
+        .await
+        .expect("Unable to open a dispute");
+    let terms: ArbiterTerms = env.assert_escrow(&holding).await.terms().unwrap();
+    assert!(terms.disputed);
+
+    let err = env
+        .process(&[resolve(&stranger)], &[&stranger])
+        .await
+        .unwrap_err();
+    assert_instruction_error(err, InstructionError::IllegalOwner);
+
+    env.process(&[resolve(&arbiter)], &[&arbiter])
+        .await
+        .expect("Unable to resolve the dispute");
+    env.assert_no_escrow(&holding).await;
+    env.assert_token_owner(&holding, &alice.wallet_account.pubkey())
+        .await;
+    assert_eq!(env.token_account(&holding).await.amount, 75);
+    assert_eq!(env.token_account(&seller_account.pubkey()).await.amount, 25);
+}
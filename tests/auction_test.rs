@@ -0,0 +1,180 @@
+use solana_escrow::instruction;
+use solana_escrow::test_fixtures::{assert_escrow_error, EscrowTestEnv};
+use solana_escrow::{AuctionTerms, EscrowError, EscrowKind};
+use solana_program::clock::Clock;
+use solana_program::pubkey::Pubkey;
+use solana_program_test::tokio;
+use solana_sdk::signature::{Keypair, Signer};
+
+/// Creates a bidder wallet with an account holding `amount` tokens of `mint`
+async fn create_bidder(env: &mut EscrowTestEnv, mint: &Pubkey, amount: u64) -> (Keypair, Keypair) {
+    let wallet = Keypair::new();
+    let account = env.create_token_account(&wallet.pubkey(), mint).await;
+    env.mint_to(mint, &account.pubkey(), amount).await;
+    (wallet, account)
+}
+
+async fn now(env: &mut EscrowTestEnv) -> i64 {
+    let clock: Clock = env.banks_client.get_sysvar().await.unwrap();
+    clock.unix_timestamp
+}
+
+#[tokio::test]
+async fn english_auction_test() {
+    let mut env = EscrowTestEnv::builder().build().await;
+    let alice = env.create_user(10).await;
+    let bid_mint = env.create_mint().await;
+    let (bob, bob_bid) = create_bidder(&mut env, &bid_mint.pubkey(), 5).await;
+    let (carol, carol_bid) = create_bidder(&mut env, &bid_mint.pubkey(), 7).await;
+    let (dave, dave_bid) = create_bidder(&mut env, &bid_mint.pubkey(), 2).await;
+
+    let end_timestamp = now(&mut env).await + 100;
+    let ix = instruction::create_auction(
+        &env.program_id,
+        &alice.wallet_account.pubkey(),
+        &alice.token_account.pubkey(),
+        &bid_mint.pubkey(),
+        EscrowKind::EnglishAuction,
+        3,
+        0,
+        end_timestamp,
+    );
+    env.process(&[ix], &[&alice.wallet_account])
+        .await
+        .expect("Unable to create an auction");
+    let auction = env.assert_escrow(&alice.token_account.pubkey()).await;
+    assert_eq!(auction.kind, EscrowKind::EnglishAuction);
+
+    // Auctions are not executable as swaps
+    let (erin, erin_account) = create_bidder(&mut env, &bid_mint.pubkey(), 5).await;
+    let ix = instruction::execute(
+        &env.program_id,
+        &erin.pubkey(),
+        &erin_account.pubkey(),
+        &alice.mint_account.pubkey(),
+        &alice.token_account.pubkey(),
+        10,
+        auction.sequence,
+    );
+    let err = env.process(&[ix], &[&erin]).await.unwrap_err();
+    assert_escrow_error(err, EscrowError::WrongEscrowKind);
+
+    let program_id = env.program_id;
+    let bid = |bidder: &Keypair, bid_account: &Keypair, previous: Option<&Pubkey>| {
+        instruction::bid(
+            &program_id,
+            &bidder.pubkey(),
+            &bid_account.pubkey(),
+            &alice.token_account.pubkey(),
+            previous,
+        )
+    };
+
+    // Below the reserve price
+    let ix = bid(&dave, &dave_bid, None);
+    let err = env.process(&[ix], &[&dave]).await.unwrap_err();
+    assert_escrow_error(err, EscrowError::BidTooLow);
+
+    let ix = bid(&bob, &bob_bid, None);
+    env.process(&[ix], &[&bob])
+        .await
+        .expect("Unable to place a bid");
+    env.assert_token_owner(&bob_bid.pubkey(), &env.registry_address())
+        .await;
+
+    // Carol outbids Bob, who gets the bid account back
+    let ix = bid(&carol, &carol_bid, Some(&bob_bid.pubkey()));
+    env.process(&[ix], &[&carol])
+        .await
+        .expect("Unable to outbid");
+    env.assert_token_owner(&bob_bid.pubkey(), &bob.pubkey())
+        .await;
+    let terms: AuctionTerms = env
+        .assert_escrow(&alice.token_account.pubkey())
+        .await
+        .terms()
+        .unwrap();
+    assert_eq!(terms.highest_bid, 7);
+    assert_eq!(terms.highest_bidder, carol.pubkey());
+
+    let ix = bid(&bob, &bob_bid, Some(&carol_bid.pubkey()));
+    let err = env.process(&[ix], &[&bob]).await.unwrap_err();
+    assert_escrow_error(err, EscrowError::BidTooLow);
+
+    let err = env.reclaim(&alice).await.unwrap_err();
+    assert_escrow_error(err, EscrowError::AuctionHasBids);
+
+    let settle = instruction::settle_auction(
+        &env.program_id,
+        &alice.token_account.pubkey(),
+        Some(&carol_bid.pubkey()),
+    );
+    let err = env
+        .process(std::slice::from_ref(&settle), &[])
+        .await
+        .unwrap_err();
+    assert_escrow_error(err, EscrowError::AuctionNotEnded);
+
+    env.warp_clock(1, 101).await;
+    let ix = bid(&bob, &bob_bid, Some(&carol_bid.pubkey()));
+    let err = env.process(&[ix], &[&bob]).await.unwrap_err();
+    assert_escrow_error(err, EscrowError::AuctionEnded);
+
+    env.process(&[settle], &[])
+        .await
+        .expect("Unable to settle the auction");
+    env.assert_no_escrow(&alice.token_account.pubkey()).await;
+    env.assert_token_owner(&alice.token_account.pubkey(), &carol.pubkey())
+        .await;
+    env.assert_token_owner(&carol_bid.pubkey(), &alice.wallet_account.pubkey())
+        .await;
+}
+
+#[tokio::test]
+async fn dutch_auction_test() {
+    let mut env = EscrowTestEnv::builder().build().await;
+    let alice = env.create_user(10).await;
+    let bid_mint = env.create_mint().await;
+    let (bob, bob_bid) = create_bidder(&mut env, &bid_mint.pubkey(), 5).await;
+
+    let end_timestamp = now(&mut env).await + 100;
+    let ix = instruction::create_auction(
+        &env.program_id,
+        &alice.wallet_account.pubkey(),
+        &alice.token_account.pubkey(),
+        &bid_mint.pubkey(),
+        EscrowKind::DutchAuction,
+        2,
+        10,
+        end_timestamp,
+    );
+    env.process(&[ix], &[&alice.wallet_account])
+        .await
+        .expect("Unable to create an auction");
+
+    let bid = instruction::bid(
+        &env.program_id,
+        &bob.pubkey(),
+        &bob_bid.pubkey(),
+        &alice.token_account.pubkey(),
+        None,
+    );
+
+    // The price starts at 10 tokens
+    let err = env
+        .process(std::slice::from_ref(&bid), &[&bob])
+        .await
+        .unwrap_err();
+    assert_escrow_error(err, EscrowError::BidTooLow);
+
+    // ...and goes down to 4 tokens after 75 seconds
+    env.warp_clock(1, 75).await;
+    env.process(&[bid], &[&bob])
+        .await
+        .expect("Unable to buy at the current price");
+    env.assert_no_escrow(&alice.token_account.pubkey()).await;
+    env.assert_token_owner(&alice.token_account.pubkey(), &bob.pubkey())
+        .await;
+    env.assert_token_owner(&bob_bid.pubkey(), &alice.wallet_account.pubkey())
+        .await;
+}
@@ -0,0 +1,84 @@
+use solana_escrow::instruction::ExecuteLeg;
+use solana_escrow::test_fixtures::EscrowTestEnv;
+use solana_escrow::EscrowError;
+use solana_program_test::{tokio, BanksClientError};
+use solana_sdk::instruction::InstructionError;
+use solana_sdk::signature::{Keypair, Signer};
+use solana_sdk::transaction::TransactionError;
+
+#[tokio::test]
+async fn execute_batch_test() {
+    let mut env = EscrowTestEnv::builder().build().await;
+    let alice = env.create_user(10).await;
+    let carol = env.create_user(7).await;
+
+    let bob_wallet = Keypair::new();
+    let wanted_mint = env.create_mint().await;
+    let bob_first = env
+        .create_token_account(&bob_wallet.pubkey(), &wanted_mint.pubkey())
+        .await;
+    env.mint_to(&wanted_mint.pubkey(), &bob_first.pubkey(), 5)
+        .await;
+    let bob_second = env
+        .create_token_account(&bob_wallet.pubkey(), &wanted_mint.pubkey())
+        .await;
+    env.mint_to(&wanted_mint.pubkey(), &bob_second.pubkey(), 3)
+        .await;
+
+    env.deposit(&alice, &wanted_mint.pubkey(), 5)
+        .await
+        .expect("Unable to make a deposit");
+    env.deposit(&carol, &wanted_mint.pubkey(), 3)
+        .await
+        .expect("Unable to make a deposit");
+
+    let legs = |carol_amount| {
+        [
+            ExecuteLeg {
+                token_account: bob_first.pubkey(),
+                token_expected: alice.mint_account.pubkey(),
+                deposit_account: alice.token_account.pubkey(),
+                amount_expected: 10,
+                sequence: 1,
+            },
+            ExecuteLeg {
+                token_account: bob_second.pubkey(),
+                token_expected: carol.mint_account.pubkey(),
+                deposit_account: carol.token_account.pubkey(),
+                amount_expected: carol_amount,
+                sequence: 2,
+            },
+        ]
+    };
+
+    // A failing leg reverts the whole batch
+    let err = env
+        .execute_batch(&bob_wallet, &legs(8))
+        .await
+        .expect_err("Batch with a mismatching leg is expected to fail");
+    match err {
+        BanksClientError::TransactionError(TransactionError::InstructionError(
+            _,
+            InstructionError::Custom(code),
+        )) => assert_eq!(code, EscrowError::DepositTokenAmtMismatch as u32),
+        err => panic!("Unexpected error: {:?}", err),
+    }
+    env.assert_escrow(&alice.token_account.pubkey()).await;
+    env.assert_escrow(&carol.token_account.pubkey()).await;
+    env.assert_token_owner(&bob_first.pubkey(), &bob_wallet.pubkey())
+        .await;
+
+    env.execute_batch(&bob_wallet, &legs(7))
+        .await
+        .expect("Unable to execute a batch");
+    env.assert_no_escrow(&alice.token_account.pubkey()).await;
+    env.assert_no_escrow(&carol.token_account.pubkey()).await;
+    env.assert_token_owner(&alice.token_account.pubkey(), &bob_wallet.pubkey())
+        .await;
+    env.assert_token_owner(&carol.token_account.pubkey(), &bob_wallet.pubkey())
+        .await;
+    env.assert_token_owner(&bob_first.pubkey(), &alice.wallet_account.pubkey())
+        .await;
+    env.assert_token_owner(&bob_second.pubkey(), &carol.wallet_account.pubkey())
+        .await;
+}
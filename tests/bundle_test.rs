@@ -0,0 +1,142 @@
+use solana_escrow::test_fixtures::{assert_instruction_error, EscrowTestEnv, UserAccounts};
+use solana_escrow::{instruction, BundleAsset, EscrowError, EscrowKind};
+use solana_program::pubkey::Pubkey;
+use solana_program_test::tokio;
+use solana_sdk::instruction::InstructionError;
+use solana_sdk::signature::Signer;
+
+/// Gives `user` a second token account holding `amount` tokens of a new mint
+async fn add_asset(env: &mut EscrowTestEnv, user: &UserAccounts, amount: u64) -> (Pubkey, Pubkey) {
+    let mint = env.create_mint().await;
+    let account = env
+        .create_token_account(&user.wallet_account.pubkey(), &mint.pubkey())
+        .await;
+    env.mint_to(&mint.pubkey(), &account.pubkey(), amount).await;
+    (mint.pubkey(), account.pubkey())
+}
+
+#[tokio::test]
+async fn bundle_test() {
+    let mut env = EscrowTestEnv::builder().build().await;
+    let alice = env.create_user(1).await;
+    let bob = env.create_user(1).await;
+    let (_, alice_gold) = add_asset(&mut env, &alice, 100).await;
+    let (bob_gem_mint, bob_gems) = add_asset(&mut env, &bob, 50).await;
+    let holdings = [alice.token_account.pubkey(), alice_gold];
+    let wanted = [
+        BundleAsset {
+            mint: bob.mint_account.pubkey(),
+            amount: 1,
+        },
+        BundleAsset {
+            mint: bob_gem_mint,
+            amount: 50,
+        },
+    ];
+
+    let ix = instruction::create_bundle(
+        &env.program_id,
+        &alice.wallet_account.pubkey(),
+        &[holdings[0], holdings[0]],
+        &wanted,
+    );
+    let err = env
+        .process(&[ix], &[&alice.wallet_account])
+        .await
+        .unwrap_err();
+    assert_instruction_error(err, InstructionError::InvalidArgument);
+
+    let ix = instruction::create_bundle(
+        &env.program_id,
+        &alice.wallet_account.pubkey(),
+        &holdings,
+        &wanted,
+    );
+    env.process(&[ix], &[&alice.wallet_account])
+        .await
+        .expect("Unable to create a bundle");
+    let bundle = env.assert_escrow(&holdings[0]).await;
+    assert_eq!(bundle.kind, EscrowKind::Bundle);
+    for holding in holdings {
+        env.assert_token_owner(&holding, &env.registry_address())
+            .await;
+    }
+
+    let program_id = env.program_id;
+    let execute = |taker_accounts: &[Pubkey]| {
+        instruction::execute_bundle(
+            &program_id,
+            &bob.wallet_account.pubkey(),
+            &holdings,
+            taker_accounts,
+            bundle.sequence,
+        )
+    };
+    let bob_sword = bob.token_account.pubkey();
+    let err = env
+        .process(&[execute(&[bob_sword])], &[&bob.wallet_account])
+        .await
+        .unwrap_err();
+    assert_instruction_error(err, InstructionError::NotEnoughAccountKeys);
+    let err = env
+        .process(&[execute(&[bob_sword, bob_sword])], &[&bob.wallet_account])
+        .await
+        .unwrap_err();
+    assert_instruction_error(err, InstructionError::InvalidArgument);
+    let err = env
+        .process(&[execute(&[bob_gems, bob_sword])], &[&bob.wallet_account])
+        .await
+        .unwrap_err();
+    assert_instruction_error(
+        err,
+        InstructionError::Custom(EscrowError::ExecutorTokenMintMismatch as u32),
+    );
+
+    env.process(&[execute(&[bob_sword, bob_gems])], &[&bob.wallet_account])
+        .await
+        .expect("Unable to execute the bundle");
+    env.assert_no_escrow(&holdings[0]).await;
+    for holding in holdings {
+        env.assert_token_owner(&holding, &bob.wallet_account.pubkey())
+            .await;
+    }
+    for taker_account in [bob_sword, bob_gems] {
+        env.assert_token_owner(&taker_account, &alice.wallet_account.pubkey())
+            .await;
+    }
+}
+
+#[tokio::test]
+async fn reclaim_bundle_test() {
+    let mut env = EscrowTestEnv::builder().build().await;
+    let alice = env.create_user(1).await;
+    let (_, alice_gold) = add_asset(&mut env, &alice, 100).await;
+    let holdings = [alice.token_account.pubkey(), alice_gold];
+    let ix = instruction::create_bundle(
+        &env.program_id,
+        &alice.wallet_account.pubkey(),
+        &holdings,
+        &[BundleAsset {
+            mint: alice.mint_account.pubkey(),
+            amount: 2,
+        }],
+    );
+    env.process(&[ix], &[&alice.wallet_account])
+        .await
+        .expect("Unable to create a bundle");
+
+    // All accounts of the bundle return at once
+    let err = env.reclaim(&alice).await.unwrap_err();
+    assert_instruction_error(err, InstructionError::NotEnoughAccountKeys);
+
+    let ix =
+        instruction::reclaim_bundle(&env.program_id, &alice.wallet_account.pubkey(), &holdings);
+    env.process(&[ix], &[&alice.wallet_account])
+        .await
+        .expect("Unable to reclaim the bundle");
+    env.assert_no_escrow(&holdings[0]).await;
+    for holding in holdings {
+        env.assert_token_owner(&holding, &alice.wallet_account.pubkey())
+            .await;
+    }
+}
@@ -0,0 +1,174 @@
+use solana_escrow::client::{ClientError, EscrowClient};
+use solana_escrow::error::EscrowError;
+use solana_escrow::test_fixtures::*;
+use solana_escrow::{process_instruction, BundleAsset};
+use solana_program::pubkey::Pubkey;
+use solana_program::system_instruction;
+use solana_program_test::{processor, tokio, ProgramTest};
+use solana_sdk::signature::{Keypair, Signer};
+use solana_sdk::transaction::Transaction;
+
+#[tokio::test]
+async fn client_flow_test() {
+    let mint_authority_kp = Keypair::new();
+    let program_id = Pubkey::new_unique();
+
+    let test_program =
+        ProgramTest::new("solana_escrow", program_id, processor!(process_instruction));
+    let (mut banks_client, payer, recent_blockhash) = test_program.start().await;
+
+    let alice = UserAccounts::prepare(
+        &mut banks_client,
+        &recent_blockhash,
+        &payer,
+        &mint_authority_kp,
+        10,
+    )
+    .await;
+    let bob = UserAccounts::prepare(
+        &mut banks_client,
+        &recent_blockhash,
+        &payer,
+        &mint_authority_kp,
+        5,
+    )
+    .await;
+
+    for wallet in [&alice.wallet_account, &bob.wallet_account] {
+        let fund_tx = Transaction::new_signed_with_payer(
+            &[solana_sdk::system_instruction::transfer(
+                &payer.pubkey(),
+                &wallet.pubkey(),
+                1_000_000_000,
+            )],
+            Some(&payer.pubkey()),
+            &[&payer],
+            recent_blockhash,
+        );
+        banks_client.process_transaction(fund_tx).await.unwrap();
+    }
+
+    let mut client = EscrowClient::new(banks_client.clone(), program_id);
+    assert!(matches!(
+        client.list_open_escrows().await,
+        Err(ClientError::RegistryNotInitialized(_))
+    ));
+
+    client
+        .init(&payer)
+        .await
+        .expect("Unable to init an escrow program");
+    assert!(client.list_open_escrows().await.unwrap().is_empty());
+
+    let entry = client
+        .deposit(
+            &alice.wallet_account,
+            &alice.token_account.pubkey(),
+            &bob.mint_account.pubkey(),
+            5,
+        )
+        .await
+        .expect("Unable to make a deposit");
+    assert_eq!(entry.escrow.owner_account, alice.wallet_account.pubkey());
+    assert_eq!(entry.escrow.amount_expected, 5);
+
+    let found = client
+        .find_escrow_by_holding_account(&alice.token_account.pubkey())
+        .await
+        .unwrap()
+        .expect("Escrow is expected to be found");
+    assert_eq!(found.slot, entry.slot);
+
+    // Program errors are decoded into typed escrow errors
+    match client
+        .execute(
+            &bob.wallet_account,
+            &bob.token_account.pubkey(),
+            &bob.mint_account.pubkey(),
+            &alice.token_account.pubkey(),
+            10,
+            entry.escrow.sequence,
+        )
+        .await
+    {
+        Err(ClientError::Escrow(EscrowError::DepositTokenMintMismatch)) => (),
+        Err(e) => panic!("Wrong error type: {}", e),
+        Ok(_) => panic!("Error is expected!"),
+    }
+
+    client
+        .reclaim(&alice.wallet_account, &alice.token_account.pubkey())
+        .await
+        .expect("Unable to make reclaim");
+    assert_eq!(
+        client
+            .token_account_owner(&alice.token_account.pubkey())
+            .await
+            .unwrap(),
+        alice.wallet_account.pubkey()
+    );
+    assert!(client
+        .find_escrow_by_holding_account(&alice.token_account.pubkey())
+        .await
+        .unwrap()
+        .is_none());
+}
+
+#[tokio::test]
+async fn client_bundle_sequence_test() {
+    let mut env = EscrowTestEnv::builder().build().await;
+    let alice = env.create_user(1).await;
+    let bob = env.create_user(1).await;
+    let payer = env.payer.pubkey();
+    let fund = [&alice, &bob].map(|user| {
+        system_instruction::transfer(&payer, &user.wallet_account.pubkey(), 1_000_000_000)
+    });
+    env.process(&fund, &[]).await.unwrap();
+
+    let holding = alice.token_account.pubkey();
+    let wanted = [BundleAsset {
+        mint: bob.mint_account.pubkey(),
+        amount: 1,
+    }];
+    let mut client = EscrowClient::new(env.banks_client.clone(), env.program_id);
+    let stale = client
+        .create_bundle(&alice.wallet_account, &[holding], &wanted)
+        .await
+        .expect("Unable to create a bundle");
+    client
+        .reclaim_bundle(&alice.wallet_account, &holding)
+        .await
+        .expect("Unable to reclaim the bundle");
+    env.warp_clock(1, 0).await;
+    let current = client
+        .create_bundle(&alice.wallet_account, &[holding], &wanted)
+        .await
+        .expect("Unable to create a bundle");
+
+    // The taker saw the bundle before it was replaced
+    match client
+        .execute_bundle(
+            &bob.wallet_account,
+            &holding,
+            &[bob.token_account.pubkey()],
+            stale.escrow.sequence,
+        )
+        .await
+    {
+        Err(ClientError::Escrow(EscrowError::SequenceMismatch)) => (),
+        Err(e) => panic!("Wrong error type: {}", e),
+        Ok(_) => panic!("Error is expected!"),
+    }
+
+    client
+        .execute_bundle(
+            &bob.wallet_account,
+            &holding,
+            &[bob.token_account.pubkey()],
+            current.escrow.sequence,
+        )
+        .await
+        .expect("Unable to execute the bundle");
+    env.assert_token_owner(&holding, &bob.wallet_account.pubkey())
+        .await;
+}
@@ -0,0 +1,238 @@
+use solana_escrow::test_fixtures::*;
+use solana_escrow::{cpi, find_registry_address, instruction, process_instruction};
+use solana_program::account_info::{next_account_info, AccountInfo};
+use solana_program::entrypoint::ProgramResult;
+use solana_program::instruction::{AccountMeta, Instruction};
+use solana_program::pubkey::Pubkey;
+use solana_program_test::{processor, tokio, BanksClient, ProgramTest};
+use solana_sdk::signature::{Keypair, Signer};
+use solana_sdk::transaction::Transaction;
+
+const VAULT_SEED: &[u8] = b"vault";
+
+/// Mock program keeping token accounts under its PDA and trading them through the escrow
+fn vault_process_instruction(
+    program_id: &Pubkey,
+    accounts: &[AccountInfo],
+    instruction_data: &[u8],
+) -> ProgramResult {
+    let account_info_iter = &mut accounts.iter();
+    let escrow_program = next_account_info(account_info_iter)?;
+    let registry = next_account_info(account_info_iter)?;
+    let vault = next_account_info(account_info_iter)?;
+    let token_account = next_account_info(account_info_iter)?;
+    let token_program = next_account_info(account_info_iter)?;
+
+    let (_, bump_seed) = Pubkey::find_program_address(&[VAULT_SEED], program_id);
+    let signer_seeds: &[&[&[u8]]] = &[&[VAULT_SEED, &[bump_seed]]];
+    let amount = || u32::from_le_bytes(instruction_data[1..5].try_into().unwrap());
+    let sequence = || u64::from_le_bytes(instruction_data[5..13].try_into().unwrap());
+
+    match instruction_data[0] {
+        0 => cpi::deposit(
+            cpi::Deposit {
+                escrow_program,
+                registry,
+                owner: vault,
+                token_account,
+                token_program,
+                token_expected: next_account_info(account_info_iter)?,
+            },
+            amount(),
+            signer_seeds,
+        ),
+        1 => cpi::execute(
+            cpi::Execute {
+                escrow_program,
+                registry,
+                owner: vault,
+                token_account,
+                token_program,
+                token_expected: next_account_info(account_info_iter)?,
+                deposit_account: next_account_info(account_info_iter)?,
+            },
+            amount(),
+            sequence(),
+            signer_seeds,
+        ),
+        _ => cpi::reclaim(
+            cpi::Reclaim {
+                escrow_program,
+                registry,
+                owner: vault,
+                token_account,
+                token_program,
+            },
+            signer_seeds,
+        ),
+    }
+}
+
+async fn send(client: &mut BanksClient, payer: &Keypair, ix: Instruction, signers: &[&Keypair]) {
+    let blockhash = client.get_latest_blockhash().await.unwrap();
+    let tx = Transaction::new_signed_with_payer(
+        &[ix],
+        Some(&payer.pubkey()),
+        &[&[payer], signers].concat(),
+        blockhash,
+    );
+    client
+        .process_transaction(tx)
+        .await
+        .expect("Unable to process transaction");
+}
+
+#[tokio::test]
+async fn cpi_deposit_execute_reclaim_test() {
+    let mint_authority_kp = Keypair::new();
+    let escrow_program_id = Pubkey::new_unique();
+    let vault_program_id = Pubkey::new_unique();
+
+    let mut test_program = ProgramTest::new(
+        "solana_escrow",
+        escrow_program_id,
+        processor!(process_instruction),
+    );
+    test_program.add_program(
+        "vault",
+        vault_program_id,
+        processor!(vault_process_instruction),
+    );
+    let (mut banks_client, payer, recent_blockhash) = test_program.start().await;
+
+    let (vault_pda, _) = Pubkey::find_program_address(&[VAULT_SEED], &vault_program_id);
+    let (registry_pk, _) = find_registry_address(&escrow_program_id);
+
+    let vault_position = UserAccounts::prepare(
+        &mut banks_client,
+        &recent_blockhash,
+        &payer,
+        &mint_authority_kp,
+        10,
+    )
+    .await;
+    let bob = UserAccounts::prepare(
+        &mut banks_client,
+        &recent_blockhash,
+        &payer,
+        &mint_authority_kp,
+        5,
+    )
+    .await;
+
+    // Hand the position over to the vault program
+    send(
+        &mut banks_client,
+        &payer,
+        spl_token::instruction::set_authority(
+            &spl_token::id(),
+            &vault_position.token_account.pubkey(),
+            Some(&vault_pda),
+            spl_token::instruction::AuthorityType::AccountOwner,
+            &vault_position.wallet_account.pubkey(),
+            &[],
+        )
+        .unwrap(),
+        &[&vault_position.wallet_account],
+    )
+    .await;
+
+    send(
+        &mut banks_client,
+        &payer,
+        instruction::init(&escrow_program_id, &payer.pubkey()),
+        &[],
+    )
+    .await;
+
+    let vault_ix = |data: Vec<u8>, extra_accounts: Vec<AccountMeta>| Instruction {
+        program_id: vault_program_id,
+        accounts: [
+            vec![
+                AccountMeta::new_readonly(escrow_program_id, false),
+                AccountMeta::new(registry_pk, false),
+                AccountMeta::new_readonly(vault_pda, false),
+                AccountMeta::new(vault_position.token_account.pubkey(), false),
+                AccountMeta::new_readonly(spl_token::id(), false),
+            ],
+            extra_accounts,
+        ]
+        .concat(),
+        data,
+    };
+
+    // The vault lists its position through the escrow
+    send(
+        &mut banks_client,
+        &payer,
+        vault_ix(
+            [[0].as_slice(), 5u32.to_le_bytes().as_slice()].concat(),
+            vec![AccountMeta::new_readonly(bob.mint_account.pubkey(), false)],
+        ),
+        &[],
+    )
+    .await;
+
+    check_account_property(
+        &mut banks_client,
+        &vault_position.token_account.pubkey(),
+        |account| assert_eq!(account.owner, registry_pk),
+    )
+    .await;
+
+    // ... and takes it back
+    send(&mut banks_client, &payer, vault_ix(vec![2], vec![]), &[]).await;
+
+    check_account_property(
+        &mut banks_client,
+        &vault_position.token_account.pubkey(),
+        |account| assert_eq!(account.owner, vault_pda),
+    )
+    .await;
+
+    // Bob offers his tokens and the vault fills the offer
+    send(
+        &mut banks_client,
+        &payer,
+        instruction::deposit(
+            &escrow_program_id,
+            &bob.wallet_account.pubkey(),
+            &bob.token_account.pubkey(),
+            &vault_position.mint_account.pubkey(),
+            10,
+        ),
+        &[&bob.wallet_account],
+    )
+    .await;
+
+    send(
+        &mut banks_client,
+        &payer,
+        vault_ix(
+            // Bob's offer is the second deposit to the registry
+            [
+                [1].as_slice(),
+                5u32.to_le_bytes().as_slice(),
+                2u64.to_le_bytes().as_slice(),
+            ]
+            .concat(),
+            vec![
+                AccountMeta::new_readonly(bob.mint_account.pubkey(), false),
+                AccountMeta::new(bob.token_account.pubkey(), false),
+            ],
+        ),
+        &[],
+    )
+    .await;
+
+    check_account_property(&mut banks_client, &bob.token_account.pubkey(), |account| {
+        assert_eq!(account.owner, vault_pda)
+    })
+    .await;
+    check_account_property(
+        &mut banks_client,
+        &vault_position.token_account.pubkey(),
+        |account| assert_eq!(account.owner, bob.wallet_account.pubkey()),
+    )
+    .await;
+}
@@ -0,0 +1,223 @@
+use solana_escrow::instruction;
+use solana_escrow::test_fixtures::{assert_escrow_error, assert_instruction_error, EscrowTestEnv};
+use solana_escrow::{DelegatedTerms, EscrowError, EscrowKind};
+use solana_program::pubkey::Pubkey;
+use solana_program_test::tokio;
+use solana_sdk::instruction::InstructionError;
+use solana_sdk::signature::Signer;
+
+#[tokio::test]
+async fn delegated_deposit_test() {
+    let mut env = EscrowTestEnv::builder().build().await;
+    let alice = env.create_user(100).await;
+    let bob = env.create_user(50).await;
+    let alice_wallet = alice.wallet_account.pubkey();
+    let holding = alice.token_account.pubkey();
+    let proceeds = env
+        .create_token_account(&alice_wallet, &bob.mint_account.pubkey())
+        .await;
+    let alice_spare = env
+        .create_token_account(&alice_wallet, &alice.mint_account.pubkey())
+        .await;
+    let bob_destination = env
+        .create_token_account(&bob.wallet_account.pubkey(), &alice.mint_account.pubkey())
+        .await;
+    let program_id = env.program_id;
+
+    let deposit = instruction::deposit_delegated(
+        &program_id,
+        &alice_wallet,
+        &holding,
+        &bob.mint_account.pubkey(),
+        &proceeds.pubkey(),
+        20,
+    );
+    let delegate = instruction::delegate(&program_id, &alice_wallet, &holding, 30).unwrap();
+
+    // Nothing is delegated yet
+    let err = env
+        .process(std::slice::from_ref(&deposit), &[&alice.wallet_account])
+        .await
+        .unwrap_err();
+    assert_escrow_error(err, EscrowError::InsufficientDelegation);
+
+    env.process(
+        &[delegate.clone(), deposit.clone()],
+        &[&alice.wallet_account],
+    )
+    .await
+    .expect("Unable to deposit delegated tokens");
+    let escrow = env.assert_escrow(&holding).await;
+    assert_eq!(escrow.kind, EscrowKind::Delegated);
+    let terms: DelegatedTerms = escrow.terms().unwrap();
+    assert_eq!(terms.amount, 30);
+    env.assert_token_owner(&holding, &alice_wallet).await;
+
+    // The same delegation can't back a second offer, neither a delegated nor a custodial one
+    env.warp_clock(1, 1).await;
+    let err = env
+        .process(&[deposit], &[&alice.wallet_account])
+        .await
+        .unwrap_err();
+    assert_instruction_error(err, InstructionError::AccountAlreadyInitialized);
+    let err = env
+        .deposit(&alice, &bob.mint_account.pubkey(), 20)
+        .await
+        .unwrap_err();
+    assert_instruction_error(err, InstructionError::AccountAlreadyInitialized);
+    env.assert_token_owner(&holding, &alice_wallet).await;
+
+    let execute = |amount_expected| {
+        instruction::execute_delegated(
+            &program_id,
+            &bob.wallet_account.pubkey(),
+            &bob.token_account.pubkey(),
+            &bob_destination.pubkey(),
+            &holding,
+            &proceeds.pubkey(),
+            amount_expected,
+            escrow.sequence,
+        )
+    };
+    let err = env
+        .process(&[execute(40)], &[&bob.wallet_account])
+        .await
+        .unwrap_err();
+    assert_escrow_error(err, EscrowError::DepositTokenAmtMismatch);
+
+    // Revoked delegation
+    let revoke =
+        spl_token::instruction::revoke(&spl_token::id(), &holding, &alice_wallet, &[]).unwrap();
+    env.process(&[revoke], &[&alice.wallet_account])
+        .await
+        .expect("Unable to revoke");
+    let err = env
+        .process(&[execute(30)], &[&bob.wallet_account])
+        .await
+        .unwrap_err();
+    assert_escrow_error(err, EscrowError::InsufficientDelegation);
+
+    // Spent tokens
+    let spend = |from: Pubkey, to: Pubkey, amount| {
+        spl_token::instruction::transfer(&spl_token::id(), &from, &to, &alice_wallet, &[], amount)
+            .unwrap()
+    };
+    env.process(
+        &[delegate, spend(holding, alice_spare.pubkey(), 80)],
+        &[&alice.wallet_account],
+    )
+    .await
+    .expect("Unable to spend");
+    env.warp_clock(1, 1).await;
+    let err = env
+        .process(&[execute(30)], &[&bob.wallet_account])
+        .await
+        .unwrap_err();
+    assert_escrow_error(err, EscrowError::DelegatedTokensSpent);
+
+    env.process(
+        &[spend(alice_spare.pubkey(), holding, 80)],
+        &[&alice.wallet_account],
+    )
+    .await
+    .expect("Unable to return the tokens");
+    env.warp_clock(1, 1).await;
+    env.process(&[execute(30)], &[&bob.wallet_account])
+        .await
+        .expect("Unable to execute the delegated deposit");
+    env.assert_no_escrow(&holding).await;
+    env.assert_token_owner(&holding, &alice_wallet).await;
+    assert_eq!(env.token_account(&holding).await.amount, 70);
+    assert_eq!(env.token_account(&proceeds.pubkey()).await.amount, 20);
+    assert_eq!(
+        env.token_account(&bob.token_account.pubkey()).await.amount,
+        30
+    );
+    assert_eq!(
+        env.token_account(&bob_destination.pubkey()).await.amount,
+        30
+    );
+}
+
+#[tokio::test]
+async fn reclaim_delegated_test() {
+    let mut env = EscrowTestEnv::builder().build().await;
+    let alice = env.create_user(100).await;
+    let alice_wallet = alice.wallet_account.pubkey();
+    let holding = alice.token_account.pubkey();
+    let proceeds = env
+        .create_token_account(&alice_wallet, &alice.mint_account.pubkey())
+        .await;
+
+    let instructions = [
+        instruction::delegate(&env.program_id, &alice_wallet, &holding, 10).unwrap(),
+        instruction::deposit_delegated(
+            &env.program_id,
+            &alice_wallet,
+            &holding,
+            &alice.mint_account.pubkey(),
+            &proceeds.pubkey(),
+            5,
+        ),
+    ];
+    env.process(&instructions, &[&alice.wallet_account])
+        .await
+        .expect("Unable to deposit delegated tokens");
+
+    env.reclaim(&alice)
+        .await
+        .expect("Unable to reclaim a delegated deposit");
+    env.assert_no_escrow(&holding).await;
+    env.assert_token_owner(&holding, &alice_wallet).await;
+}
+
+#[tokio::test]
+async fn large_delegation_test() {
+    let mut env = EscrowTestEnv::builder().build().await;
+    let amount = u32::MAX as u64 + 10;
+    let alice = env.create_user(amount).await;
+    let bob = env.create_user(5).await;
+    let alice_wallet = alice.wallet_account.pubkey();
+    let holding = alice.token_account.pubkey();
+    let proceeds = env
+        .create_token_account(&alice_wallet, &bob.mint_account.pubkey())
+        .await;
+    let bob_destination = env
+        .create_token_account(&bob.wallet_account.pubkey(), &alice.mint_account.pubkey())
+        .await;
+
+    let instructions = [
+        instruction::delegate(&env.program_id, &alice_wallet, &holding, amount).unwrap(),
+        instruction::deposit_delegated(
+            &env.program_id,
+            &alice_wallet,
+            &holding,
+            &bob.mint_account.pubkey(),
+            &proceeds.pubkey(),
+            5,
+        ),
+    ];
+    env.process(&instructions, &[&alice.wallet_account])
+        .await
+        .expect("Unable to deposit delegated tokens");
+    let escrow = env.assert_escrow(&holding).await;
+
+    let execute = instruction::execute_delegated(
+        &env.program_id,
+        &bob.wallet_account.pubkey(),
+        &bob.token_account.pubkey(),
+        &bob_destination.pubkey(),
+        &holding,
+        &proceeds.pubkey(),
+        amount,
+        escrow.sequence,
+    );
+    env.process(&[execute], &[&bob.wallet_account])
+        .await
+        .expect("Unable to execute the delegated deposit");
+    assert_eq!(
+        env.token_account(&bob_destination.pubkey()).await.amount,
+        amount
+    );
+    assert_eq!(env.token_account(&proceeds.pubkey()).await.amount, 5);
+}
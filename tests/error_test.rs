@@ -0,0 +1,28 @@
+use solana_escrow::error::EscrowError;
+use solana_program::instruction::InstructionError;
+use solana_program::program_error::ProgramError;
+
+#[test]
+fn custom_codes_round_trip_test() {
+    for code in 0..=25u32 {
+        let error = EscrowError::try_from(code).expect("Known error code");
+        assert_eq!(ProgramError::from(error), ProgramError::Custom(code));
+    }
+    assert_eq!(EscrowError::try_from(26), Err(ProgramError::Custom(26)));
+}
+
+#[test]
+fn instruction_error_decoding_test() {
+    assert_eq!(
+        EscrowError::from_instruction_error(&InstructionError::Custom(3)),
+        Some(EscrowError::DepositTokenAmtMismatch)
+    );
+    assert_eq!(
+        EscrowError::from_instruction_error(&InstructionError::InvalidArgument),
+        None
+    );
+    assert_eq!(
+        EscrowError::DepositTokenAmtMismatch.to_string(),
+        "Executor expects another amount of token deposited"
+    );
+}
@@ -0,0 +1,69 @@
+use solana_escrow::test_fixtures::EscrowTestEnv;
+use solana_program_test::tokio;
+use solana_sdk::signature::Signer;
+
+#[tokio::test]
+async fn fixtures_flow_test() {
+    let mut env = EscrowTestEnv::builder().build().await;
+    let alice = env.create_user(10).await;
+    let bob = env.create_user(5).await;
+
+    env.deposit(&alice, &bob.mint_account.pubkey(), 5)
+        .await
+        .expect("Unable to make a deposit");
+    let escrow = env.assert_escrow(&alice.token_account.pubkey()).await;
+    assert_eq!(escrow.owner_account, alice.wallet_account.pubkey());
+    assert_eq!(escrow.amount_expected, 5);
+    env.assert_token_owner(&alice.token_account.pubkey(), &env.registry_address())
+        .await;
+
+    env.execute(
+        &bob,
+        &alice.token_account.pubkey(),
+        &alice.mint_account.pubkey(),
+        10,
+    )
+    .await
+    .expect("Unable to make an escrow execution");
+    env.assert_no_escrow(&alice.token_account.pubkey()).await;
+    env.assert_token_owner(&alice.token_account.pubkey(), &bob.wallet_account.pubkey())
+        .await;
+    env.assert_token_owner(&bob.token_account.pubkey(), &alice.wallet_account.pubkey())
+        .await;
+
+    // Tokens minted on top of an existing mint
+    let carol_wallet = solana_sdk::signature::Keypair::new();
+    let carol_account = env
+        .create_token_account(&carol_wallet.pubkey(), &alice.mint_account.pubkey())
+        .await;
+    env.mint_to(
+        &alice.mint_account.pubkey(),
+        &carol_account.pubkey(),
+        u64::from(u32::MAX) + 1,
+    )
+    .await;
+    assert_eq!(
+        env.token_account(&carol_account.pubkey()).await.amount,
+        u64::from(u32::MAX) + 1
+    );
+}
+
+#[tokio::test]
+async fn fixtures_reclaim_test() {
+    let mut env = EscrowTestEnv::builder().without_registry().build().await;
+    env.init_registry()
+        .await
+        .expect("Unable to init an escrow program");
+    let alice = env.create_user(10).await;
+
+    env.deposit(&alice, &alice.mint_account.pubkey(), 10)
+        .await
+        .expect("Unable to make a deposit");
+    env.reclaim(&alice).await.expect("Unable to make reclaim");
+    env.assert_no_escrow(&alice.token_account.pubkey()).await;
+    env.assert_token_owner(
+        &alice.token_account.pubkey(),
+        &alice.wallet_account.pubkey(),
+    )
+    .await;
+}
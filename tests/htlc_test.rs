@@ -0,0 +1,98 @@
+use solana_escrow::instruction;
+use solana_escrow::test_fixtures::{assert_escrow_error, EscrowTestEnv, UserAccounts};
+use solana_escrow::EscrowError;
+use solana_program::clock::Clock;
+use solana_program::hash::hash;
+use solana_program_test::tokio;
+use solana_sdk::signature::{Keypair, Signer};
+
+const PREIMAGE: &[u8] = b"cross-chain secret";
+
+async fn create_htlc(env: &mut EscrowTestEnv, owner: &UserAccounts, recipient: &Keypair) -> i64 {
+    let clock: Clock = env.banks_client.get_sysvar().await.unwrap();
+    let timeout = clock.unix_timestamp + 600;
+    let ix = instruction::create_htlc(
+        &env.program_id,
+        &owner.wallet_account.pubkey(),
+        &owner.token_account.pubkey(),
+        &owner.mint_account.pubkey(),
+        &recipient.pubkey(),
+        hash(PREIMAGE).to_bytes(),
+        timeout,
+    );
+    env.process(&[ix], &[&owner.wallet_account])
+        .await
+        .expect("Unable to create an HTLC");
+    timeout
+}
+
+#[tokio::test]
+async fn htlc_claim_test() {
+    let mut env = EscrowTestEnv::builder().build().await;
+    let alice = env.create_user(10).await;
+    let bob = Keypair::new();
+    let holding = alice.token_account.pubkey();
+    create_htlc(&mut env, &alice, &bob).await;
+
+    let err = env.reclaim(&alice).await.unwrap_err();
+    assert_escrow_error(err, EscrowError::WrongEscrowKind);
+    let ix = instruction::refund_htlc(&env.program_id, &alice.wallet_account.pubkey(), &holding);
+    let err = env
+        .process(&[ix], &[&alice.wallet_account])
+        .await
+        .unwrap_err();
+    assert_escrow_error(err, EscrowError::DeadlineNotReached);
+
+    let ix = instruction::claim(&env.program_id, &holding, b"wrong guess");
+    let err = env.process(&[ix], &[]).await.unwrap_err();
+    assert_escrow_error(err, EscrowError::HashlockMismatch);
+
+    // Anyone may claim for the recipient, the secret is revealed in the logs
+    let ix = instruction::claim(&env.program_id, &holding, PREIMAGE);
+    let tx = solana_sdk::transaction::Transaction::new_signed_with_payer(
+        &[ix],
+        Some(&env.payer.pubkey()),
+        &[&env.payer],
+        env.banks_client.get_latest_blockhash().await.unwrap(),
+    );
+    let result = env
+        .banks_client
+        .process_transaction_with_metadata(tx)
+        .await
+        .unwrap();
+    assert!(result.result.is_ok());
+    let preimage_hex: String = PREIMAGE
+        .iter()
+        .map(|byte| format!("{:02x}", byte))
+        .collect();
+    assert!(result
+        .metadata
+        .unwrap()
+        .log_messages
+        .iter()
+        .any(|log| log.ends_with(&format!("HTLC claimed, preimage: {}", preimage_hex))));
+    env.assert_no_escrow(&holding).await;
+    env.assert_token_owner(&holding, &bob.pubkey()).await;
+}
+
+#[tokio::test]
+async fn htlc_refund_test() {
+    let mut env = EscrowTestEnv::builder().build().await;
+    let alice = env.create_user(10).await;
+    let bob = Keypair::new();
+    let holding = alice.token_account.pubkey();
+    create_htlc(&mut env, &alice, &bob).await;
+
+    env.warp_clock(1, 600).await;
+    let ix = instruction::claim(&env.program_id, &holding, PREIMAGE);
+    let err = env.process(&[ix], &[]).await.unwrap_err();
+    assert_escrow_error(err, EscrowError::HtlcExpired);
+
+    let ix = instruction::refund_htlc(&env.program_id, &alice.wallet_account.pubkey(), &holding);
+    env.process(&[ix], &[&alice.wallet_account])
+        .await
+        .expect("Unable to refund the HTLC");
+    env.assert_no_escrow(&holding).await;
+    env.assert_token_owner(&holding, &alice.wallet_account.pubkey())
+        .await;
+}
@@ -0,0 +1,102 @@
+use solana_escrow::instruction;
+use solana_escrow::test_fixtures::{assert_instruction_error, EscrowTestEnv};
+use solana_escrow::{EscrowError, ReclaimDelay};
+use solana_program::system_instruction;
+use solana_program_test::tokio;
+use solana_sdk::instruction::InstructionError;
+use solana_sdk::native_token::LAMPORTS_PER_SOL;
+use solana_sdk::signature::{Keypair, Signer};
+
+#[tokio::test]
+async fn match_test() {
+    let mut env = EscrowTestEnv::builder().build().await;
+    let alice = env.create_user(10).await;
+    let bob = env.create_user(5).await;
+
+    let carol_wallet = Keypair::new();
+    let carol_account = env
+        .create_token_account(&carol_wallet.pubkey(), &bob.mint_account.pubkey())
+        .await;
+    env.mint_to(&bob.mint_account.pubkey(), &carol_account.pubkey(), 5)
+        .await;
+
+    env.deposit(&alice, &bob.mint_account.pubkey(), 5)
+        .await
+        .expect("Unable to make a deposit");
+    env.deposit(&bob, &alice.mint_account.pubkey(), 10)
+        .await
+        .expect("Unable to make a deposit");
+    let carol_deposit = instruction::deposit(
+        &env.program_id,
+        &carol_wallet.pubkey(),
+        &carol_account.pubkey(),
+        &alice.mint_account.pubkey(),
+        9,
+    );
+    env.process(&[carol_deposit], &[&carol_wallet])
+        .await
+        .expect("Unable to make a deposit");
+
+    // Only the admin sets the reward
+    let stranger = Keypair::new();
+    let ix = instruction::configure(
+        &env.program_id,
+        &stranger.pubkey(),
+        1_000,
+        ReclaimDelay::None,
+    );
+    let err = env.process(&[ix], &[&stranger]).await.unwrap_err();
+    assert_instruction_error(err, InstructionError::IllegalOwner);
+
+    env.configure(1_000, ReclaimDelay::None)
+        .await
+        .expect("Unable to configure the registry");
+    assert_eq!(env.registry().await.config.admin, env.payer.pubkey());
+    assert_eq!(env.registry().await.config.match_reward_lamports, 1_000);
+
+    let matcher = Keypair::new();
+    let fund = [
+        system_instruction::transfer(&env.payer.pubkey(), &matcher.pubkey(), LAMPORTS_PER_SOL),
+        system_instruction::transfer(&env.payer.pubkey(), &env.registry_address(), 5_000),
+    ];
+    env.process(&fund, &[])
+        .await
+        .expect("Unable to fund accounts");
+
+    // Carol wants 9 tokens while Alice provides 10
+    let err = env
+        .match_escrows(
+            &matcher,
+            &alice.token_account.pubkey(),
+            &carol_account.pubkey(),
+        )
+        .await
+        .unwrap_err();
+    assert_instruction_error(
+        err,
+        InstructionError::Custom(EscrowError::DepositTokenAmtMismatch as u32),
+    );
+
+    env.match_escrows(
+        &matcher,
+        &alice.token_account.pubkey(),
+        &bob.token_account.pubkey(),
+    )
+    .await
+    .expect("Unable to match escrows");
+
+    env.assert_no_escrow(&alice.token_account.pubkey()).await;
+    env.assert_no_escrow(&bob.token_account.pubkey()).await;
+    env.assert_escrow(&carol_account.pubkey()).await;
+    env.assert_token_owner(&alice.token_account.pubkey(), &bob.wallet_account.pubkey())
+        .await;
+    env.assert_token_owner(&bob.token_account.pubkey(), &alice.wallet_account.pubkey())
+        .await;
+    assert_eq!(
+        env.banks_client
+            .get_balance(matcher.pubkey())
+            .await
+            .unwrap(),
+        LAMPORTS_PER_SOL + 1_000
+    );
+}
@@ -0,0 +1,117 @@
+use solana_escrow::state::LEGACY_REGISTRY_LEN;
+use solana_escrow::test_fixtures::{assert_escrow_error, assert_instruction_error, EscrowTestEnv};
+use solana_escrow::{find_registry_address, instruction, EscrowError, EscrowKind};
+use solana_program::program_option::COption;
+use solana_program::program_pack::Pack;
+use solana_program::pubkey::Pubkey;
+use solana_program::rent::Rent;
+use solana_program_test::tokio;
+use solana_sdk::account::Account;
+use solana_sdk::instruction::InstructionError;
+use solana_sdk::signature::{Keypair, Signer};
+use spl_token::state::AccountState;
+
+#[tokio::test]
+async fn migrate_registry_test() {
+    let program_id = Pubkey::new_unique();
+    let (registry, _) = find_registry_address(&program_id);
+    let owner = Keypair::new();
+    let holding_account = Pubkey::new_unique();
+    let token_expected = Pubkey::new_unique();
+
+    // Registry of the first layout with one escrow in its second slot
+    let mut registry_data = vec![0u8; LEGACY_REGISTRY_LEN];
+    let record = [
+        [1].as_slice(),
+        &5u32.to_le_bytes(),
+        token_expected.as_ref(),
+        holding_account.as_ref(),
+        owner.pubkey().as_ref(),
+    ]
+    .concat();
+    registry_data[101..202].copy_from_slice(&record);
+
+    let mut token_data = vec![0u8; spl_token::state::Account::LEN];
+    spl_token::state::Account {
+        mint: Pubkey::new_unique(),
+        owner: registry,
+        amount: 10,
+        delegate: COption::None,
+        state: AccountState::Initialized,
+        is_native: COption::None,
+        delegated_amount: 0,
+        close_authority: COption::None,
+    }
+    .pack_into_slice(&mut token_data);
+
+    let mut env = EscrowTestEnv::builder()
+        .program_id(program_id)
+        .without_registry()
+        .configure(|program_test| {
+            program_test.add_account(
+                registry,
+                Account {
+                    lamports: Rent::default().minimum_balance(LEGACY_REGISTRY_LEN),
+                    data: registry_data,
+                    owner: program_id,
+                    executable: false,
+                    rent_epoch: 0,
+                },
+            );
+            program_test.add_account(
+                holding_account,
+                Account {
+                    lamports: Rent::default().minimum_balance(token_data.len()),
+                    data: token_data,
+                    owner: spl_token::id(),
+                    executable: false,
+                    rent_epoch: 0,
+                },
+            );
+        })
+        .build()
+        .await;
+    let alice = env.create_user(10).await;
+
+    let err = env
+        .deposit(&alice, &token_expected, 5)
+        .await
+        .expect_err("Deposit into an old registry is expected to fail");
+    assert_escrow_error(err, EscrowError::OutdatedRegistryLayout);
+
+    let ix = instruction::migrate_registry(&program_id, &env.payer.pubkey());
+    env.process(&[ix], &[])
+        .await
+        .expect("Unable to migrate the registry");
+
+    let escrow = env.assert_escrow(&holding_account).await;
+    assert_eq!(escrow.owner_account, owner.pubkey());
+    assert_eq!(escrow.token_expected, token_expected);
+    assert_eq!(escrow.amount_expected, 5);
+    assert_eq!(escrow.kind, EscrowKind::Swap);
+    assert_eq!(escrow.sequence, 1);
+    let registry_state = env.registry().await;
+    assert_eq!(registry_state.config.admin, Pubkey::default());
+    assert_eq!(registry_state.config.next_sequence, 2);
+
+    // Migrated registries are left alone
+    env.warp_clock(1, 0).await;
+    let ix = instruction::migrate_registry(&program_id, &env.payer.pubkey());
+    let err = env.process(&[ix], &[]).await.unwrap_err();
+    assert_instruction_error(err, InstructionError::InvalidAccountData);
+
+    // Escrows of the old registry are reclaimed as usual, new ones are numbered on
+    let ix = instruction::reclaim(&program_id, &owner.pubkey(), &holding_account);
+    env.process(&[ix], &[&owner])
+        .await
+        .expect("Unable to reclaim a migrated escrow");
+    env.assert_token_owner(&holding_account, &owner.pubkey())
+        .await;
+    env.assert_no_escrow(&holding_account).await;
+
+    env.deposit(&alice, &token_expected, 5)
+        .await
+        .expect("Unable to make a deposit");
+    let escrow = env.assert_escrow(&alice.token_account.pubkey()).await;
+    assert_eq!(escrow.sequence, 2);
+}
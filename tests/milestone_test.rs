@@ -0,0 +1,172 @@
+use solana_escrow::instruction;
+use solana_escrow::test_fixtures::{assert_instruction_error, EscrowTestEnv};
+use solana_escrow::{EscrowError, Milestone, MilestoneTerms};
+use solana_program::clock::Clock;
+use solana_program_test::tokio;
+use solana_sdk::instruction::InstructionError;
+use solana_sdk::signature::{Keypair, Signer};
+
+fn milestones(amounts: &[u64]) -> Vec<Milestone> {
+    amounts
+        .iter()
+        .enumerate()
+        .map(|(i, &amount)| Milestone {
+            amount,
+            description_hash: [i as u8; 32],
+        })
+        .collect()
+}
+
+#[tokio::test]
+async fn milestone_test() {
+    let mut env = EscrowTestEnv::builder().build().await;
+    let alice = env.create_user(100).await;
+    let payee = Keypair::new();
+    let arbiter = Keypair::new();
+    let payee_account = env
+        .create_token_account(&payee.pubkey(), &alice.mint_account.pubkey())
+        .await;
+    let holding = alice.token_account.pubkey();
+    let clock: Clock = env.banks_client.get_sysvar().await.unwrap();
+    let deadline = clock.unix_timestamp + 1_000;
+
+    let program_id = env.program_id;
+    let create = |amounts: &[u64]| {
+        instruction::create_milestones(
+            &program_id,
+            &alice.wallet_account.pubkey(),
+            &holding,
+            &alice.mint_account.pubkey(),
+            &payee.pubkey(),
+            Some(&arbiter.pubkey()),
+            Some(deadline),
+            &milestones(amounts),
+        )
+    };
+
+    // Milestones split the whole deposit
+    let ix = create(&[50, 40]);
+    let err = env
+        .process(&[ix], &[&alice.wallet_account])
+        .await
+        .unwrap_err();
+    assert_instruction_error(err, InstructionError::InvalidArgument);
+
+    let ix = create(&[50, 30, 20]);
+    env.process(&[ix], &[&alice.wallet_account])
+        .await
+        .expect("Unable to create a milestone escrow");
+
+    let err = env.reclaim(&alice).await.unwrap_err();
+    assert_instruction_error(
+        err,
+        InstructionError::Custom(EscrowError::WrongEscrowKind as u32),
+    );
+
+    let release = |releaser: &Keypair, index| {
+        instruction::release_milestone(
+            &program_id,
+            &releaser.pubkey(),
+            &holding,
+            &payee_account.pubkey(),
+            index,
+        )
+    };
+
+    // Only the depositor or the arbiter release milestones, in order
+    let err = env
+        .process(&[release(&payee, 0)], &[&payee])
+        .await
+        .unwrap_err();
+    assert_instruction_error(err, InstructionError::IllegalOwner);
+    let err = env
+        .process(&[release(&arbiter, 1)], &[&arbiter])
+        .await
+        .unwrap_err();
+    assert_instruction_error(err, InstructionError::InvalidArgument);
+
+    env.process(
+        &[release(&alice.wallet_account, 0)],
+        &[&alice.wallet_account],
+    )
+    .await
+    .expect("Unable to release a milestone");
+    env.process(&[release(&arbiter, 1)], &[&arbiter])
+        .await
+        .expect("Unable to release a milestone");
+    assert_eq!(env.token_account(&payee_account.pubkey()).await.amount, 80);
+    let terms: MilestoneTerms = env.assert_escrow(&holding).await.terms().unwrap();
+    assert_eq!(terms.released, 2);
+
+    let refund =
+        instruction::refund_milestones(&env.program_id, &alice.wallet_account.pubkey(), &holding);
+    let err = env
+        .process(std::slice::from_ref(&refund), &[&alice.wallet_account])
+        .await
+        .unwrap_err();
+    assert_instruction_error(
+        err,
+        InstructionError::Custom(EscrowError::DeadlineNotReached as u32),
+    );
+
+    env.warp_clock(1, 1_000).await;
+    env.process(&[refund], &[&alice.wallet_account])
+        .await
+        .expect("Unable to refund milestones");
+    env.assert_no_escrow(&holding).await;
+    env.assert_token_owner(&holding, &alice.wallet_account.pubkey())
+        .await;
+    assert_eq!(env.token_account(&holding).await.amount, 20);
+}
+
+#[tokio::test]
+async fn last_milestone_closes_escrow_test() {
+    let mut env = EscrowTestEnv::builder().build().await;
+    let alice = env.create_user(10).await;
+    let payee = Keypair::new();
+    let payee_account = env
+        .create_token_account(&payee.pubkey(), &alice.mint_account.pubkey())
+        .await;
+    let holding = alice.token_account.pubkey();
+
+    let ix = instruction::create_milestones(
+        &env.program_id,
+        &alice.wallet_account.pubkey(),
+        &holding,
+        &alice.mint_account.pubkey(),
+        &payee.pubkey(),
+        None,
+        None,
+        &milestones(&[10]),
+    );
+    env.process(&[ix], &[&alice.wallet_account])
+        .await
+        .expect("Unable to create a milestone escrow");
+
+    // Without a deadline nothing is refundable
+    let ix =
+        instruction::refund_milestones(&env.program_id, &alice.wallet_account.pubkey(), &holding);
+    let err = env
+        .process(&[ix], &[&alice.wallet_account])
+        .await
+        .unwrap_err();
+    assert_instruction_error(
+        err,
+        InstructionError::Custom(EscrowError::DeadlineNotReached as u32),
+    );
+
+    let ix = instruction::release_milestone(
+        &env.program_id,
+        &alice.wallet_account.pubkey(),
+        &holding,
+        &payee_account.pubkey(),
+        0,
+    );
+    env.process(&[ix], &[&alice.wallet_account])
+        .await
+        .expect("Unable to release a milestone");
+    env.assert_no_escrow(&holding).await;
+    env.assert_token_owner(&holding, &alice.wallet_account.pubkey())
+        .await;
+    assert_eq!(env.token_account(&payee_account.pubkey()).await.amount, 10);
+}
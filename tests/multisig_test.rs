@@ -0,0 +1,112 @@
+use solana_escrow::instruction;
+use solana_escrow::test_fixtures::{assert_instruction_error, EscrowTestEnv, UserAccounts};
+use solana_escrow::{EscrowError, MultisigTerms};
+use solana_program::pubkey::Pubkey;
+use solana_program_test::{tokio, BanksClientError};
+use solana_sdk::instruction::InstructionError;
+use solana_sdk::signature::{Keypair, Signer};
+
+async fn create_multisig(
+    env: &mut EscrowTestEnv,
+    owner: &UserAccounts,
+    recipient: &Pubkey,
+    threshold: u8,
+    approvers: &[Pubkey],
+) -> Result<(), BanksClientError> {
+    let ix = instruction::create_multisig(
+        &env.program_id,
+        &owner.wallet_account.pubkey(),
+        &owner.token_account.pubkey(),
+        &owner.mint_account.pubkey(),
+        recipient,
+        threshold,
+        approvers,
+    );
+    env.process(&[ix], &[&owner.wallet_account]).await
+}
+
+#[tokio::test]
+async fn multisig_test() {
+    let mut env = EscrowTestEnv::builder().build().await;
+    let alice = env.create_user(10).await;
+    let dave = env.create_user(10).await;
+    let bob = Keypair::new();
+    let approvers = [Keypair::new(), Keypair::new(), Keypair::new()];
+    let approver_keys = approvers.each_ref().map(|approver| approver.pubkey());
+    let holding = alice.token_account.pubkey();
+
+    let err = create_multisig(&mut env, &alice, &bob.pubkey(), 4, &approver_keys)
+        .await
+        .unwrap_err();
+    assert_instruction_error(err, InstructionError::InvalidArgument);
+    let duplicates = [approver_keys[0], approver_keys[0]];
+    let err = create_multisig(&mut env, &alice, &bob.pubkey(), 1, &duplicates)
+        .await
+        .unwrap_err();
+    assert_instruction_error(err, InstructionError::InvalidArgument);
+
+    create_multisig(&mut env, &alice, &bob.pubkey(), 2, &approver_keys)
+        .await
+        .expect("Unable to create a multisig escrow");
+
+    let err = env.reclaim(&alice).await.unwrap_err();
+    assert_instruction_error(
+        err,
+        InstructionError::Custom(EscrowError::WrongEscrowKind as u32),
+    );
+
+    let program_id = env.program_id;
+    let approve = |approver: &Keypair, refund| {
+        instruction::approve(&program_id, &approver.pubkey(), &holding, refund)
+    };
+    let stranger = Keypair::new();
+    let err = env
+        .process(&[approve(&stranger, false)], &[&stranger])
+        .await
+        .unwrap_err();
+    assert_instruction_error(err, InstructionError::IllegalOwner);
+
+    // Approvals are revocable before the threshold
+    env.process(&[approve(&approvers[0], false)], &[&approvers[0]])
+        .await
+        .expect("Unable to approve");
+    let terms: MultisigTerms = env.assert_escrow(&holding).await.terms().unwrap();
+    assert_eq!(terms.release_approvals, 0b001);
+    let ix = instruction::revoke(&env.program_id, &approvers[0].pubkey(), &holding);
+    env.process(&[ix], &[&approvers[0]])
+        .await
+        .expect("Unable to revoke");
+    let terms: MultisigTerms = env.assert_escrow(&holding).await.terms().unwrap();
+    assert_eq!(terms.release_approvals, 0);
+
+    // Split approvals don't reach the threshold
+    env.process(&[approve(&approvers[1], false)], &[&approvers[1]])
+        .await
+        .expect("Unable to approve");
+    env.process(&[approve(&approvers[0], true)], &[&approvers[0]])
+        .await
+        .expect("Unable to approve");
+    env.assert_escrow(&holding).await;
+
+    env.process(&[approve(&approvers[2], false)], &[&approvers[2]])
+        .await
+        .expect("Unable to approve");
+    env.assert_no_escrow(&holding).await;
+    env.assert_token_owner(&holding, &bob.pubkey()).await;
+
+    create_multisig(&mut env, &dave, &bob.pubkey(), 1, &approver_keys[..1])
+        .await
+        .expect("Unable to create a multisig escrow");
+    let ix = instruction::approve(
+        &env.program_id,
+        &approvers[0].pubkey(),
+        &dave.token_account.pubkey(),
+        true,
+    );
+    env.process(&[ix], &[&approvers[0]])
+        .await
+        .expect("Unable to approve");
+    env.assert_no_escrow(&dave.token_account.pubkey()).await;
+    env.assert_token_owner(&dave.token_account.pubkey(), &dave.wallet_account.pubkey())
+        .await;
+}
@@ -1,15 +1,14 @@
-mod test_utils;
-
-use std::panic::panic_any;
+use solana_escrow::test_fixtures::*;
+use solana_escrow::{process_instruction, SEED};
 use solana_program::instruction::AccountMeta;
 use solana_program::pubkey::Pubkey;
-use solana_program_test::{tokio, ProgramTest};
 use solana_program_test::BanksClientError::TransactionError;
+use solana_program_test::{processor, tokio, ProgramTest};
+use solana_sdk::instruction::InstructionError;
 use solana_sdk::signature::{Keypair, Signer};
 use solana_sdk::system_program;
 use solana_sdk::transaction::Transaction;
-use solana_sdk::instruction::InstructionError;
-use test_utils::*;
+use std::panic::panic_any;
 
 #[tokio::test]
 async fn negative_test() {
@@ -19,8 +18,11 @@ async fn negative_test() {
     let bob_token_amount: u32 = 5u32;
     let david_token_amount: u32 = 6u32;
 
-    let mut test_program = ProgramTest::default();
-    test_program.add_program("solana_escrow", escrow_program_kp.pubkey(), None);
+    let test_program = ProgramTest::new(
+        "solana_escrow",
+        escrow_program_kp.pubkey(),
+        processor!(process_instruction),
+    );
     let (mut banks_client, payer, recent_blockhash) = test_program.start().await;
 
     let alice = UserAccounts::prepare(
@@ -28,24 +30,27 @@ async fn negative_test() {
         &recent_blockhash,
         &payer,
         &mint_authority_kp,
-        alice_token_amount,
-    ).await;
+        alice_token_amount.into(),
+    )
+    .await;
 
     let bob = UserAccounts::prepare(
         &mut banks_client,
         &recent_blockhash,
         &payer,
         &mint_authority_kp,
-        bob_token_amount,
-    ).await;
+        bob_token_amount.into(),
+    )
+    .await;
 
     let david = UserAccounts::prepare(
         &mut banks_client,
         &recent_blockhash,
         &payer,
         &mint_authority_kp,
-        david_token_amount,
-    ).await;
+        david_token_amount.into(),
+    )
+    .await;
 
     let (pda_account_pk, bump_seed) =
         Pubkey::find_program_address(&[SEED], &escrow_program_kp.pubkey());
@@ -73,7 +78,7 @@ async fn negative_test() {
         .expect("Unable to init an escrow program");
 
     // Make a deposit by the first party user
-    let alice_desired_amount = unsafe { std::mem::transmute::<u32, [u8; 4]>(bob_token_amount) };
+    let alice_desired_amount = bob_token_amount.to_le_bytes();
     let deposit_ix = solana_sdk::instruction::Instruction {
         program_id: escrow_program_kp.pubkey(),
         accounts: vec![
@@ -99,7 +104,9 @@ async fn negative_test() {
         .expect("Unable to make a deposit");
 
     // Bob's try failed due to his mint mismatch
-    let bob_desired_amount = unsafe { std::mem::transmute::<u32, [u8; 4]>(alice_token_amount) };
+    let bob_desired_amount = alice_token_amount.to_le_bytes();
+    // Alice's deposit is the first one of the registry
+    let alice_sequence = 1u64.to_le_bytes();
     let execute_ix = solana_sdk::instruction::Instruction {
         program_id: escrow_program_kp.pubkey(),
         accounts: vec![
@@ -110,7 +117,12 @@ async fn negative_test() {
             AccountMeta::new_readonly(alice.mint_account.pubkey(), false),
             AccountMeta::new(alice.token_account.pubkey(), false),
         ],
-        data: [[2].as_slice(), bob_desired_amount.as_slice()].concat(),
+        data: [
+            [2].as_slice(),
+            bob_desired_amount.as_slice(),
+            alice_sequence.as_slice(),
+        ]
+        .concat(),
     };
 
     let execute_tx = Transaction::new_signed_with_payer(
@@ -120,26 +132,17 @@ async fn negative_test() {
         recent_blockhash,
     );
 
-    match banks_client
-        .process_transaction(execute_tx)
-        .await {
-        Err(e) => {
-            match e {
-                TransactionError(te) => {match te {
-                    solana_sdk::transaction::TransactionError::InstructionError(_, ie) => { match ie {
-                        InstructionError::Custom(code) => assert_eq!(code, 6u32),
-                        _ => panic_any("Wrong error type"),
-                    }}
-                    _ => panic_any("Wrong error type"),
-                }}
-                _ => panic_any("Wrong error type"),
-            }
-        },
+    match banks_client.process_transaction(execute_tx).await {
+        Err(TransactionError(solana_sdk::transaction::TransactionError::InstructionError(
+            _,
+            InstructionError::Custom(code),
+        ))) => assert_eq!(code, 6u32),
+        Err(_) => panic_any("Wrong error type"),
         Ok(_) => panic_any("Error is expected!"),
     }
 
     // David's try failed due to token amount mismatch
-    let david_desired_amount = unsafe { std::mem::transmute::<u32, [u8; 4]>(alice_token_amount) };
+    let david_desired_amount = alice_token_amount.to_le_bytes();
     let execute_ix = solana_sdk::instruction::Instruction {
         program_id: escrow_program_kp.pubkey(),
         accounts: vec![
@@ -150,7 +153,12 @@ async fn negative_test() {
             AccountMeta::new_readonly(alice.mint_account.pubkey(), false),
             AccountMeta::new(alice.token_account.pubkey(), false),
         ],
-        data: [[2].as_slice(), david_desired_amount.as_slice()].concat(),
+        data: [
+            [2].as_slice(),
+            david_desired_amount.as_slice(),
+            alice_sequence.as_slice(),
+        ]
+        .concat(),
     };
 
     let execute_tx = Transaction::new_signed_with_payer(
@@ -160,22 +168,12 @@ async fn negative_test() {
         recent_blockhash,
     );
 
-    match banks_client
-        .process_transaction(execute_tx)
-        .await {
-        Err(e) => {
-            match e {
-                TransactionError(te) => {match te {
-                    solana_sdk::transaction::TransactionError::InstructionError(_, ie) => { match ie {
-                        InstructionError::Custom(code) => assert_eq!(code, 5u32),
-                        _ => panic_any("Wrong error type"),
-                    }}
-                    _ => panic_any("Wrong error type"),
-                }}
-                _ => panic_any("Wrong error type"),
-            }
-        },
+    match banks_client.process_transaction(execute_tx).await {
+        Err(TransactionError(solana_sdk::transaction::TransactionError::InstructionError(
+            _,
+            InstructionError::Custom(code),
+        ))) => assert_eq!(code, 5u32),
+        Err(_) => panic_any("Wrong error type"),
         Ok(_) => panic_any("Error is expected!"),
     }
-
 }
@@ -1,12 +1,11 @@
-mod test_utils;
-
+use solana_escrow::test_fixtures::*;
+use solana_escrow::{process_instruction, SEED};
 use solana_program::instruction::AccountMeta;
 use solana_program::pubkey::Pubkey;
-use solana_program_test::{tokio, ProgramTest};
+use solana_program_test::{processor, tokio, ProgramTest};
 use solana_sdk::signature::{Keypair, Signer};
 use solana_sdk::system_program;
 use solana_sdk::transaction::Transaction;
-use test_utils::*;
 
 // Replace with devnet or whichever cluster you're currently on
 // let rpc = RpcClient::new(Cluster::Localnet.url());
@@ -18,8 +17,11 @@ async fn positive_generic_flow_test() {
     let alice_token_amount: u32 = 10u32;
     let bob_token_amount: u32 = 5u32;
 
-    let mut test_program = ProgramTest::default();
-    test_program.add_program("solana_escrow", escrow_program_kp.pubkey(), None);
+    let test_program = ProgramTest::new(
+        "solana_escrow",
+        escrow_program_kp.pubkey(),
+        processor!(process_instruction),
+    );
     let (mut banks_client, payer, recent_blockhash) = test_program.start().await;
 
     let alice = UserAccounts::prepare(
@@ -27,7 +29,7 @@ async fn positive_generic_flow_test() {
         &recent_blockhash,
         &payer,
         &mint_authority_kp,
-        alice_token_amount,
+        alice_token_amount.into(),
     )
     .await;
     let bob = UserAccounts::prepare(
@@ -35,7 +37,7 @@ async fn positive_generic_flow_test() {
         &recent_blockhash,
         &payer,
         &mint_authority_kp,
-        bob_token_amount,
+        bob_token_amount.into(),
     )
     .await;
 
@@ -84,7 +86,7 @@ async fn positive_generic_flow_test() {
     assert_eq!(pda_account.owner, escrow_program_kp.pubkey());
 
     // Make a deposit by the first party user
-    let alice_desired_amount = unsafe { std::mem::transmute::<u32, [u8; 4]>(bob_token_amount) };
+    let alice_desired_amount = bob_token_amount.to_le_bytes();
     let deposit_ix = solana_sdk::instruction::Instruction {
         program_id: escrow_program_kp.pubkey(),
         accounts: vec![
@@ -117,7 +119,9 @@ async fn positive_generic_flow_test() {
     .await;
 
     // Execute escrow swap
-    let bob_desired_amount = unsafe { std::mem::transmute::<u32, [u8; 4]>(alice_token_amount) };
+    let bob_desired_amount = alice_token_amount.to_le_bytes();
+    // Alice's deposit is the first one of the registry
+    let alice_sequence = 1u64.to_le_bytes();
     let execute_ix = solana_sdk::instruction::Instruction {
         program_id: escrow_program_kp.pubkey(),
         accounts: vec![
@@ -128,7 +132,12 @@ async fn positive_generic_flow_test() {
             AccountMeta::new_readonly(alice.mint_account.pubkey(), false),
             AccountMeta::new(alice.token_account.pubkey(), false),
         ],
-        data: [[2].as_slice(), bob_desired_amount.as_slice()].concat(),
+        data: [
+            [2].as_slice(),
+            bob_desired_amount.as_slice(),
+            alice_sequence.as_slice(),
+        ]
+        .concat(),
     };
 
     let execute_tx = Transaction::new_signed_with_payer(
@@ -0,0 +1,288 @@
+use proptest::prelude::*;
+use solana_escrow::state::{COLLECTION_WIDTH, TERMS_LEN};
+use solana_escrow::{
+    find_registry_address, process_instruction, Alternative, BundleAsset, Escrow, EscrowCollection,
+    EscrowInstruction, EscrowKind, EscrowQuery, Milestone, ReclaimDelay, RegistryConfig,
+    SignedOffer,
+};
+use solana_program::account_info::AccountInfo;
+use solana_program::program_error::ProgramError;
+use solana_program::program_option::COption;
+use solana_program::program_pack::Pack;
+use solana_program::pubkey::Pubkey;
+use solana_program::rent::Rent;
+
+fn pubkey() -> impl Strategy<Value = Pubkey> {
+    any::<[u8; 32]>().prop_map(Pubkey::new_from_array)
+}
+
+fn escrow() -> impl Strategy<Value = Escrow> {
+    (
+        any::<bool>(),
+        any::<u32>(),
+        pubkey(),
+        pubkey(),
+        pubkey(),
+        any::<u64>(),
+        any::<u64>(),
+        any::<i64>(),
+        prop_oneof![
+            Just(EscrowKind::Swap),
+            Just(EscrowKind::EnglishAuction),
+            Just(EscrowKind::DutchAuction),
+            Just(EscrowKind::Rfq),
+            Just(EscrowKind::Arbitrated),
+            Just(EscrowKind::Multisig),
+            Just(EscrowKind::Milestones),
+            Just(EscrowKind::Htlc),
+            Just(EscrowKind::Delegated),
+            Just(EscrowKind::Bundle),
+        ],
+        prop::collection::vec(any::<u8>(), TERMS_LEN),
+    )
+        .prop_map(
+            |(
+                active,
+                amount_expected,
+                token_expected,
+                holding_account,
+                owner_account,
+                sequence,
+                deposit_slot,
+                deposit_timestamp,
+                kind,
+                terms,
+            )| {
+                Escrow {
+                    active,
+                    amount_expected,
+                    token_expected,
+                    holding_account,
+                    owner_account,
+                    sequence,
+                    deposit_slot,
+                    deposit_timestamp,
+                    kind,
+                    terms: terms.try_into().unwrap(),
+                }
+            },
+        )
+}
+
+fn instruction() -> impl Strategy<Value = (Vec<u8>, u8, u8, u32, Vec<u32>, u64, Pubkey)> {
+    (
+        prop::collection::vec(any::<u8>(), 0..40),
+        any::<u8>(),
+        0..44u8,
+        any::<u32>(),
+        prop::collection::vec(any::<u32>(), 1..8),
+        any::<u64>(),
+        pubkey(),
+    )
+}
+
+proptest! {
+    #[test]
+    fn instruction_round_trip(
+        (seed, bump_seed, tag, amount_expected, amounts_expected, lamports, key) in instruction()
+    ) {
+        let instruction = match tag {
+            0 => EscrowInstruction::Init { seed: &seed, bump_seed },
+            1 => EscrowInstruction::Deposit { amount_expected },
+            2 => EscrowInstruction::Execute { amount_expected, sequence: lamports },
+            3 => EscrowInstruction::ExecuteBatch {
+                sequences: amounts_expected.iter().map(|&amount| lamports.wrapping_add(amount as u64)).collect(),
+                amounts_expected,
+            },
+            4 => EscrowInstruction::Configure {
+                match_reward_lamports: lamports,
+                reclaim_delay: ReclaimDelay::Seconds(amount_expected.into()),
+            },
+            5 => EscrowInstruction::Match,
+            6 => EscrowInstruction::Query { query: EscrowQuery::HoldingAccount(key) },
+            7 => EscrowInstruction::Query { query: EscrowQuery::Owner(key) },
+            8 => EscrowInstruction::Execute { amount_expected, sequence: 0 },
+            9 => EscrowInstruction::Amend { amount_expected },
+            10 => EscrowInstruction::TopUp { amount: lamports, amount_expected },
+            11 => EscrowInstruction::Withdraw { amount: lamports, amount_expected },
+            12 => EscrowInstruction::CreateAuction {
+                kind: if bump_seed % 2 == 0 { EscrowKind::EnglishAuction } else { EscrowKind::DutchAuction },
+                reserve_price: amount_expected.into(),
+                start_price: lamports,
+                end_timestamp: lamports as i64,
+            },
+            13 => EscrowInstruction::Bid,
+            14 => EscrowInstruction::SettleAuction,
+            15 => EscrowInstruction::CreateRfq,
+            16 => EscrowInstruction::SubmitBid,
+            17 => EscrowInstruction::AcceptBid,
+            18 => EscrowInstruction::WithdrawBid,
+            19 => EscrowInstruction::CreateArbitrated { seller: key, arbiter: Pubkey::new_from_array(key.to_bytes().map(|b| b ^ bump_seed)) },
+            20 => EscrowInstruction::Release,
+            21 => EscrowInstruction::Refund,
+            22 => EscrowInstruction::Dispute,
+            23 => EscrowInstruction::Resolve { seller_bps: amount_expected as u16 },
+            24 => EscrowInstruction::CreateMultisig {
+                recipient: key,
+                threshold: bump_seed,
+                approvers: seed.chunks_exact(32).map(|chunk| Pubkey::try_from(chunk).unwrap()).collect(),
+            },
+            25 => EscrowInstruction::Approve { refund: bump_seed % 2 == 0 },
+            26 => EscrowInstruction::Revoke,
+            27 => EscrowInstruction::CreateMilestones {
+                payee: key,
+                arbiter: (bump_seed % 2 == 0).then_some(Pubkey::new_from_array([bump_seed | 1; 32])),
+                deadline: (lamports != 0).then_some(lamports as i64),
+                milestones: amounts_expected.iter().take(4).map(|&amount| Milestone {
+                    amount: amount.into(),
+                    description_hash: key.to_bytes(),
+                }).collect(),
+            },
+            28 => EscrowInstruction::ReleaseMilestone { index: bump_seed },
+            29 => EscrowInstruction::RefundMilestones,
+            30 => EscrowInstruction::CreateHtlc {
+                recipient: key,
+                hashlock: [bump_seed; 32],
+                timeout: lamports as i64,
+            },
+            31 => EscrowInstruction::Claim { preimage: &seed },
+            32 => EscrowInstruction::RefundHtlc,
+            33 => EscrowInstruction::FillOffer {
+                offer: SignedOffer {
+                    maker: key,
+                    maker_mint: Pubkey::new_from_array([bump_seed; 32]),
+                    maker_amount: lamports,
+                    taker_mint: key,
+                    taker_amount: amount_expected.into(),
+                    expiry: lamports as i64,
+                    nonce: amounts_expected[0].into(),
+                },
+            },
+            34 => EscrowInstruction::CancelOffer { nonce: lamports },
+            35 => EscrowInstruction::DepositDelegated { amount_expected },
+            36 => EscrowInstruction::ExecuteDelegated { amount_expected: lamports, sequence: amount_expected.into() },
+            37 => EscrowInstruction::DepositAlternatives {
+                amount_expected,
+                alternatives: amounts_expected
+                    .iter()
+                    .take(3)
+                    .map(|&amount| Alternative { mint: key, amount })
+                    .collect(),
+            },
+            38 => EscrowInstruction::CreateBundle {
+                wanted: amounts_expected
+                    .iter()
+                    .take(4)
+                    .map(|&amount| BundleAsset { mint: key, amount })
+                    .collect(),
+            },
+            39 => EscrowInstruction::ExecuteBundle { sequence: lamports },
+            40 => EscrowInstruction::ExecuteRing,
+            41 => EscrowInstruction::MigrateRegistry,
+            _ => EscrowInstruction::Reclaim,
+        };
+        let data = instruction.pack();
+        prop_assert_eq!(EscrowInstruction::unpack(&data).unwrap(), instruction);
+    }
+
+    #[test]
+    fn execute_without_full_sequence_is_rejected(
+        amount_expected in any::<u32>(),
+        sequence in prop::collection::vec(any::<u8>(), 0..8),
+    ) {
+        let data = [[2].as_slice(), &amount_expected.to_le_bytes(), &sequence].concat();
+        prop_assert_eq!(EscrowInstruction::unpack(&data), Err(ProgramError::InvalidInstructionData));
+    }
+
+    #[test]
+    fn unpack_never_panics(data in prop::collection::vec(any::<u8>(), 0..16)) {
+        let _ = EscrowInstruction::unpack(&data);
+    }
+
+    #[test]
+    fn escrow_round_trip(escrow in escrow()) {
+        let mut data = vec![0u8; Escrow::LEN];
+        escrow.pack_into_slice(&mut data);
+        prop_assert_eq!(Escrow::unpack_from_slice(&data).unwrap(), escrow);
+    }
+
+    #[test]
+    fn escrow_collection_round_trip(
+        items in prop::collection::vec(escrow(), COLLECTION_WIDTH),
+        admin in pubkey(),
+        match_reward_lamports in any::<u64>(),
+        next_sequence in any::<u64>(),
+        reclaim_delay_slots in any::<u64>(),
+    ) {
+        let collection = EscrowCollection {
+            config: RegistryConfig {
+                admin,
+                match_reward_lamports,
+                next_sequence,
+                reclaim_delay: ReclaimDelay::Slots(reclaim_delay_slots),
+            },
+            items: items.try_into().unwrap(),
+        };
+        let mut data = vec![0u8; EscrowCollection::LEN];
+        collection.pack_into_slice(&mut data);
+        prop_assert_eq!(EscrowCollection::unpack_from_slice(&data).unwrap(), collection);
+    }
+
+    #[test]
+    fn short_state_is_rejected(len in 0..EscrowCollection::LEN) {
+        let data = vec![0u8; len];
+        prop_assert!(EscrowCollection::unpack_from_slice(&data).is_err());
+        prop_assert!(Escrow::unpack_from_slice(&data[..len.min(Escrow::LEN - 1)]).is_err());
+    }
+
+    #[test]
+    fn processing_arbitrary_data_never_panics(
+        instruction_data in prop::collection::vec(any::<u8>(), 0..16),
+        registry_data in prop::collection::vec(any::<u8>(), 0..EscrowCollection::LEN + 8),
+        flags in any::<[bool; 6]>(),
+        amount in any::<u64>(),
+        account_count in 0..7usize,
+    ) {
+        let program_id = Pubkey::new_unique();
+        let (registry, _) = find_registry_address(&program_id);
+        let owner = Pubkey::new_unique();
+        let mint = Pubkey::new_unique();
+        let keys = [registry, owner, Pubkey::new_unique(), spl_token::id(), mint, Pubkey::new_unique()];
+
+        let token_account = spl_token::state::Account {
+            mint,
+            owner: if flags[5] { registry } else { owner },
+            amount,
+            state: spl_token::state::AccountState::Initialized,
+            delegate: COption::None,
+            is_native: COption::None,
+            delegated_amount: 0,
+            close_authority: COption::None,
+        };
+        let mut token_data = vec![0u8; spl_token::state::Account::LEN];
+        token_account.pack_into_slice(&mut token_data);
+
+        let mut datas = [
+            registry_data,
+            vec![],
+            token_data.clone(),
+            vec![],
+            vec![],
+            token_data,
+        ];
+        let owners = [program_id, Pubkey::default(), spl_token::id(), Pubkey::default(), spl_token::id(), spl_token::id()];
+        let rent = Rent::default().minimum_balance(spl_token::state::Account::LEN);
+        let mut lamports = [rent; 6];
+
+        let accounts: Vec<AccountInfo> = datas
+            .iter_mut()
+            .zip(lamports.iter_mut())
+            .enumerate()
+            .map(|(i, (data, lamports))| {
+                AccountInfo::new(&keys[i], flags[i], flags[i] || i != 1, lamports, data, &owners[i], false, 0)
+            })
+            .collect();
+
+        let _ = process_instruction(&program_id, &accounts[..account_count], &instruction_data);
+    }
+}
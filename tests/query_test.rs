@@ -0,0 +1,150 @@
+use solana_escrow::instruction::{self, unpack_query_result};
+use solana_escrow::test_fixtures::EscrowTestEnv;
+use solana_escrow::{cpi, Escrow, EscrowQuery, MAX_QUERY_RESULTS};
+use solana_program::account_info::{next_account_info, AccountInfo};
+use solana_program::entrypoint::ProgramResult;
+use solana_program::instruction::{AccountMeta, Instruction};
+use solana_program::program::MAX_RETURN_DATA;
+use solana_program::program_error::ProgramError;
+use solana_program::program_pack::Pack;
+use solana_program::pubkey::Pubkey;
+use solana_program_test::{processor, tokio};
+use solana_sdk::signature::Signer;
+use solana_sdk::transaction::Transaction;
+
+/// Mock program failing unless the escrow program reports the expected number of escrows of an owner
+fn checker_process_instruction(
+    _program_id: &Pubkey,
+    accounts: &[AccountInfo],
+    instruction_data: &[u8],
+) -> ProgramResult {
+    let account_info_iter = &mut accounts.iter();
+    let escrow_program = next_account_info(account_info_iter)?;
+    let registry = next_account_info(account_info_iter)?;
+    let owner = next_account_info(account_info_iter)?;
+
+    let escrows = cpi::query(escrow_program, registry, EscrowQuery::Owner(*owner.key))?;
+    if escrows.len() != instruction_data[0] as usize {
+        return Err(ProgramError::InvalidArgument);
+    }
+    Ok(())
+}
+
+async fn simulate_query(env: &mut EscrowTestEnv, query: EscrowQuery) -> Vec<Escrow> {
+    let ix = instruction::query(&env.program_id, query);
+    let tx = Transaction::new_signed_with_payer(
+        &[ix],
+        Some(&env.payer.pubkey()),
+        &[&env.payer],
+        env.banks_client.get_latest_blockhash().await.unwrap(),
+    );
+    let simulation = env
+        .banks_client
+        .simulate_transaction(tx)
+        .await
+        .expect("Unable to simulate a query");
+    let return_data = simulation
+        .simulation_details
+        .unwrap()
+        .return_data
+        .map(|return_data| return_data.data)
+        .unwrap_or_default();
+    unpack_query_result(&return_data).expect("Unable to decode query result")
+}
+
+#[tokio::test]
+async fn query_test() {
+    let checker_program_id = Pubkey::new_unique();
+    let mut env = EscrowTestEnv::builder()
+        .configure(|program_test| {
+            program_test.add_program(
+                "checker",
+                checker_program_id,
+                processor!(checker_process_instruction),
+            )
+        })
+        .build()
+        .await;
+    let alice = env.create_user(10).await;
+    let bob = env.create_user(5).await;
+
+    assert!(simulate_query(
+        &mut env,
+        EscrowQuery::HoldingAccount(alice.token_account.pubkey())
+    )
+    .await
+    .is_empty());
+
+    env.deposit(&alice, &bob.mint_account.pubkey(), 5)
+        .await
+        .expect("Unable to make a deposit");
+
+    let found = simulate_query(
+        &mut env,
+        EscrowQuery::HoldingAccount(alice.token_account.pubkey()),
+    )
+    .await;
+    assert_eq!(
+        found,
+        vec![env.assert_escrow(&alice.token_account.pubkey()).await]
+    );
+    let found = simulate_query(&mut env, EscrowQuery::Owner(alice.wallet_account.pubkey())).await;
+    assert_eq!(found.len(), 1);
+    assert_eq!(found[0].amount_expected, 5);
+    assert!(
+        simulate_query(&mut env, EscrowQuery::Owner(bob.wallet_account.pubkey()))
+            .await
+            .is_empty()
+    );
+
+    let check = |owner: Pubkey, expected: u8| Instruction {
+        program_id: checker_program_id,
+        accounts: vec![
+            AccountMeta::new_readonly(env.program_id, false),
+            AccountMeta::new_readonly(env.registry_address(), false),
+            AccountMeta::new_readonly(owner, false),
+        ],
+        data: vec![expected],
+    };
+    let alice_check = check(alice.wallet_account.pubkey(), 1);
+    let bob_check = check(bob.wallet_account.pubkey(), 0);
+    env.process(&[alice_check, bob_check], &[])
+        .await
+        .expect("Unable to query the escrow via CPI");
+}
+
+#[tokio::test]
+async fn query_result_fits_return_data_test() {
+    let mut env = EscrowTestEnv::builder().build().await;
+    let alice = env.create_user(10).await;
+    let bob = env.create_user(5).await;
+    let owner = alice.wallet_account.pubkey();
+    let mint = alice.mint_account.pubkey();
+
+    let mut holdings = vec![alice.token_account.pubkey()];
+    for amount in [20, 30] {
+        let token_account = env.create_token_account(&owner, &mint).await;
+        env.mint_to(&mint, &token_account.pubkey(), amount).await;
+        holdings.push(token_account.pubkey());
+    }
+    for holding in &holdings {
+        let ix = instruction::deposit(
+            &env.program_id,
+            &owner,
+            holding,
+            &bob.mint_account.pubkey(),
+            5,
+        );
+        env.process(&[ix], &[&alice.wallet_account])
+            .await
+            .expect("Unable to make a deposit");
+    }
+
+    let found = simulate_query(&mut env, EscrowQuery::Owner(owner)).await;
+    assert!(holdings.len() > MAX_QUERY_RESULTS);
+    assert_eq!(found.len(), MAX_QUERY_RESULTS);
+    assert!(found.len() * Escrow::LEN <= MAX_RETURN_DATA);
+    assert!(found
+        .iter()
+        .all(|escrow| holdings.contains(&escrow.holding_account)));
+}
@@ -0,0 +1,66 @@
+use solana_escrow::state::MAX_RECLAIM_DELAY_SLOTS;
+use solana_escrow::test_fixtures::EscrowTestEnv;
+use solana_escrow::{EscrowError, ReclaimDelay};
+use solana_program_test::{tokio, BanksClientError};
+use solana_sdk::instruction::InstructionError;
+use solana_sdk::signature::Signer;
+use solana_sdk::transaction::TransactionError;
+
+fn instruction_error(err: BanksClientError) -> InstructionError {
+    match err {
+        BanksClientError::TransactionError(TransactionError::InstructionError(_, err)) => err,
+        err => panic!("Unexpected error: {:?}", err),
+    }
+}
+
+#[tokio::test]
+async fn reclaim_delay_in_slots_test() {
+    let mut env = EscrowTestEnv::builder().build().await;
+    let alice = env.create_user(10).await;
+
+    let err = env
+        .configure(0, ReclaimDelay::Slots(MAX_RECLAIM_DELAY_SLOTS + 1))
+        .await
+        .unwrap_err();
+    assert_eq!(instruction_error(err), InstructionError::InvalidArgument);
+
+    env.configure(0, ReclaimDelay::Slots(100))
+        .await
+        .expect("Unable to configure the registry");
+    env.deposit(&alice, &alice.mint_account.pubkey(), 10)
+        .await
+        .expect("Unable to make a deposit");
+
+    let err = env.reclaim(&alice).await.unwrap_err();
+    assert_eq!(
+        instruction_error(err),
+        InstructionError::Custom(EscrowError::ReclaimTooEarly as u32)
+    );
+
+    env.warp_clock(100, 0).await;
+    env.reclaim(&alice).await.expect("Unable to make reclaim");
+    env.assert_no_escrow(&alice.token_account.pubkey()).await;
+}
+
+#[tokio::test]
+async fn reclaim_delay_in_seconds_test() {
+    let mut env = EscrowTestEnv::builder().build().await;
+    let alice = env.create_user(10).await;
+
+    env.configure(0, ReclaimDelay::Seconds(60))
+        .await
+        .expect("Unable to configure the registry");
+    env.deposit(&alice, &alice.mint_account.pubkey(), 10)
+        .await
+        .expect("Unable to make a deposit");
+
+    env.warp_clock(1, 30).await;
+    let err = env.reclaim(&alice).await.unwrap_err();
+    assert_eq!(
+        instruction_error(err),
+        InstructionError::Custom(EscrowError::ReclaimTooEarly as u32)
+    );
+
+    env.warp_clock(1, 30).await;
+    env.reclaim(&alice).await.expect("Unable to make reclaim");
+}
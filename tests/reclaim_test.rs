@@ -1,12 +1,11 @@
-mod test_utils;
-
+use solana_escrow::test_fixtures::*;
+use solana_escrow::{process_instruction, SEED};
 use solana_program::instruction::AccountMeta;
 use solana_program::pubkey::Pubkey;
-use solana_program_test::{tokio, ProgramTest};
+use solana_program_test::{processor, tokio, ProgramTest};
 use solana_sdk::signature::{Keypair, Signer};
 use solana_sdk::system_program;
 use solana_sdk::transaction::Transaction;
-use test_utils::*;
 
 #[tokio::test]
 async fn reclaim_test() {
@@ -14,8 +13,11 @@ async fn reclaim_test() {
     let escrow_program_kp = Keypair::new();
     let alice_token_amount: u32 = 10u32;
 
-    let mut test_program = ProgramTest::default();
-    test_program.add_program("solana_escrow", escrow_program_kp.pubkey(), None);
+    let test_program = ProgramTest::new(
+        "solana_escrow",
+        escrow_program_kp.pubkey(),
+        processor!(process_instruction),
+    );
     let (mut banks_client, payer, recent_blockhash) = test_program.start().await;
 
     let alice = UserAccounts::prepare(
@@ -23,9 +25,9 @@ async fn reclaim_test() {
         &recent_blockhash,
         &payer,
         &mint_authority_kp,
-        alice_token_amount,
+        alice_token_amount.into(),
     )
-        .await;
+    .await;
 
     let (pda_account_pk, bump_seed) =
         Pubkey::find_program_address(&[SEED], &escrow_program_kp.pubkey());
@@ -52,9 +54,8 @@ async fn reclaim_test() {
         .await
         .expect("Unable to init an escrow program");
 
-
     // Make a deposit by the first party user
-    let alice_desired_amount = unsafe { std::mem::transmute::<u32, [u8; 4]>(10u32) };
+    let alice_desired_amount = 10u32.to_le_bytes();
     let deposit_ix = solana_sdk::instruction::Instruction {
         program_id: escrow_program_kp.pubkey(),
         accounts: vec![
@@ -84,7 +85,7 @@ async fn reclaim_test() {
         &alice.token_account.pubkey(),
         |account| assert_eq!(account.owner, pda_account_pk),
     )
-        .await;
+    .await;
 
     let reclaim_ix = solana_sdk::instruction::Instruction {
         program_id: escrow_program_kp.pubkey(),
@@ -113,5 +114,6 @@ async fn reclaim_test() {
         &mut banks_client,
         &alice.token_account.pubkey(),
         |account| assert_eq!(account.owner, alice.wallet_account.pubkey()),
-    ).await;
+    )
+    .await;
 }
@@ -0,0 +1,137 @@
+use solana_escrow::instruction;
+use solana_escrow::test_fixtures::{assert_instruction_error, EscrowTestEnv};
+use solana_escrow::{find_bid_vault_address, BidRecord, EscrowError, EscrowKind};
+use solana_program::program_pack::Pack;
+use solana_program::pubkey::Pubkey;
+use solana_program::system_instruction;
+use solana_program_test::tokio;
+use solana_sdk::instruction::InstructionError;
+use solana_sdk::native_token::LAMPORTS_PER_SOL;
+use solana_sdk::signature::{Keypair, Signer};
+
+/// Creates a funded bidder wallet with an account holding `amount` tokens of `mint`
+async fn create_bidder(env: &mut EscrowTestEnv, mint: &Pubkey, amount: u64) -> (Keypair, Keypair) {
+    let wallet = Keypair::new();
+    let fund =
+        system_instruction::transfer(&env.payer.pubkey(), &wallet.pubkey(), LAMPORTS_PER_SOL);
+    env.process(&[fund], &[])
+        .await
+        .expect("Unable to fund a bidder");
+    let account = env.create_token_account(&wallet.pubkey(), mint).await;
+    env.mint_to(mint, &account.pubkey(), amount).await;
+    (wallet, account)
+}
+
+#[tokio::test]
+async fn rfq_test() {
+    let mut env = EscrowTestEnv::builder().build().await;
+    let alice = env.create_user(10).await;
+    let bid_mint = env.create_mint().await;
+    let (bob, bob_bid) = create_bidder(&mut env, &bid_mint.pubkey(), 5).await;
+    let (carol, carol_bid) = create_bidder(&mut env, &bid_mint.pubkey(), 7).await;
+    let (dave, dave_bid) = create_bidder(&mut env, &alice.mint_account.pubkey(), 7).await;
+    let holding = alice.token_account.pubkey();
+
+    let ix = instruction::create_rfq(
+        &env.program_id,
+        &alice.wallet_account.pubkey(),
+        &holding,
+        &bid_mint.pubkey(),
+    );
+    env.process(&[ix], &[&alice.wallet_account])
+        .await
+        .expect("Unable to create an RFQ");
+    let rfq = env.assert_escrow(&holding).await;
+    assert_eq!(rfq.kind, EscrowKind::Rfq);
+
+    // Bids are expected in the RFQ mint
+    let ix = instruction::submit_bid(
+        &env.program_id,
+        &dave.pubkey(),
+        &dave_bid.pubkey(),
+        &holding,
+    );
+    let err = env.process(&[ix], &[&dave]).await.unwrap_err();
+    assert_instruction_error(
+        err,
+        InstructionError::Custom(EscrowError::ExecutorTokenMintMismatch as u32),
+    );
+
+    // Lamports sent to a vault address beforehand don't block the bid
+    let (carol_vault, _) = find_bid_vault_address(&env.program_id, &holding, &carol.pubkey());
+    let fund = system_instruction::transfer(&env.payer.pubkey(), &carol_vault, 1_000_000);
+    env.process(&[fund], &[])
+        .await
+        .expect("Unable to fund the vault address");
+
+    for (bidder, bid_account) in [(&bob, &bob_bid), (&carol, &carol_bid)] {
+        let ix = instruction::submit_bid(
+            &env.program_id,
+            &bidder.pubkey(),
+            &bid_account.pubkey(),
+            &holding,
+        );
+        env.process(&[ix], &[bidder])
+            .await
+            .expect("Unable to submit a bid");
+        let (vault, _) = find_bid_vault_address(&env.program_id, &holding, &bidder.pubkey());
+        env.assert_token_owner(&bid_account.pubkey(), &vault).await;
+    }
+    let record = env
+        .banks_client
+        .get_account(carol_vault)
+        .await
+        .unwrap()
+        .expect("Bid vault is expected");
+    let record = BidRecord::unpack_from_slice(&record.data).unwrap();
+    assert_eq!(record.amount, 7);
+    assert_eq!(record.sequence, rfq.sequence);
+
+    // Bids are locked while the RFQ is open
+    let withdraw_bob =
+        instruction::withdraw_bid(&env.program_id, &bob.pubkey(), &holding, &bob_bid.pubkey());
+    let err = env
+        .process(std::slice::from_ref(&withdraw_bob), &[&bob])
+        .await
+        .unwrap_err();
+    assert_instruction_error(err, InstructionError::Custom(EscrowError::BidLocked as u32));
+
+    // Only the maker accepts bids
+    let ix = instruction::accept_bid(
+        &env.program_id,
+        &bob.pubkey(),
+        &holding,
+        &carol.pubkey(),
+        &carol_bid.pubkey(),
+    );
+    let err = env.process(&[ix], &[&bob]).await.unwrap_err();
+    assert_instruction_error(err, InstructionError::IllegalOwner);
+
+    let ix = instruction::accept_bid(
+        &env.program_id,
+        &alice.wallet_account.pubkey(),
+        &holding,
+        &carol.pubkey(),
+        &carol_bid.pubkey(),
+    );
+    env.process(&[ix], &[&alice.wallet_account])
+        .await
+        .expect("Unable to accept a bid");
+    env.assert_no_escrow(&holding).await;
+    env.assert_token_owner(&holding, &carol.pubkey()).await;
+    env.assert_token_owner(&carol_bid.pubkey(), &alice.wallet_account.pubkey())
+        .await;
+    assert!(env
+        .banks_client
+        .get_account(carol_vault)
+        .await
+        .unwrap()
+        .is_none());
+
+    // The other bids are refundable now
+    env.process(&[withdraw_bob], &[&bob])
+        .await
+        .expect("Unable to withdraw a bid");
+    env.assert_token_owner(&bob_bid.pubkey(), &bob.pubkey())
+        .await;
+}
@@ -0,0 +1,85 @@
+use solana_escrow::instruction;
+use solana_escrow::test_fixtures::{assert_instruction_error, EscrowTestEnv};
+use solana_escrow::EscrowError;
+use solana_program_test::{tokio, BanksClientError};
+use solana_sdk::instruction::InstructionError;
+use solana_sdk::pubkey::Pubkey;
+use solana_sdk::signature::{Keypair, Signer};
+
+async fn execute_ring(
+    env: &mut EscrowTestEnv,
+    executor: &Keypair,
+    deposits: &[Pubkey],
+) -> Result<(), BanksClientError> {
+    let ix = instruction::execute_ring(&env.program_id, &executor.pubkey(), deposits);
+    env.process(&[ix], &[executor]).await
+}
+
+#[tokio::test]
+async fn execute_ring_test() {
+    let mut env = EscrowTestEnv::builder().build().await;
+    let alice = env.create_user(10).await;
+    let bob = env.create_user(20).await;
+    let carol = env.create_user(30).await;
+    let executor = Keypair::new();
+
+    // Alice wants what Carol offers, Bob wants Alice's and Carol wants Bob's
+    env.deposit(&alice, &carol.mint_account.pubkey(), 30)
+        .await
+        .expect("Unable to deposit for alice");
+    env.deposit(&bob, &alice.mint_account.pubkey(), 10)
+        .await
+        .expect("Unable to deposit for bob");
+    env.deposit(&carol, &bob.mint_account.pubkey(), 20)
+        .await
+        .expect("Unable to deposit for carol");
+
+    let alice_holding = alice.token_account.pubkey();
+    let bob_holding = bob.token_account.pubkey();
+    let carol_holding = carol.token_account.pubkey();
+
+    let err = execute_ring(&mut env, &executor, &[alice_holding, bob_holding])
+        .await
+        .unwrap_err();
+    assert_instruction_error(err, InstructionError::InvalidArgument);
+
+    let err = execute_ring(
+        &mut env,
+        &executor,
+        &[alice_holding, bob_holding, alice_holding],
+    )
+    .await
+    .unwrap_err();
+    assert_instruction_error(err, InstructionError::InvalidArgument);
+
+    // Alice's tokens would go to Carol, who wants Bob's
+    let err = execute_ring(
+        &mut env,
+        &executor,
+        &[alice_holding, carol_holding, bob_holding],
+    )
+    .await
+    .unwrap_err();
+    assert_instruction_error(
+        err,
+        InstructionError::Custom(EscrowError::ExecutorTokenAmtMismatch as u32),
+    );
+
+    execute_ring(
+        &mut env,
+        &executor,
+        &[alice_holding, bob_holding, carol_holding],
+    )
+    .await
+    .expect("Unable to execute the ring");
+
+    env.assert_token_owner(&alice_holding, &bob.wallet_account.pubkey())
+        .await;
+    env.assert_token_owner(&bob_holding, &carol.wallet_account.pubkey())
+        .await;
+    env.assert_token_owner(&carol_holding, &alice.wallet_account.pubkey())
+        .await;
+    for holding in [alice_holding, bob_holding, carol_holding] {
+        env.assert_no_escrow(&holding).await;
+    }
+}
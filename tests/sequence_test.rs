@@ -0,0 +1,58 @@
+use solana_escrow::instruction;
+use solana_escrow::test_fixtures::EscrowTestEnv;
+use solana_escrow::EscrowError;
+use solana_program_test::{tokio, BanksClientError};
+use solana_sdk::instruction::InstructionError;
+use solana_sdk::signature::Signer;
+use solana_sdk::transaction::TransactionError;
+
+#[tokio::test]
+async fn replaced_escrow_test() {
+    let mut env = EscrowTestEnv::builder().build().await;
+    let alice = env.create_user(10).await;
+    let bob = env.create_user(5).await;
+
+    env.deposit(&alice, &bob.mint_account.pubkey(), 5)
+        .await
+        .expect("Unable to make a deposit");
+    let seen = env.assert_escrow(&alice.token_account.pubkey()).await;
+    assert_eq!(seen.sequence, 1);
+
+    // Alice replaces her offer with the same looking one before Bob's execution lands
+    env.reclaim(&alice).await.expect("Unable to make reclaim");
+    env.deposit(&alice, &bob.mint_account.pubkey(), 5)
+        .await
+        .expect("Unable to make a deposit");
+    let replaced = env.assert_escrow(&alice.token_account.pubkey()).await;
+    assert_eq!(replaced.sequence, 2);
+    assert_eq!(env.registry().await.config.next_sequence, 3);
+
+    let execute = |sequence| {
+        instruction::execute(
+            &env.program_id,
+            &bob.wallet_account.pubkey(),
+            &bob.token_account.pubkey(),
+            &alice.mint_account.pubkey(),
+            &alice.token_account.pubkey(),
+            10,
+            sequence,
+        )
+    };
+    let stale = execute(seen.sequence);
+    let fresh = execute(replaced.sequence);
+
+    match env.process(&[stale], &[&bob.wallet_account]).await {
+        Err(BanksClientError::TransactionError(TransactionError::InstructionError(
+            _,
+            InstructionError::Custom(code),
+        ))) => assert_eq!(code, EscrowError::SequenceMismatch as u32),
+        res => panic!("Unexpected result: {:?}", res),
+    }
+    env.assert_escrow(&alice.token_account.pubkey()).await;
+
+    env.process(&[fresh], &[&bob.wallet_account])
+        .await
+        .expect("Unable to make an escrow execution");
+    env.assert_token_owner(&alice.token_account.pubkey(), &bob.wallet_account.pubkey())
+        .await;
+}
@@ -0,0 +1,181 @@
+use solana_escrow::instruction;
+use solana_escrow::test_fixtures::{assert_escrow_error, EscrowTestEnv};
+use solana_escrow::{find_nonce_address, EscrowError, SignedOffer};
+use solana_program::clock::Clock;
+use solana_program::instruction::Instruction;
+use solana_program::pubkey::Pubkey;
+use solana_program::system_instruction;
+use solana_program_test::tokio;
+use solana_sdk::native_token::LAMPORTS_PER_SOL;
+use solana_sdk::signature::{Keypair, Signer};
+
+/// Accounts of both sides of a signed offer of Alice's tokens for Bob's ones
+struct Parties {
+    alice: Keypair,
+    alice_source: Pubkey,
+    alice_destination: Pubkey,
+    bob: Keypair,
+    bob_source: Pubkey,
+    bob_destination: Pubkey,
+}
+
+impl Parties {
+    fn fill(&self, program_id: &Pubkey, offer: &SignedOffer) -> Instruction {
+        instruction::fill_offer(
+            program_id,
+            &self.bob.pubkey(),
+            &self.bob_source,
+            &self.bob_destination,
+            &self.alice_source,
+            &self.alice_destination,
+            offer,
+        )
+    }
+
+    fn signed_fill(&self, program_id: &Pubkey, offer: &SignedOffer) -> [Instruction; 2] {
+        let signature = self.alice.sign_message(&offer.message(program_id)).into();
+        [
+            instruction::verify_offer(program_id, offer, &signature),
+            self.fill(program_id, offer),
+        ]
+    }
+}
+
+#[tokio::test]
+async fn signed_offer_test() {
+    let mut env = EscrowTestEnv::builder().build().await;
+    let alice = env.create_user(100).await;
+    let bob = env.create_user(50).await;
+    // Nonce accounts are paid by the taker, or by the maker cancelling an offer
+    let fund = [&alice, &bob].map(|user| {
+        system_instruction::transfer(
+            &env.payer.pubkey(),
+            &user.wallet_account.pubkey(),
+            LAMPORTS_PER_SOL,
+        )
+    });
+    env.process(&fund, &[])
+        .await
+        .expect("Unable to fund the parties");
+    let alice_destination = env
+        .create_token_account(&alice.wallet_account.pubkey(), &bob.mint_account.pubkey())
+        .await;
+    let bob_destination = env
+        .create_token_account(&bob.wallet_account.pubkey(), &alice.mint_account.pubkey())
+        .await;
+    let parties = Parties {
+        alice_source: alice.token_account.pubkey(),
+        alice_destination: alice_destination.pubkey(),
+        alice: alice.wallet_account,
+        bob_source: bob.token_account.pubkey(),
+        bob_destination: bob_destination.pubkey(),
+        bob: bob.wallet_account,
+    };
+    let program_id = env.program_id;
+
+    // Alice keeps her tokens, the registry may only move 60 of them
+    let ix = instruction::delegate(
+        &program_id,
+        &parties.alice.pubkey(),
+        &parties.alice_source,
+        60,
+    )
+    .unwrap();
+    env.process(&[ix], &[&parties.alice])
+        .await
+        .expect("Unable to delegate");
+
+    let clock: Clock = env.banks_client.get_sysvar().await.unwrap();
+    let offer = SignedOffer {
+        maker: parties.alice.pubkey(),
+        maker_mint: alice.mint_account.pubkey(),
+        maker_amount: 30,
+        taker_mint: bob.mint_account.pubkey(),
+        taker_amount: 20,
+        expiry: clock.unix_timestamp + 100,
+        nonce: 7,
+    };
+
+    let err = env
+        .process(&[parties.fill(&program_id, &offer)], &[&parties.bob])
+        .await
+        .unwrap_err();
+    assert_escrow_error(err, EscrowError::InvalidOfferSignature);
+
+    // The signature is valid, but for another offer
+    let [verify, _] = parties.signed_fill(&program_id, &offer);
+    let tampered = SignedOffer {
+        maker_amount: 60,
+        ..offer
+    };
+    let err = env
+        .process(
+            &[verify, parties.fill(&program_id, &tampered)],
+            &[&parties.bob],
+        )
+        .await
+        .unwrap_err();
+    assert_escrow_error(err, EscrowError::InvalidOfferSignature);
+
+    // Lamports sent to the nonce address beforehand don't block the fill
+    let (nonce_address, _) = find_nonce_address(&program_id, &offer.maker, offer.nonce);
+    let fund = system_instruction::transfer(&env.payer.pubkey(), &nonce_address, 1_000_000);
+    env.process(&[fund], &[])
+        .await
+        .expect("Unable to fund the nonce address");
+
+    env.process(&parties.signed_fill(&program_id, &offer), &[&parties.bob])
+        .await
+        .expect("Unable to fill the offer");
+    assert_eq!(env.token_account(&parties.alice_source).await.amount, 70);
+    assert_eq!(
+        env.token_account(&parties.alice_destination).await.amount,
+        20
+    );
+    assert_eq!(env.token_account(&parties.bob_source).await.amount, 30);
+    assert_eq!(env.token_account(&parties.bob_destination).await.amount, 30);
+
+    // Each nonce fills once, the new blockhash keeps the retry from being deduplicated
+    env.warp_clock(1, 1).await;
+    let err = env
+        .process(&parties.signed_fill(&program_id, &offer), &[&parties.bob])
+        .await
+        .unwrap_err();
+    assert_escrow_error(err, EscrowError::NonceAlreadyUsed);
+
+    let cancelled = SignedOffer { nonce: 8, ..offer };
+    let ix = instruction::cancel_offer(&program_id, &parties.alice.pubkey(), cancelled.nonce);
+    env.process(&[ix], &[&parties.alice])
+        .await
+        .expect("Unable to cancel the offer");
+    let err = env
+        .process(
+            &parties.signed_fill(&program_id, &cancelled),
+            &[&parties.bob],
+        )
+        .await
+        .unwrap_err();
+    assert_escrow_error(err, EscrowError::NonceAlreadyUsed);
+
+    let oversized = SignedOffer {
+        maker_amount: 40,
+        nonce: 9,
+        ..offer
+    };
+    let err = env
+        .process(
+            &parties.signed_fill(&program_id, &oversized),
+            &[&parties.bob],
+        )
+        .await
+        .unwrap_err();
+    assert_escrow_error(err, EscrowError::InsufficientDelegation);
+
+    env.warp_clock(1, 100).await;
+    let expired = SignedOffer { nonce: 9, ..offer };
+    let err = env
+        .process(&parties.signed_fill(&program_id, &expired), &[&parties.bob])
+        .await
+        .unwrap_err();
+    assert_escrow_error(err, EscrowError::OfferExpired);
+}
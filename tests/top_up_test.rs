@@ -0,0 +1,96 @@
+use solana_escrow::instruction;
+use solana_escrow::test_fixtures::EscrowTestEnv;
+use solana_program_test::{tokio, BanksClientError};
+use solana_sdk::instruction::InstructionError;
+use solana_sdk::signature::Signer;
+use solana_sdk::transaction::TransactionError;
+
+#[tokio::test]
+async fn top_up_and_withdraw_test() {
+    let mut env = EscrowTestEnv::builder().build().await;
+    let alice = env.create_user(10).await;
+    let bob = env.create_user(6).await;
+    let alice_reserve = env
+        .create_token_account(&alice.wallet_account.pubkey(), &alice.mint_account.pubkey())
+        .await;
+    env.mint_to(&alice.mint_account.pubkey(), &alice_reserve.pubkey(), 5)
+        .await;
+
+    env.deposit(&alice, &bob.mint_account.pubkey(), 5)
+        .await
+        .expect("Unable to make a deposit");
+    let deposited = env.assert_escrow(&alice.token_account.pubkey()).await;
+
+    let ix = instruction::top_up(
+        &env.program_id,
+        &alice.wallet_account.pubkey(),
+        &alice.token_account.pubkey(),
+        &alice_reserve.pubkey(),
+        5,
+        8,
+    );
+    env.process(&[ix], &[&alice.wallet_account])
+        .await
+        .expect("Unable to top up the escrow");
+    assert_eq!(
+        env.token_account(&alice.token_account.pubkey())
+            .await
+            .amount,
+        15
+    );
+    let topped_up = env.assert_escrow(&alice.token_account.pubkey()).await;
+    assert_eq!(topped_up.amount_expected, 8);
+    assert!(topped_up.sequence > deposited.sequence);
+
+    // Only the depositor takes tokens out
+    let bob_reserve = env
+        .create_token_account(&bob.wallet_account.pubkey(), &alice.mint_account.pubkey())
+        .await;
+    let ix = instruction::withdraw(
+        &env.program_id,
+        &bob.wallet_account.pubkey(),
+        &alice.token_account.pubkey(),
+        &bob_reserve.pubkey(),
+        15,
+        0,
+    );
+    match env.process(&[ix], &[&bob.wallet_account]).await {
+        Err(BanksClientError::TransactionError(TransactionError::InstructionError(
+            _,
+            InstructionError::IllegalOwner,
+        ))) => (),
+        res => panic!("Unexpected result: {:?}", res),
+    }
+
+    let ix = instruction::withdraw(
+        &env.program_id,
+        &alice.wallet_account.pubkey(),
+        &alice.token_account.pubkey(),
+        &alice_reserve.pubkey(),
+        3,
+        6,
+    );
+    env.process(&[ix], &[&alice.wallet_account])
+        .await
+        .expect("Unable to withdraw from the escrow");
+    assert_eq!(
+        env.token_account(&alice.token_account.pubkey())
+            .await
+            .amount,
+        12
+    );
+    assert_eq!(env.token_account(&alice_reserve.pubkey()).await.amount, 3);
+    env.assert_token_owner(&alice.token_account.pubkey(), &env.registry_address())
+        .await;
+
+    env.execute(
+        &bob,
+        &alice.token_account.pubkey(),
+        &alice.mint_account.pubkey(),
+        12,
+    )
+    .await
+    .expect("Unable to make an escrow execution");
+    env.assert_token_owner(&alice.token_account.pubkey(), &bob.wallet_account.pubkey())
+        .await;
+}